@@ -0,0 +1,29 @@
+#!/bin/bash
+# 使用Sub CA签发客户端证书, CN需与tbl_auth_user.username一致
+# 用法: ./build-client.sh <username>
+set -xe
+username=$1
+if [ -z "$username" ]; then
+    echo "usage: $0 <username>"
+    exit 1
+fi
+home_ca="home-ca"
+
+openssl req -nodes \
+    -newkey rsa:2048 \
+    -keyout $home_ca/private/client-$username.key \
+    -out $home_ca/csrs/client-$username.csr \
+    -sha256 \
+    -batch \
+    -subj "/CN=$username"
+
+openssl x509 -req \
+    -in $home_ca/csrs/client-$username.csr \
+    -out $home_ca/crts/client-$username.crt \
+    -CA $home_ca/crts/sub-ca.crt \
+    -CAkey $home_ca/private/sub-ca.key \
+    -sha256 \
+    -days 365 \
+    -CAcreateserial \
+    -extensions client_ext \
+    -extfile build-ca.conf
@@ -2,9 +2,18 @@
 
 pub mod prelude;
 
+pub mod tbl_api_token;
 pub mod tbl_article;
+pub mod tbl_article_answer;
+pub mod tbl_article_file;
+pub mod tbl_article_revision;
+pub mod tbl_article_tag;
 pub mod tbl_auth_user;
 pub mod tbl_file;
 pub mod tbl_log;
 pub mod tbl_pdf_article;
 pub mod tbl_pdf_article_access_log;
+pub mod tbl_pdf_article_tag;
+pub mod tbl_reflection_prompt;
+pub mod tbl_reflection_template;
+pub mod tbl_tag;
@@ -1,8 +1,17 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.12
 
+pub use super::tbl_api_token::Entity as TblApiToken;
 pub use super::tbl_article::Entity as TblArticle;
+pub use super::tbl_article_answer::Entity as TblArticleAnswer;
+pub use super::tbl_article_file::Entity as TblArticleFile;
+pub use super::tbl_article_revision::Entity as TblArticleRevision;
+pub use super::tbl_article_tag::Entity as TblArticleTag;
 pub use super::tbl_auth_user::Entity as TblAuthUser;
 pub use super::tbl_file::Entity as TblFile;
 pub use super::tbl_log::Entity as TblLog;
 pub use super::tbl_pdf_article::Entity as TblPdfArticle;
 pub use super::tbl_pdf_article_access_log::Entity as TblPdfArticleAccessLog;
+pub use super::tbl_pdf_article_tag::Entity as TblPdfArticleTag;
+pub use super::tbl_reflection_prompt::Entity as TblReflectionPrompt;
+pub use super::tbl_reflection_template::Entity as TblReflectionTemplate;
+pub use super::tbl_tag::Entity as TblTag;
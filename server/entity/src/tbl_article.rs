@@ -13,9 +13,46 @@ pub struct Model {
     pub content: String,
     pub created_at: DateTime,
     pub updated_at: DateTime,
+    pub user_id: i32,
+    pub entry_date: Option<Date>,
+    pub template_id: Option<i32>,
+    pub deleted_at: Option<DateTime>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    #[sea_orm(has_many = "super::tbl_article_answer::Entity")]
+    TblArticleAnswer,
+    #[sea_orm(has_many = "super::tbl_article_file::Entity")]
+    TblArticleFile,
+    #[sea_orm(has_many = "super::tbl_article_revision::Entity")]
+    TblArticleRevision,
+    #[sea_orm(has_many = "super::tbl_article_tag::Entity")]
+    TblArticleTag,
+}
+
+impl Related<super::tbl_article_answer::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TblArticleAnswer.def()
+    }
+}
+
+impl Related<super::tbl_article_file::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TblArticleFile.def()
+    }
+}
+
+impl Related<super::tbl_article_revision::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TblArticleRevision.def()
+    }
+}
+
+impl Related<super::tbl_article_tag::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TblArticleTag.def()
+    }
+}
 
 impl ActiveModelBehavior for ActiveModel {}
@@ -11,9 +11,27 @@ pub struct Model {
     pub username: String,
     pub password: String,
     pub created_at: DateTime,
+    pub enabled: bool,
+    pub role: String,
+    pub totp_secret: Option<String>,
+    pub totp_enabled: bool,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub totp_recovery_codes: Option<String>,
+    pub time_zone: String,
+    #[sea_orm(unique)]
+    pub oidc_subject: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    #[sea_orm(has_many = "super::tbl_api_token::Entity")]
+    TblApiToken,
+}
+
+impl Related<super::tbl_api_token::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TblApiToken.def()
+    }
+}
 
 impl ActiveModelBehavior for ActiveModel {}
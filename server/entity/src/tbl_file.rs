@@ -12,9 +12,22 @@ pub struct Model {
     #[sea_orm(column_type = "VarBinary(StringLen::None)")]
     pub content: Vec<u8>,
     pub created_at: DateTime,
+    pub deleted_at: Option<DateTime>,
+    pub content_type: Option<String>,
+    pub user_id: Option<i32>,
+    pub article_id: Option<i32>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    #[sea_orm(has_many = "super::tbl_article_file::Entity")]
+    TblArticleFile,
+}
+
+impl Related<super::tbl_article_file::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TblArticleFile.def()
+    }
+}
 
 impl ActiveModelBehavior for ActiveModel {}
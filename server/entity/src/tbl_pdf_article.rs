@@ -13,12 +13,15 @@ pub struct Model {
     pub pdf_content: Vec<u8>,
     pub created_at: DateTime,
     pub updated_at: DateTime,
+    pub deleted_at: Option<DateTime>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
     #[sea_orm(has_many = "super::tbl_pdf_article_access_log::Entity")]
     TblPdfArticleAccessLog,
+    #[sea_orm(has_many = "super::tbl_pdf_article_tag::Entity")]
+    TblPdfArticleTag,
 }
 
 impl Related<super::tbl_pdf_article_access_log::Entity> for Entity {
@@ -27,4 +30,10 @@ impl Related<super::tbl_pdf_article_access_log::Entity> for Entity {
     }
 }
 
+impl Related<super::tbl_pdf_article_tag::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TblPdfArticleTag.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
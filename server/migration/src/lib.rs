@@ -6,6 +6,23 @@ mod m20250711_022548_create_tbl_file;
 mod m20250715_014127_create_tbl_auth_user;
 mod m20250716_151156_create_tbl_pdf_article;
 mod m20250717_002329_create_tbl_pdf_article_access_log;
+mod m20261018_093000_alter_tbl_auth_user_add_enabled;
+mod m20261018_100000_alter_tbl_auth_user_add_role;
+mod m20261018_103000_alter_tbl_article_add_user_id;
+mod m20261018_110000_alter_tbl_auth_user_add_totp;
+mod m20261018_120000_create_tbl_api_token;
+mod m20261018_130000_alter_tbl_article_add_entry_date;
+mod m20261018_130500_alter_tbl_auth_user_add_time_zone;
+mod m20261018_140000_create_tbl_reflection_template;
+mod m20261018_140500_create_tbl_article_answer;
+mod m20261018_150000_create_tbl_article_fts;
+mod m20261018_160000_create_tbl_article_revision;
+mod m20261018_170000_alter_add_deleted_at;
+mod m20261018_180000_create_tbl_tag;
+mod m20261018_190000_create_tbl_article_file;
+mod m20261018_200000_alter_tbl_file_add_owner;
+mod m20261018_210000_alter_tbl_auth_user_add_oidc_subject;
+mod m20261018_220000_alter_tbl_article_revision_add_answers;
 
 pub struct Migrator;
 
@@ -19,6 +36,23 @@ impl MigratorTrait for Migrator {
             Box::new(m20250715_014127_create_tbl_auth_user::Migration),
             Box::new(m20250716_151156_create_tbl_pdf_article::Migration),
             Box::new(m20250717_002329_create_tbl_pdf_article_access_log::Migration),
+            Box::new(m20261018_093000_alter_tbl_auth_user_add_enabled::Migration),
+            Box::new(m20261018_100000_alter_tbl_auth_user_add_role::Migration),
+            Box::new(m20261018_103000_alter_tbl_article_add_user_id::Migration),
+            Box::new(m20261018_110000_alter_tbl_auth_user_add_totp::Migration),
+            Box::new(m20261018_120000_create_tbl_api_token::Migration),
+            Box::new(m20261018_130000_alter_tbl_article_add_entry_date::Migration),
+            Box::new(m20261018_130500_alter_tbl_auth_user_add_time_zone::Migration),
+            Box::new(m20261018_140000_create_tbl_reflection_template::Migration),
+            Box::new(m20261018_140500_create_tbl_article_answer::Migration),
+            Box::new(m20261018_150000_create_tbl_article_fts::Migration),
+            Box::new(m20261018_160000_create_tbl_article_revision::Migration),
+            Box::new(m20261018_170000_alter_add_deleted_at::Migration),
+            Box::new(m20261018_180000_create_tbl_tag::Migration),
+            Box::new(m20261018_190000_create_tbl_article_file::Migration),
+            Box::new(m20261018_200000_alter_tbl_file_add_owner::Migration),
+            Box::new(m20261018_210000_alter_tbl_auth_user_add_oidc_subject::Migration),
+            Box::new(m20261018_220000_alter_tbl_article_revision_add_answers::Migration),
         ]
     }
 }
@@ -0,0 +1,44 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TblAuthUser::Table)
+                    .add_column(string(TblAuthUser::Role).default("reader"))
+                    .to_owned(),
+            )
+            .await?;
+        // 已有用户此前拥有全部权限, 第一个用户作为管理员, 其余用户作为编辑
+        let db = manager.get_connection();
+        db.execute_unprepared("UPDATE tbl_auth_user SET role = 'editor'")
+            .await?;
+        db.execute_unprepared(
+            "UPDATE tbl_auth_user SET role = 'admin' WHERE id = (SELECT MIN(id) FROM tbl_auth_user)",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TblAuthUser::Table)
+                    .drop_column(TblAuthUser::Role)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum TblAuthUser {
+    Table,
+    Role,
+}
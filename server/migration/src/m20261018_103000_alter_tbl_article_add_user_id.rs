@@ -0,0 +1,60 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TblArticle::Table)
+                    .add_column(integer(TblArticle::UserId).default(0))
+                    .to_owned(),
+            )
+            .await?;
+        // 历史文章归属于第一个管理员
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "UPDATE tbl_article SET user_id = COALESCE((SELECT MIN(id) FROM tbl_auth_user WHERE role = 'admin'), 0)",
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_tbl_article_user_id")
+                    .table(TblArticle::Table)
+                    .col(TblArticle::UserId)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_tbl_article_user_id")
+                    .table(TblArticle::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TblArticle::Table)
+                    .drop_column(TblArticle::UserId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum TblArticle {
+    Table,
+    UserId,
+}
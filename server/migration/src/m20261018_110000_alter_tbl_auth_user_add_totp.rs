@@ -0,0 +1,61 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite每条ALTER TABLE只能增加一列
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TblAuthUser::Table)
+                    .add_column(string_null(TblAuthUser::TotpSecret))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TblAuthUser::Table)
+                    .add_column(boolean(TblAuthUser::TotpEnabled).default(false))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TblAuthUser::Table)
+                    .add_column(text_null(TblAuthUser::TotpRecoveryCodes))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [
+            TblAuthUser::TotpSecret,
+            TblAuthUser::TotpEnabled,
+            TblAuthUser::TotpRecoveryCodes,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(TblAuthUser::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum TblAuthUser {
+    Table,
+    TotpSecret,
+    TotpEnabled,
+    TotpRecoveryCodes,
+}
@@ -0,0 +1,69 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(TblApiToken::Table)
+                    .if_not_exists()
+                    .col(pk_auto(TblApiToken::Id))
+                    .col(integer(TblApiToken::UserId))
+                    .col(string(TblApiToken::Name))
+                    .col(string(TblApiToken::TokenPrefix))
+                    .col(string_uniq(TblApiToken::TokenHash))
+                    .col(string(TblApiToken::Scopes))
+                    .col(date_time(TblApiToken::CreatedAt).default(Expr::current_timestamp()))
+                    .col(date_time_null(TblApiToken::LastUsedAt))
+                    .col(date_time_null(TblApiToken::ExpiresAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(TblApiToken::Table, TblApiToken::UserId)
+                            .to(TblAuthUser::Table, TblAuthUser::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_tbl_api_token_user_id")
+                    .table(TblApiToken::Table)
+                    .col(TblApiToken::UserId)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TblApiToken::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum TblApiToken {
+    Table,
+    Id,
+    UserId,
+    Name,
+    TokenPrefix,
+    TokenHash,
+    Scopes,
+    CreatedAt,
+    LastUsedAt,
+    ExpiresAt,
+}
+
+#[derive(DeriveIden)]
+enum TblAuthUser {
+    Table,
+    Id,
+}
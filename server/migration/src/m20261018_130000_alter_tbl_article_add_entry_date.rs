@@ -0,0 +1,64 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 为空表示不属于某一天的普通文章
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TblArticle::Table)
+                    .add_column(date_null(TblArticle::EntryDate))
+                    .to_owned(),
+            )
+            .await?;
+        // 历史文章按创建日期(UTC)归档, 同一天有多篇时只归档最早的一篇
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "UPDATE tbl_article SET entry_date = date(created_at) WHERE id IN (SELECT MIN(id) FROM tbl_article GROUP BY user_id, date(created_at))",
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_tbl_article_user_id_entry_date")
+                    .table(TblArticle::Table)
+                    .col(TblArticle::UserId)
+                    .col(TblArticle::EntryDate)
+                    .unique()
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_tbl_article_user_id_entry_date")
+                    .table(TblArticle::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TblArticle::Table)
+                    .drop_column(TblArticle::EntryDate)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum TblArticle {
+    Table,
+    UserId,
+    EntryDate,
+}
@@ -0,0 +1,36 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // IANA时区名, 用于计算用户的"今天"
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TblAuthUser::Table)
+                    .add_column(string(TblAuthUser::TimeZone).default("UTC"))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TblAuthUser::Table)
+                    .drop_column(TblAuthUser::TimeZone)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum TblAuthUser {
+    Table,
+    TimeZone,
+}
@@ -0,0 +1,92 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(TblReflectionTemplate::Table)
+                    .if_not_exists()
+                    .col(pk_auto(TblReflectionTemplate::Id))
+                    .col(string_uniq(TblReflectionTemplate::Name))
+                    .col(
+                        date_time(TblReflectionTemplate::CreatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(TblReflectionPrompt::Table)
+                    .if_not_exists()
+                    .col(pk_auto(TblReflectionPrompt::Id))
+                    .col(integer(TblReflectionPrompt::TemplateId))
+                    .col(integer(TblReflectionPrompt::Position))
+                    .col(text(TblReflectionPrompt::Content))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(TblReflectionPrompt::Table, TblReflectionPrompt::TemplateId)
+                            .to(TblReflectionTemplate::Table, TblReflectionTemplate::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_tbl_reflection_prompt_template_id")
+                    .table(TblReflectionPrompt::Table)
+                    .col(TblReflectionPrompt::TemplateId)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+        // 内置模板: 吾日三省吾身
+        let db = manager.get_connection();
+        db.execute_unprepared("INSERT INTO tbl_reflection_template (name) VALUES ('吾日三省吾身')")
+            .await?;
+        for (position, content) in ["为人谋而不忠乎？", "与朋友交而不信乎？", "传不习乎？"]
+            .iter()
+            .enumerate()
+        {
+            db.execute_unprepared(&format!(
+                "INSERT INTO tbl_reflection_prompt (template_id, position, content) SELECT id, {position}, '{content}' FROM tbl_reflection_template WHERE name = '吾日三省吾身'"
+            ))
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TblReflectionPrompt::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(TblReflectionTemplate::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum TblReflectionTemplate {
+    Table,
+    Id,
+    Name,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum TblReflectionPrompt {
+    Table,
+    Id,
+    TemplateId,
+    Position,
+    Content,
+}
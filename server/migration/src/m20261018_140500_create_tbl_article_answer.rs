@@ -0,0 +1,101 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 按模板创建的文章记录所用模板, 为空表示普通文章
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TblArticle::Table)
+                    .add_column(integer_null(TblArticle::TemplateId))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(TblArticleAnswer::Table)
+                    .if_not_exists()
+                    .col(pk_auto(TblArticleAnswer::Id))
+                    .col(integer(TblArticleAnswer::ArticleId))
+                    .col(integer(TblArticleAnswer::PromptId))
+                    .col(text(TblArticleAnswer::Answer))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(TblArticleAnswer::Table, TblArticleAnswer::ArticleId)
+                            .to(TblArticle::Table, TblArticle::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(TblArticleAnswer::Table, TblArticleAnswer::PromptId)
+                            .to(TblReflectionPrompt::Table, TblReflectionPrompt::Id)
+                            .on_delete(ForeignKeyAction::Restrict),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_tbl_article_answer_article_id_prompt_id")
+                    .table(TblArticleAnswer::Table)
+                    .col(TblArticleAnswer::ArticleId)
+                    .col(TblArticleAnswer::PromptId)
+                    .unique()
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_tbl_article_answer_prompt_id")
+                    .table(TblArticleAnswer::Table)
+                    .col(TblArticleAnswer::PromptId)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TblArticleAnswer::Table).to_owned())
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TblArticle::Table)
+                    .drop_column(TblArticle::TemplateId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum TblArticle {
+    Table,
+    Id,
+    TemplateId,
+}
+
+#[derive(DeriveIden)]
+enum TblArticleAnswer {
+    Table,
+    Id,
+    ArticleId,
+    PromptId,
+    Answer,
+}
+
+#[derive(DeriveIden)]
+enum TblReflectionPrompt {
+    Table,
+    Id,
+}
@@ -0,0 +1,55 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        // 外部内容表, 只存索引不存正文; trigram分词不依赖空格, 中英文均可检索
+        db.execute_unprepared(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS tbl_article_fts USING fts5(title, content, content='tbl_article', content_rowid='id', tokenize='trigram')",
+        )
+        .await?;
+        // 由触发器与tbl_article保持同步
+        db.execute_unprepared(
+            "CREATE TRIGGER IF NOT EXISTS tbl_article_fts_ai AFTER INSERT ON tbl_article BEGIN
+                INSERT INTO tbl_article_fts(rowid, title, content) VALUES (new.id, new.title, new.content);
+            END",
+        )
+        .await?;
+        db.execute_unprepared(
+            "CREATE TRIGGER IF NOT EXISTS tbl_article_fts_ad AFTER DELETE ON tbl_article BEGIN
+                INSERT INTO tbl_article_fts(tbl_article_fts, rowid, title, content) VALUES ('delete', old.id, old.title, old.content);
+            END",
+        )
+        .await?;
+        db.execute_unprepared(
+            "CREATE TRIGGER IF NOT EXISTS tbl_article_fts_au AFTER UPDATE OF title, content ON tbl_article BEGIN
+                INSERT INTO tbl_article_fts(tbl_article_fts, rowid, title, content) VALUES ('delete', old.id, old.title, old.content);
+                INSERT INTO tbl_article_fts(rowid, title, content) VALUES (new.id, new.title, new.content);
+            END",
+        )
+        .await?;
+        // 为已有文章建立索引
+        db.execute_unprepared("INSERT INTO tbl_article_fts(tbl_article_fts) VALUES ('rebuild')")
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        for trigger in [
+            "tbl_article_fts_ai",
+            "tbl_article_fts_ad",
+            "tbl_article_fts_au",
+        ] {
+            db.execute_unprepared(&format!("DROP TRIGGER IF EXISTS {trigger}"))
+                .await?;
+        }
+        db.execute_unprepared("DROP TABLE IF EXISTS tbl_article_fts")
+            .await?;
+        Ok(())
+    }
+}
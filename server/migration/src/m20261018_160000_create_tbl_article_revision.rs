@@ -0,0 +1,70 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 文章每次修改前的版本, revision为文章内从1开始的版本号
+        manager
+            .create_table(
+                Table::create()
+                    .table(TblArticleRevision::Table)
+                    .if_not_exists()
+                    .col(pk_auto(TblArticleRevision::Id))
+                    .col(integer(TblArticleRevision::ArticleId))
+                    .col(integer(TblArticleRevision::Revision))
+                    .col(string(TblArticleRevision::Title))
+                    .col(text(TblArticleRevision::Content))
+                    .col(integer(TblArticleRevision::UserId))
+                    .col(
+                        date_time(TblArticleRevision::CreatedAt).default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(TblArticleRevision::Table, TblArticleRevision::ArticleId)
+                            .to(TblArticle::Table, TblArticle::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_tbl_article_revision_article_id_revision")
+                    .table(TblArticleRevision::Table)
+                    .col(TblArticleRevision::ArticleId)
+                    .col(TblArticleRevision::Revision)
+                    .unique()
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TblArticleRevision::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum TblArticleRevision {
+    Table,
+    Id,
+    ArticleId,
+    Revision,
+    Title,
+    Content,
+    UserId,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum TblArticle {
+    Table,
+    Id,
+}
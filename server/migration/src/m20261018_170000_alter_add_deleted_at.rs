@@ -0,0 +1,126 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 删除时只记录删除时间并放入回收站, 超过保留期后由清理任务物理删除
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TblArticle::Table)
+                    .add_column(date_time_null(TblArticle::DeletedAt))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TblFile::Table)
+                    .add_column(date_time_null(TblFile::DeletedAt))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TblPdfArticle::Table)
+                    .add_column(date_time_null(TblPdfArticle::DeletedAt))
+                    .to_owned(),
+            )
+            .await?;
+        // 回收站中的日记不再占用当天, 否则删除后无法重新写当天的日记
+        // sea-query不支持部分索引, 直接执行SQL
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_tbl_article_user_id_entry_date")
+                    .table(TblArticle::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "CREATE UNIQUE INDEX IF NOT EXISTS idx_tbl_article_user_id_entry_date ON tbl_article (user_id, entry_date) WHERE deleted_at IS NULL",
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_tbl_article_user_id_entry_date")
+                    .table(TblArticle::Table)
+                    .to_owned(),
+            )
+            .await?;
+        // 恢复全表唯一索引前先清空回收站
+        let db_conn = manager.get_connection();
+        for table in ["tbl_article", "tbl_file", "tbl_pdf_article"] {
+            db_conn
+                .execute_unprepared(&format!("DELETE FROM {table} WHERE deleted_at IS NOT NULL"))
+                .await?;
+        }
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_tbl_article_user_id_entry_date")
+                    .table(TblArticle::Table)
+                    .col(TblArticle::UserId)
+                    .col(TblArticle::EntryDate)
+                    .unique()
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TblPdfArticle::Table)
+                    .drop_column(TblPdfArticle::DeletedAt)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TblFile::Table)
+                    .drop_column(TblFile::DeletedAt)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TblArticle::Table)
+                    .drop_column(TblArticle::DeletedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum TblArticle {
+    Table,
+    UserId,
+    EntryDate,
+    DeletedAt,
+}
+
+#[derive(DeriveIden)]
+enum TblFile {
+    Table,
+    DeletedAt,
+}
+
+#[derive(DeriveIden)]
+enum TblPdfArticle {
+    Table,
+    DeletedAt,
+}
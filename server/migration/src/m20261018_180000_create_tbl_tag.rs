@@ -0,0 +1,160 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 标签在文章和PDF之间共用, 便于交叉查找
+        manager
+            .create_table(
+                Table::create()
+                    .table(TblTag::Table)
+                    .if_not_exists()
+                    .col(pk_auto(TblTag::Id))
+                    .col(string_uniq(TblTag::Name))
+                    .col(date_time(TblTag::CreatedAt).default(Expr::current_timestamp()))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(TblArticleTag::Table)
+                    .if_not_exists()
+                    .col(pk_auto(TblArticleTag::Id))
+                    .col(integer(TblArticleTag::ArticleId))
+                    .col(integer(TblArticleTag::TagId))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(TblArticleTag::Table, TblArticleTag::ArticleId)
+                            .to(TblArticle::Table, TblArticle::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(TblArticleTag::Table, TblArticleTag::TagId)
+                            .to(TblTag::Table, TblTag::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_tbl_article_tag_article_id_tag_id")
+                    .table(TblArticleTag::Table)
+                    .col(TblArticleTag::ArticleId)
+                    .col(TblArticleTag::TagId)
+                    .unique()
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_tbl_article_tag_tag_id")
+                    .table(TblArticleTag::Table)
+                    .col(TblArticleTag::TagId)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(TblPdfArticleTag::Table)
+                    .if_not_exists()
+                    .col(pk_auto(TblPdfArticleTag::Id))
+                    .col(integer(TblPdfArticleTag::PdfArticleId))
+                    .col(integer(TblPdfArticleTag::TagId))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(TblPdfArticleTag::Table, TblPdfArticleTag::PdfArticleId)
+                            .to(TblPdfArticle::Table, TblPdfArticle::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(TblPdfArticleTag::Table, TblPdfArticleTag::TagId)
+                            .to(TblTag::Table, TblTag::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_tbl_pdf_article_tag_pdf_article_id_tag_id")
+                    .table(TblPdfArticleTag::Table)
+                    .col(TblPdfArticleTag::PdfArticleId)
+                    .col(TblPdfArticleTag::TagId)
+                    .unique()
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_tbl_pdf_article_tag_tag_id")
+                    .table(TblPdfArticleTag::Table)
+                    .col(TblPdfArticleTag::TagId)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TblPdfArticleTag::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(TblArticleTag::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(TblTag::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum TblTag {
+    Table,
+    Id,
+    Name,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum TblArticleTag {
+    Table,
+    Id,
+    ArticleId,
+    TagId,
+}
+
+#[derive(DeriveIden)]
+enum TblPdfArticleTag {
+    Table,
+    Id,
+    PdfArticleId,
+    TagId,
+}
+
+#[derive(DeriveIden)]
+enum TblArticle {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum TblPdfArticle {
+    Table,
+    Id,
+}
@@ -0,0 +1,102 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 上传时的Content-Type, 图片等可直接在页面中显示, 历史文件为空
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TblFile::Table)
+                    .add_column(string_null(TblFile::ContentType))
+                    .to_owned(),
+            )
+            .await?;
+        // 文章的附件, 一个文件可以被多篇文章引用
+        manager
+            .create_table(
+                Table::create()
+                    .table(TblArticleFile::Table)
+                    .if_not_exists()
+                    .col(pk_auto(TblArticleFile::Id))
+                    .col(integer(TblArticleFile::ArticleId))
+                    .col(integer(TblArticleFile::FileId))
+                    .col(date_time(TblArticleFile::CreatedAt).default(Expr::current_timestamp()))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(TblArticleFile::Table, TblArticleFile::ArticleId)
+                            .to(TblArticle::Table, TblArticle::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(TblArticleFile::Table, TblArticleFile::FileId)
+                            .to(TblFile::Table, TblFile::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_tbl_article_file_article_id_file_id")
+                    .table(TblArticleFile::Table)
+                    .col(TblArticleFile::ArticleId)
+                    .col(TblArticleFile::FileId)
+                    .unique()
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_tbl_article_file_file_id")
+                    .table(TblArticleFile::Table)
+                    .col(TblArticleFile::FileId)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TblArticleFile::Table).to_owned())
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TblFile::Table)
+                    .drop_column(TblFile::ContentType)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum TblArticleFile {
+    Table,
+    Id,
+    ArticleId,
+    FileId,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum TblArticle {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum TblFile {
+    Table,
+    Id,
+    ContentType,
+}
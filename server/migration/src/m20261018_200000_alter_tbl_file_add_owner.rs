@@ -0,0 +1,72 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 上传者, 历史文件为空
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TblFile::Table)
+                    .add_column(integer_null(TblFile::UserId))
+                    .to_owned(),
+            )
+            .await?;
+        // 从文章编辑页上传时所属的文章, 为空的是文件库中的文件
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TblFile::Table)
+                    .add_column(integer_null(TblFile::ArticleId))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_tbl_file_article_id")
+                    .table(TblFile::Table)
+                    .col(TblFile::ArticleId)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_tbl_file_article_id")
+                    .table(TblFile::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TblFile::Table)
+                    .drop_column(TblFile::ArticleId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TblFile::Table)
+                    .drop_column(TblFile::UserId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum TblFile {
+    Table,
+    UserId,
+    ArticleId,
+}
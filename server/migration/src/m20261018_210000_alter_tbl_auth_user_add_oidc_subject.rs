@@ -0,0 +1,55 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 身份提供方的sub, OIDC登录只按该列匹配用户, 为空表示未关联
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TblAuthUser::Table)
+                    .add_column(string_null(TblAuthUser::OidcSubject))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_tbl_auth_user_oidc_subject")
+                    .table(TblAuthUser::Table)
+                    .col(TblAuthUser::OidcSubject)
+                    .unique()
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_tbl_auth_user_oidc_subject")
+                    .table(TblAuthUser::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TblAuthUser::Table)
+                    .drop_column(TblAuthUser::OidcSubject)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum TblAuthUser {
+    Table,
+    OidcSubject,
+}
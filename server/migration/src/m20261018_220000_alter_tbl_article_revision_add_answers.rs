@@ -0,0 +1,36 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 保存版本时文章的回答, JSON数组; 为空的是增加该列之前保存的版本
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TblArticleRevision::Table)
+                    .add_column(text_null(TblArticleRevision::Answers))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TblArticleRevision::Table)
+                    .drop_column(TblArticleRevision::Answers)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum TblArticleRevision {
+    Table,
+    Answers,
+}
@@ -0,0 +1,406 @@
+use std::str::FromStr;
+
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::{Method, StatusCode},
+    response::IntoResponse,
+    routing::{delete, get},
+};
+use entity::{tbl_api_token, tbl_auth_user};
+use sea_orm::{
+    ActiveValue::Set, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter, QueryOrder,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use validator::Validate;
+
+use crate::{
+    AppState,
+    auth::{self, ADMIN, AuthUser, READER, RequireRole, RequireSession, Role},
+    session,
+};
+
+// API token以固定前缀开头, RequireAuth据此区分登录token
+const TOKEN_PREFIX: &str = "sxp_";
+// last_used_at最多每分钟更新一次, 避免每个请求都写数据库
+const LAST_USED_UPDATE_SECS: i64 = 60;
+
+pub fn routers(state: AppState) -> Router {
+    Router::new()
+        .route("/api_tokens", get(query).post(create))
+        .route("/api_tokens/{id}", delete(revoke))
+        .route("/users/{id}/api_tokens", get(query_of_user))
+        .route("/users/{id}/api_tokens/{token_id}", delete(revoke_of_user))
+        .with_state(state)
+}
+
+/// API token的权限范围, 与用户角色共同生效, 不会超出用户角色的权限
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    // 只读, 仅允许GET请求
+    Read,
+    // 读写, 允许全部请求
+    Write,
+    // 仅允许上传pdf文章
+    PdfUpload,
+    // 仅允许上传文件
+    FileUpload,
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::Read => "read",
+            Scope::Write => "write",
+            Scope::PdfUpload => "pdf_upload",
+            Scope::FileUpload => "file_upload",
+        }
+    }
+
+    /// 判断是否允许访问接口, matched_path为完整路由模板
+    fn allows(&self, method: &Method, matched_path: &str) -> bool {
+        match self {
+            Scope::Read => method == Method::GET || method == Method::HEAD,
+            Scope::Write => true,
+            Scope::PdfUpload => method == Method::POST && matched_path == "/api/pdf_articles",
+            Scope::FileUpload => method == Method::POST && matched_path == "/api/files",
+        }
+    }
+}
+
+impl FromStr for Scope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read" => Ok(Scope::Read),
+            "write" => Ok(Scope::Write),
+            "pdf_upload" => Ok(Scope::PdfUpload),
+            "file_upload" => Ok(Scope::FileUpload),
+            _ => Err(format!("unknown scope: {s}")),
+        }
+    }
+}
+
+fn parse_scopes(scopes: &str) -> Vec<Scope> {
+    scopes
+        .split(',')
+        .filter_map(|scope| Scope::from_str(scope).ok())
+        .collect()
+}
+
+pub fn is_api_token(token: &str) -> bool {
+    token.starts_with(TOKEN_PREFIX)
+}
+
+/// 校验API token并返回所属用户, 无效返回401, 权限范围不允许返回403
+pub async fn authenticate(
+    app_state: &AppState,
+    token: &str,
+    method: &Method,
+    matched_path: &str,
+) -> Result<AuthUser, StatusCode> {
+    let tbl_api_token = match tbl_api_token::Entity::find()
+        .filter(tbl_api_token::Column::TokenHash.eq(session::sha256_hex(token)))
+        .one(&app_state.db_conn)
+        .await
+    {
+        Ok(Some(v)) => v,
+        Ok(None) => {
+            log::warn!("api token not exists");
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+        Err(e) => {
+            log::error!("tbl_api_token find err: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    let now = chrono::Utc::now().naive_utc();
+    if let Some(expires_at) = tbl_api_token.expires_at
+        && expires_at <= now
+    {
+        log::warn!("api token {} expired", tbl_api_token.id);
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    let tbl_auth_user = match tbl_auth_user::Entity::find_by_id(tbl_api_token.user_id)
+        .one(&app_state.db_conn)
+        .await
+    {
+        Ok(Some(v)) if v.enabled => v,
+        Ok(_) => {
+            log::warn!("user {} of api token not available", tbl_api_token.user_id);
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+        Err(e) => {
+            log::error!("tbl_auth_user find err: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    let role = match Role::from_str(&tbl_auth_user.role) {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("user {} role err: {}", tbl_auth_user.username, e);
+            return Err(StatusCode::FORBIDDEN);
+        }
+    };
+    if !parse_scopes(&tbl_api_token.scopes)
+        .iter()
+        .any(|scope| scope.allows(method, matched_path))
+    {
+        log::warn!(
+            "api token {} scopes {} not allow {} {}",
+            tbl_api_token.id,
+            tbl_api_token.scopes,
+            method,
+            matched_path
+        );
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let last_used_stale = tbl_api_token
+        .last_used_at
+        .is_none_or(|last_used_at| (now - last_used_at).num_seconds() >= LAST_USED_UPDATE_SECS);
+    if last_used_stale {
+        let mut tbl_api_token_am = tbl_api_token.into_active_model();
+        tbl_api_token_am.last_used_at = Set(Some(now));
+        if let Err(e) = tbl_api_token::Entity::update(tbl_api_token_am)
+            .exec(&app_state.db_conn)
+            .await
+        {
+            log::error!("tbl_api_token update last_used_at err: {}", e);
+        }
+    }
+    Ok(AuthUser {
+        id: tbl_auth_user.id,
+        username: tbl_auth_user.username,
+        role,
+    })
+}
+
+#[derive(Serialize, Debug)]
+struct QueryOutputDto {
+    id: i32,
+    name: String,
+    token_prefix: String,
+    scopes: Vec<Scope>,
+    created_at: i64,
+    last_used_at: Option<i64>,
+    expires_at: Option<i64>,
+}
+
+async fn user_api_tokens(
+    app_state: &AppState,
+    user_id: i32,
+) -> (
+    StatusCode,
+    [(&'static str, &'static str); 2],
+    Json<serde_json::Value>,
+) {
+    match tbl_api_token::Entity::find()
+        .filter(tbl_api_token::Column::UserId.eq(user_id))
+        .order_by_desc(tbl_api_token::Column::CreatedAt)
+        .all(&app_state.db_conn)
+        .await
+    {
+        Ok(tbl_api_tokens) => {
+            let api_tokens: Vec<QueryOutputDto> = tbl_api_tokens
+                .into_iter()
+                .map(|tbl_api_token| QueryOutputDto {
+                    id: tbl_api_token.id,
+                    scopes: parse_scopes(&tbl_api_token.scopes),
+                    name: tbl_api_token.name,
+                    token_prefix: tbl_api_token.token_prefix,
+                    created_at: tbl_api_token.created_at.and_utc().timestamp_millis(),
+                    last_used_at: tbl_api_token
+                        .last_used_at
+                        .map(|v| v.and_utc().timestamp_millis()),
+                    expires_at: tbl_api_token
+                        .expires_at
+                        .map(|v| v.and_utc().timestamp_millis()),
+                })
+                .collect();
+            (
+                StatusCode::OK,
+                [("code", "200"), ("msg", "ok")],
+                Json(json!({
+                    "_embedded": {
+                        "api_token": api_tokens
+                    }
+                })),
+            )
+        }
+        Err(e) => {
+            log::error!("tbl_api_token find err: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_api_token find err")],
+                Json(json!({})),
+            )
+        }
+    }
+}
+
+async fn query(
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<READER>,
+    _: RequireSession,
+) -> impl IntoResponse {
+    user_api_tokens(&app_state, auth_user.id).await
+}
+
+#[derive(Deserialize, Debug, Validate)]
+struct CreateInputDto {
+    #[validate(length(min = 1, max = 64))]
+    name: String,
+    #[validate(length(min = 1))]
+    scopes: Vec<Scope>,
+    #[validate(range(min = 1, max = 3650))]
+    expires_in_days: Option<i64>,
+}
+/// 创建API token, 明文token只在创建时返回一次, 数据库只保存hash
+async fn create(
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<READER>,
+    _: RequireSession,
+    Json(create_input_dto): Json<CreateInputDto>,
+) -> impl IntoResponse {
+    if let Err(e) = create_input_dto.validate() {
+        log::warn!("create api token input invalid: {}", e);
+        return (
+            StatusCode::BAD_REQUEST,
+            [("code", "400"), ("msg", "invalid input")],
+            Json(json!({})),
+        );
+    }
+    auth::insert_log(
+        &app_state,
+        format!(
+            "{} create api token {} scopes: {:?}",
+            auth_user.username, create_input_dto.name, create_input_dto.scopes
+        ),
+    )
+    .await;
+    let token = format!(
+        "{TOKEN_PREFIX}{}",
+        session::to_hex(&rand::random::<[u8; 32]>())
+    );
+    let mut scopes: Vec<&str> = create_input_dto.scopes.iter().map(Scope::as_str).collect();
+    scopes.sort();
+    scopes.dedup();
+    let tbl_api_token_am = tbl_api_token::ActiveModel {
+        user_id: Set(auth_user.id),
+        name: Set(create_input_dto.name),
+        token_prefix: Set(token.chars().take(TOKEN_PREFIX.len() + 8).collect()),
+        token_hash: Set(session::sha256_hex(&token)),
+        scopes: Set(scopes.join(",")),
+        expires_at: Set(create_input_dto
+            .expires_in_days
+            .map(|days| (chrono::Utc::now() + chrono::Duration::days(days)).naive_utc())),
+        ..Default::default()
+    };
+    match tbl_api_token::Entity::insert(tbl_api_token_am)
+        .exec(&app_state.db_conn)
+        .await
+    {
+        Ok(insert_result) => (
+            StatusCode::OK,
+            [("code", "200"), ("msg", "ok")],
+            Json(json!({
+                "id": insert_result.last_insert_id,
+                "token": token
+            })),
+        ),
+        Err(e) => {
+            log::error!("tbl_api_token insert err: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_api_token insert err")],
+                Json(json!({})),
+            )
+        }
+    }
+}
+
+async fn revoke_user_api_token(
+    app_state: &AppState,
+    user_id: i32,
+    id: i32,
+) -> (
+    StatusCode,
+    [(&'static str, &'static str); 2],
+    Json<serde_json::Value>,
+) {
+    match tbl_api_token::Entity::delete_by_id(id)
+        .filter(tbl_api_token::Column::UserId.eq(user_id))
+        .exec(&app_state.db_conn)
+        .await
+    {
+        Ok(delete_result) if delete_result.rows_affected == 0 => {
+            log::warn!("api token {} of user {} not exists", id, user_id);
+            (
+                StatusCode::NOT_FOUND,
+                [("code", "404"), ("msg", "api token not exists")],
+                Json(json!({})),
+            )
+        }
+        Ok(_) => (
+            StatusCode::OK,
+            [("code", "200"), ("msg", "ok")],
+            Json(json!({})),
+        ),
+        Err(e) => {
+            log::error!("tbl_api_token delete {id} err: {e}");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "delete db err")],
+                Json(json!({})),
+            )
+        }
+    }
+}
+
+async fn revoke(
+    Path(id): Path<i32>,
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<READER>,
+    _: RequireSession,
+) -> impl IntoResponse {
+    auth::insert_log(
+        &app_state,
+        format!("{} revoke api token {}", auth_user.username, id),
+    )
+    .await;
+    revoke_user_api_token(&app_state, auth_user.id, id).await
+}
+
+async fn query_of_user(
+    Path(id): Path<i32>,
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<ADMIN>,
+    _: RequireSession,
+) -> impl IntoResponse {
+    log::info!(
+        "admin {} query api tokens of user {}",
+        auth_user.username,
+        id
+    );
+    user_api_tokens(&app_state, id).await
+}
+
+async fn revoke_of_user(
+    Path((id, token_id)): Path<(i32, i32)>,
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<ADMIN>,
+    _: RequireSession,
+) -> impl IntoResponse {
+    auth::insert_log(
+        &app_state,
+        format!(
+            "{} revoke api token {} of user {}",
+            auth_user.username, token_id, id
+        ),
+    )
+    .await;
+    revoke_user_api_token(&app_state, id, token_id).await
+}
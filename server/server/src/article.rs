@@ -1,15 +1,24 @@
-use crate::AppState;
+use crate::{
+    AppState, article_revision, attachment,
+    auth::{self, AuthUser, EDITOR, READER, RequireRole, Role, SessionId},
+    config::SERVER_TOML,
+    file, markdown,
+    reflection::{self, AnswerInputDto, TemplateContentErr},
+    tag::{self, TagMode},
+    user,
+};
 use axum::{
-    Json, Router,
+    Extension, Json, Router,
     extract::{Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
-    routing::{get, patch},
+    routing::get,
 };
+use chrono::{Datelike, Months, NaiveDate};
 use entity::{tbl_article, tbl_log};
 use sea_orm::{
-    ActiveValue::Set, ColumnTrait, EntityTrait, IntoActiveModel, PaginatorTrait, QueryFilter,
-    QueryOrder,
+    ActiveValue::Set, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, IntoActiveModel,
+    PaginatorTrait, QueryFilter, QueryOrder, SqlErr, TransactionTrait, sea_query::Expr,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::json;
@@ -18,14 +27,52 @@ use validator::Validate;
 pub fn routers(state: AppState) -> Router {
     Router::new()
         .route("/articles", get(query).post(create))
-        .route("/articles/{id}", patch(update).delete(delete))
+        .route("/articles/today", get(today).put(upsert_today))
+        .route("/articles/dates/{date}", get(by_date))
+        .route("/articles/calendar", get(calendar))
+        .route("/articles/{id}", get(detail).patch(update).delete(delete))
         .with_state(state)
 }
 
+/// 文章日期的SQL表达式, 优先取entry_date, 普通文章取创建日期
+pub const ARTICLE_DATE: &str = "COALESCE(tbl_article.entry_date, date(tbl_article.created_at))";
+
+/// 管理员通过all_users=true查看或操作全部用户的文章, 用于运维支持
+#[derive(Deserialize, Debug)]
+struct ScopeInputDto {
+    #[serde(default)]
+    all_users: bool,
+}
+
+/// 返回需要过滤的user_id, None表示不过滤
+fn owner_scope(auth_user: &AuthUser, all_users: bool) -> Result<Option<i32>, StatusCode> {
+    if !all_users {
+        return Ok(Some(auth_user.id));
+    }
+    if auth_user.role == Role::Admin {
+        log::info!("admin {} access all users articles", auth_user.username);
+        Ok(None)
+    } else {
+        log::warn!(
+            "user {} not allowed to access all users",
+            auth_user.username
+        );
+        Err(StatusCode::FORBIDDEN)
+    }
+}
+
 #[derive(Deserialize, Debug, Validate)]
 struct QueryInputDto {
     title: Option<String>,
     content: Option<String>,
+    // 逗号分隔的标签
+    tags: Option<String>,
+    #[serde(default)]
+    tag_mode: TagMode,
+    #[serde(default)]
+    all_users: bool,
+    // 正文摘要的字符数, 为空时使用配置值
+    preview_length: Option<usize>,
     size: u64,
     page: u64,
 }
@@ -33,17 +80,25 @@ struct QueryInputDto {
 #[derive(Serialize, Debug)]
 struct QueryOutputDto {
     id: i32,
+    user_id: i32,
     title: String,
-    content: String,
+    // 去掉Markdown语法后的纯文本摘要
+    preview: String,
+    entry_date: Option<String>,
+    tags: Vec<String>,
     created_at: i64,
     updated_at: i64,
 }
 async fn query(
     app_state: State<AppState>,
+    RequireRole(auth_user): RequireRole<READER>,
     Query(query_input_dto): Query<QueryInputDto>,
 ) -> impl IntoResponse {
     let tbl_log_am = tbl_log::ActiveModel {
-        content: Set(format!("query by {:?}", query_input_dto)),
+        content: Set(format!(
+            "{} query by {:?}",
+            auth_user.username, query_input_dto
+        )),
         ..Default::default()
     };
     if let Err(e) = tbl_log::Entity::insert(tbl_log_am)
@@ -52,17 +107,36 @@ async fn query(
     {
         log::error!("tbl_log insert err: {}", e);
     }
-    let mut select = tbl_article::Entity::find();
-    if let Some(title) = query_input_dto.title {
-        if !title.is_empty() {
-            let like_pattern = format!("%{title}%");
-            select = select.filter(tbl_article::Column::Title.like(like_pattern));
+    let mut select = tbl_article::Entity::find().filter(tbl_article::Column::DeletedAt.is_null());
+    match owner_scope(&auth_user, query_input_dto.all_users) {
+        Ok(Some(user_id)) => {
+            select = select.filter(tbl_article::Column::UserId.eq(user_id));
+        }
+        Ok(None) => {}
+        Err(status_code) => {
+            return (
+                status_code,
+                [("code", "403"), ("msg", "FORBIDDEN")],
+                Json(json!({})),
+            );
         }
     }
-    if let Some(content) = query_input_dto.content {
-        if !content.is_empty() {
-            let like_pattern = format!("%{content}%");
-            select = select.filter(tbl_article::Column::Content.like(like_pattern));
+    if let Some(title) = query_input_dto.title
+        && !title.is_empty()
+    {
+        let like_pattern = format!("%{title}%");
+        select = select.filter(tbl_article::Column::Title.like(like_pattern));
+    }
+    if let Some(content) = query_input_dto.content
+        && !content.is_empty()
+    {
+        let like_pattern = format!("%{content}%");
+        select = select.filter(tbl_article::Column::Content.like(like_pattern));
+    }
+    if let Some(tags) = &query_input_dto.tags {
+        let tags = tag::parse_tags(tags);
+        if !tags.is_empty() {
+            select = select.filter(tag::article_filter(&tags, query_input_dto.tag_mode));
         }
     }
     let paginator = select
@@ -110,12 +184,31 @@ async fn query(
             );
         }
     };
+    let article_ids: Vec<i32> = tbl_articles.iter().map(|v| v.id).collect();
+    let mut article_tags = match tag::article_tags(&app_state.db_conn, &article_ids).await {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("tbl_article_tag find err: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_article_tag find err")],
+                Json(json!({})),
+            );
+        }
+    };
+    let preview_length = query_input_dto
+        .preview_length
+        .unwrap_or(SERVER_TOML.article.preview_length)
+        .clamp(1, 1000);
     let mut articles = Vec::new();
     for tbl_article in tbl_articles {
         articles.push(QueryOutputDto {
             id: tbl_article.id,
+            user_id: tbl_article.user_id,
             title: tbl_article.title.chars().take(10).collect(),
-            content: tbl_article.content.chars().take(10).collect(),
+            preview: markdown::plain_text_excerpt(&tbl_article.content, preview_length),
+            entry_date: tbl_article.entry_date.map(|v| v.to_string()),
+            tags: article_tags.remove(&tbl_article.id).unwrap_or_default(),
             created_at: tbl_article.created_at.and_utc().timestamp_millis(),
             updated_at: tbl_article.updated_at.and_utc().timestamp_millis(),
         });
@@ -138,17 +231,119 @@ async fn query(
     )
 }
 
+#[derive(Serialize, Debug)]
+struct DetailOutputDto {
+    id: i32,
+    user_id: i32,
+    title: String,
+    // Markdown原文
+    content: String,
+    // 渲染并清理过的HTML, 可直接插入页面
+    html: String,
+    entry_date: Option<String>,
+    template_id: Option<i32>,
+    tags: Vec<String>,
+    created_at: i64,
+    updated_at: i64,
+}
+async fn detail(
+    Path(id): Path<i32>,
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<READER>,
+    session_id: Option<Extension<SessionId>>,
+    Query(scope_input_dto): Query<ScopeInputDto>,
+) -> impl IntoResponse {
+    let mut select =
+        tbl_article::Entity::find_by_id(id).filter(tbl_article::Column::DeletedAt.is_null());
+    match owner_scope(&auth_user, scope_input_dto.all_users) {
+        Ok(Some(user_id)) => {
+            select = select.filter(tbl_article::Column::UserId.eq(user_id));
+        }
+        Ok(None) => {}
+        Err(status_code) => {
+            return (
+                status_code,
+                [("code", "403"), ("msg", "FORBIDDEN")],
+                Json(json!({})),
+            );
+        }
+    }
+    let tbl_article = match select.one(&app_state.db_conn).await {
+        Ok(Some(v)) => v,
+        Ok(None) => {
+            log::warn!("article {id} not found");
+            return (
+                StatusCode::NOT_FOUND,
+                [("code", "404"), ("msg", "not found")],
+                Json(json!({})),
+            );
+        }
+        Err(e) => {
+            log::error!("find by id {id} err: {e}");
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_article find err")],
+                Json(json!({})),
+            );
+        }
+    };
+    let tags = match tag::article_tags(&app_state.db_conn, &[id]).await {
+        Ok(mut v) => v.remove(&id).unwrap_or_default(),
+        Err(e) => {
+            log::error!("tbl_article_tag find err: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_article_tag find err")],
+                Json(json!({})),
+            );
+        }
+    };
+    let detail_output_dto = DetailOutputDto {
+        id: tbl_article.id,
+        user_id: tbl_article.user_id,
+        title: tbl_article.title,
+        html: markdown::render_html(&tbl_article.content, |file_id| {
+            file::file_url(&auth_user, session_id.as_ref().map(|v| &v.0), file_id)
+        }),
+        content: tbl_article.content,
+        entry_date: tbl_article.entry_date.map(|v| v.to_string()),
+        template_id: tbl_article.template_id,
+        tags,
+        created_at: tbl_article.created_at.and_utc().timestamp_millis(),
+        updated_at: tbl_article.updated_at.and_utc().timestamp_millis(),
+    };
+    (
+        StatusCode::OK,
+        [("code", "200"), ("msg", "ok")],
+        Json(json!(detail_output_dto)),
+    )
+}
+
 #[derive(Deserialize, Debug, Validate)]
 struct CreateInputDto {
-    title: String,
+    // 按模板创建时为空则使用模板名
+    title: Option<String>,
+    #[serde(default)]
     content: String,
+    // 每个用户每天只能有一篇, 为空时为普通文章
+    entry_date: Option<NaiveDate>,
+    // 按模板创建时填写各问题的回答
+    template_id: Option<i32>,
+    #[serde(default)]
+    answers: Vec<AnswerInputDto>,
+    #[serde(default)]
+    tags: Vec<String>,
 }
 async fn create(
     app_state: State<AppState>,
+    RequireRole(auth_user): RequireRole<EDITOR>,
     Json(create_input_dto): Json<CreateInputDto>,
 ) -> impl IntoResponse {
     let tbl_log_am = tbl_log::ActiveModel {
-        content: Set(format!("create by {:?}", create_input_dto)),
+        content: Set(format!(
+            "{} create by {:?}",
+            auth_user.username, create_input_dto
+        )),
         ..Default::default()
     };
     if let Err(e) = tbl_log::Entity::insert(tbl_log_am)
@@ -157,23 +352,127 @@ async fn create(
     {
         log::error!("tbl_log insert err: {}", e);
     }
+    if let Some(entry_date) = create_input_dto.entry_date {
+        match find_entry(&app_state.db_conn, auth_user.id, entry_date).await {
+            Ok(None) => {}
+            Ok(Some(_)) => {
+                log::warn!("user {} entry {} exists", auth_user.username, entry_date);
+                return (
+                    StatusCode::CONFLICT,
+                    [("code", "409"), ("msg", "entry exists")],
+                    Json(json!({})),
+                );
+            }
+            Err(e) => {
+                log::error!("tbl_article find err: {}", e);
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    [("code", "500"), ("msg", "tbl_article find err")],
+                    Json(json!({})),
+                );
+            }
+        }
+    }
+    let mut title = create_input_dto.title;
+    let mut content = create_input_dto.content;
+    if let Some(template_id) = create_input_dto.template_id {
+        let (template, prompts) =
+            match reflection::template_prompts(&app_state.db_conn, template_id).await {
+                Ok(Some(v)) => v,
+                Ok(None) => {
+                    log::warn!("reflection template {} not found", template_id);
+                    return (
+                        StatusCode::BAD_REQUEST,
+                        [("code", "400"), ("msg", "template not found")],
+                        Json(json!({})),
+                    );
+                }
+                Err(e) => {
+                    log::error!("tbl_reflection_template find err: {}", e);
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        [("code", "500"), ("msg", "tbl_reflection_template find err")],
+                        Json(json!({})),
+                    );
+                }
+            };
+        if let Err(e) = reflection::validate_answers(&prompts, &create_input_dto.answers) {
+            log::warn!("create answers invalid: {}", e);
+            return (
+                StatusCode::BAD_REQUEST,
+                [("code", "400"), ("msg", "invalid answers")],
+                Json(json!({})),
+            );
+        }
+        title.get_or_insert(template.name);
+        content = reflection::compose_content(
+            &reflection::render_content(&prompts, &create_input_dto.answers),
+            &content,
+        );
+    } else if !create_input_dto.answers.is_empty() {
+        log::warn!("create answers without template");
+        return (
+            StatusCode::BAD_REQUEST,
+            [("code", "400"), ("msg", "template required")],
+            Json(json!({})),
+        );
+    }
+    let Some(title) = title else {
+        log::warn!("create article without title");
+        return (
+            StatusCode::BAD_REQUEST,
+            [("code", "400"), ("msg", "title required")],
+            Json(json!({})),
+        );
+    };
+    let tags = match tag::normalize_tags(&create_input_dto.tags) {
+        Ok(v) => v,
+        Err(e) => {
+            log::warn!("create tags invalid: {}", e);
+            return (
+                StatusCode::BAD_REQUEST,
+                [("code", "400"), ("msg", "invalid tags")],
+                Json(json!({})),
+            );
+        }
+    };
+    let file_ids = markdown::file_references(&content);
     let tbl_article_am = tbl_article::ActiveModel {
-        title: Set(create_input_dto.title),
-        content: Set(create_input_dto.content),
+        title: Set(title),
+        content: Set(content),
+        user_id: Set(auth_user.id),
+        entry_date: Set(create_input_dto.entry_date),
+        template_id: Set(create_input_dto.template_id),
         ..Default::default()
     };
-    match tbl_article::Entity::insert(tbl_article_am)
-        .exec(&app_state.db_conn)
-        .await
-    {
-        Ok(insert_result) => {
-            let artile_id = insert_result.last_insert_id;
+    let result = async {
+        let txn = app_state.db_conn.begin().await?;
+        let artile_id = tbl_article::Entity::insert(tbl_article_am)
+            .exec(&txn)
+            .await?
+            .last_insert_id;
+        reflection::replace_answers(&txn, artile_id, &create_input_dto.answers).await?;
+        tag::replace_article_tags(&txn, artile_id, &tags).await?;
+        attachment::link_files(&txn, artile_id, auth_user.id, &file_ids).await?;
+        txn.commit().await?;
+        Ok::<_, DbErr>(artile_id)
+    }
+    .await;
+    match result {
+        Ok(artile_id) => (
+            StatusCode::OK,
+            [("code", "200"), ("msg", "ok")],
+            Json(json!({
+                "artile_id":artile_id
+            })),
+        ),
+        // 并发创建同一天的文章时由唯一索引拦截
+        Err(e) if is_unique_violation(&e) => {
+            log::warn!("tbl_article insert conflict: {}", e);
             (
-                StatusCode::OK,
-                [("code", "200"), ("msg", "ok")],
-                Json(json!({
-                    "artile_id":artile_id
-                })),
+                StatusCode::CONFLICT,
+                [("code", "409"), ("msg", "entry exists")],
+                Json(json!({})),
             )
         }
         Err(e) => {
@@ -190,23 +489,35 @@ async fn create(
 #[derive(Deserialize, Debug, Validate)]
 struct UpdateInputDto {
     title: Option<String>,
+    // 模板文章为回答之后额外填写的正文
     content: Option<String>,
+    // 模板文章替换全部回答, 正文随之重新生成
+    answers: Option<Vec<AnswerInputDto>>,
+    // 替换全部标签, 为空时不修改
+    tags: Option<Vec<String>>,
 }
 #[derive(Serialize, Debug)]
 struct UpdateOutputDto {
     id: i32,
     title: String,
     content: String,
+    entry_date: Option<String>,
+    tags: Vec<String>,
     created_at: i64,
     updated_at: i64,
 }
 async fn update(
     Path(id): Path<i32>,
     State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<EDITOR>,
+    Query(scope_input_dto): Query<ScopeInputDto>,
     Json(update_input_dto): Json<UpdateInputDto>,
 ) -> impl IntoResponse {
     let tbl_log_am = tbl_log::ActiveModel {
-        content: Set(format!("update {} by {:?}", id, update_input_dto)),
+        content: Set(format!(
+            "{} update {} by {:?}",
+            auth_user.username, id, update_input_dto
+        )),
         ..Default::default()
     };
     if let Err(e) = tbl_log::Entity::insert(tbl_log_am)
@@ -215,10 +526,22 @@ async fn update(
     {
         log::error!("tbl_log insert err: {}", e);
     }
-    let tbl_article = match tbl_article::Entity::find_by_id(id)
-        .one(&app_state.db_conn)
-        .await
-    {
+    let mut select =
+        tbl_article::Entity::find_by_id(id).filter(tbl_article::Column::DeletedAt.is_null());
+    match owner_scope(&auth_user, scope_input_dto.all_users) {
+        Ok(Some(user_id)) => {
+            select = select.filter(tbl_article::Column::UserId.eq(user_id));
+        }
+        Ok(None) => {}
+        Err(status_code) => {
+            return (
+                status_code,
+                [("code", "403"), ("msg", "FORBIDDEN")],
+                Json(json!({})),
+            );
+        }
+    }
+    let tbl_article = match select.one(&app_state.db_conn).await {
         Ok(tbl_article_opt) => match tbl_article_opt {
             Some(model) => model,
             None => {
@@ -239,23 +562,94 @@ async fn update(
             );
         }
     };
+    let mut content = update_input_dto.content;
+    let answers = update_input_dto.answers;
+    if let Some(template_id) = tbl_article.template_id
+        && (answers.is_some() || content.is_some())
+    {
+        match reflection::template_content(
+            &app_state.db_conn,
+            &tbl_article,
+            template_id,
+            answers.as_deref(),
+            content.as_deref(),
+        )
+        .await
+        {
+            Ok(v) => content = Some(v),
+            Err(e) => return template_content_err_response(e),
+        }
+    } else if answers.is_some() {
+        log::warn!("article {id} not created from template");
+        return (
+            StatusCode::BAD_REQUEST,
+            [("code", "400"), ("msg", "template required")],
+            Json(json!({})),
+        );
+    }
+    let tags = match update_input_dto.tags.as_deref().map(tag::normalize_tags) {
+        Some(Ok(v)) => Some(v),
+        Some(Err(e)) => {
+            log::warn!("update tags invalid: {}", e);
+            return (
+                StatusCode::BAD_REQUEST,
+                [("code", "400"), ("msg", "invalid tags")],
+                Json(json!({})),
+            );
+        }
+        None => None,
+    };
+    let previous = tbl_article.clone();
     let mut tbl_article_am = tbl_article.into_active_model();
-    if let Some(title) = update_input_dto.title {
-        tbl_article_am.title = Set(title);
+    if let Some(title) = &update_input_dto.title {
+        tbl_article_am.title = Set(title.clone());
     }
-    if let Some(content) = update_input_dto.content {
-        tbl_article_am.content = Set(content);
+    if let Some(content) = &content {
+        tbl_article_am.content = Set(content.clone());
     }
     tbl_article_am.updated_at = Set(chrono::Utc::now().naive_utc());
-    match tbl_article::Entity::update(tbl_article_am)
-        .exec(&app_state.db_conn)
-        .await
-    {
-        Ok(model) => {
+    let result = async {
+        let txn = app_state.db_conn.begin().await?;
+        article_revision::save_revision(
+            &txn,
+            &previous,
+            update_input_dto.title.as_deref(),
+            content.as_deref(),
+            auth_user.id,
+        )
+        .await?;
+        let model = tbl_article::Entity::update(tbl_article_am)
+            .exec(&txn)
+            .await?;
+        if let Some(answers) = &answers {
+            reflection::replace_answers(&txn, id, answers).await?;
+        }
+        if let Some(tags) = &tags {
+            tag::replace_article_tags(&txn, id, tags).await?;
+        }
+        attachment::link_files(
+            &txn,
+            id,
+            auth_user.id,
+            &markdown::file_references(&model.content),
+        )
+        .await?;
+        let tags = tag::article_tags(&txn, &[id])
+            .await?
+            .remove(&id)
+            .unwrap_or_default();
+        txn.commit().await?;
+        Ok::<_, DbErr>((model, tags))
+    }
+    .await;
+    match result {
+        Ok((model, tags)) => {
             let update_output_dto = UpdateOutputDto {
                 id,
                 title: model.title,
                 content: model.content,
+                entry_date: model.entry_date.map(|v| v.to_string()),
+                tags,
                 created_at: model.created_at.and_utc().timestamp_millis(),
                 updated_at: model.updated_at.and_utc().timestamp_millis(),
             };
@@ -287,9 +681,14 @@ async fn update(
     }
 }
 
-async fn delete(Path(id): Path<i32>, State(app_state): State<AppState>) -> impl IntoResponse {
+async fn delete(
+    Path(id): Path<i32>,
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<EDITOR>,
+    Query(scope_input_dto): Query<ScopeInputDto>,
+) -> impl IntoResponse {
     let tbl_log_am = tbl_log::ActiveModel {
-        content: Set(format!("delete by {}", id)),
+        content: Set(format!("{} delete by {}", auth_user.username, id)),
         ..Default::default()
     };
     if let Err(e) = tbl_log::Entity::insert(tbl_log_am)
@@ -298,10 +697,37 @@ async fn delete(Path(id): Path<i32>, State(app_state): State<AppState>) -> impl
     {
         log::error!("tbl_log insert err: {}", e);
     }
-    match tbl_article::Entity::delete_by_id(id)
-        .exec(&app_state.db_conn)
-        .await
-    {
+    // 只标记删除时间放入回收站, 由trash模块恢复或到期清理
+    let deleted_at = chrono::Utc::now().naive_utc();
+    let mut delete = tbl_article::Entity::update_many()
+        .col_expr(tbl_article::Column::DeletedAt, Expr::value(deleted_at))
+        .filter(tbl_article::Column::Id.eq(id))
+        .filter(tbl_article::Column::DeletedAt.is_null());
+    match owner_scope(&auth_user, scope_input_dto.all_users) {
+        Ok(Some(user_id)) => {
+            delete = delete.filter(tbl_article::Column::UserId.eq(user_id));
+        }
+        Ok(None) => {}
+        Err(status_code) => {
+            return (
+                status_code,
+                [("code", "403"), ("msg", "FORBIDDEN")],
+                Json(json!({})),
+            );
+        }
+    }
+    let result = async {
+        let txn = app_state.db_conn.begin().await?;
+        let delete_result = delete.exec(&txn).await?;
+        if delete_result.rows_affected == 1 && SERVER_TOML.article.trash_orphan_files {
+            let count = attachment::trash_orphan_files(&txn, id, deleted_at).await?;
+            log::info!("article {id} delete trash {count} files");
+        }
+        txn.commit().await?;
+        Ok::<_, DbErr>(delete_result)
+    }
+    .await;
+    match result {
         Ok(delete_result) => {
             if delete_result.rows_affected == 1 {
                 log::info!("delete {id} success");
@@ -327,3 +753,358 @@ async fn delete(Path(id): Path<i32>, State(app_state): State<AppState>) -> impl
         }
     }
 }
+
+pub fn is_unique_violation(e: &DbErr) -> bool {
+    matches!(e.sql_err(), Some(SqlErr::UniqueConstraintViolation(_)))
+}
+
+async fn find_entry(
+    db_conn: &DatabaseConnection,
+    user_id: i32,
+    entry_date: NaiveDate,
+) -> Result<Option<tbl_article::Model>, DbErr> {
+    tbl_article::Entity::find()
+        .filter(tbl_article::Column::UserId.eq(user_id))
+        .filter(tbl_article::Column::EntryDate.eq(entry_date))
+        .filter(tbl_article::Column::DeletedAt.is_null())
+        .one(db_conn)
+        .await
+}
+
+/// 用户所在时区的今天
+async fn user_today(db_conn: &DatabaseConnection, user_id: i32) -> Result<NaiveDate, DbErr> {
+    let time_zone = user::user_time_zone(db_conn, user_id).await?;
+    Ok(chrono::Utc::now().with_timezone(&time_zone).date_naive())
+}
+
+#[derive(Serialize, Debug)]
+struct EntryOutputDto {
+    id: i32,
+    entry_date: String,
+    title: String,
+    content: String,
+    created_at: i64,
+    updated_at: i64,
+}
+
+impl From<tbl_article::Model> for EntryOutputDto {
+    fn from(model: tbl_article::Model) -> Self {
+        Self {
+            id: model.id,
+            entry_date: model.entry_date.map(|v| v.to_string()).unwrap_or_default(),
+            title: model.title,
+            content: model.content,
+            created_at: model.created_at.and_utc().timestamp_millis(),
+            updated_at: model.updated_at.and_utc().timestamp_millis(),
+        }
+    }
+}
+
+async fn entry_response(
+    db_conn: &DatabaseConnection,
+    user_id: i32,
+    entry_date: NaiveDate,
+) -> (
+    StatusCode,
+    [(&'static str, &'static str); 2],
+    Json<serde_json::Value>,
+) {
+    match find_entry(db_conn, user_id, entry_date).await {
+        Ok(Some(model)) => (
+            StatusCode::OK,
+            [("code", "200"), ("msg", "ok")],
+            Json(json!(EntryOutputDto::from(model))),
+        ),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            [("code", "404"), ("msg", "not found")],
+            Json(json!({
+                "entry_date": entry_date.to_string()
+            })),
+        ),
+        Err(e) => {
+            log::error!("tbl_article find err: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_article find err")],
+                Json(json!({})),
+            )
+        }
+    }
+}
+
+async fn today(
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<READER>,
+) -> impl IntoResponse {
+    let entry_date = match user_today(&app_state.db_conn, auth_user.id).await {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("user {} today err: {}", auth_user.id, e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_auth_user find err")],
+                Json(json!({})),
+            );
+        }
+    };
+    entry_response(&app_state.db_conn, auth_user.id, entry_date).await
+}
+
+async fn by_date(
+    Path(entry_date): Path<NaiveDate>,
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<READER>,
+) -> impl IntoResponse {
+    entry_response(&app_state.db_conn, auth_user.id, entry_date).await
+}
+
+fn template_content_err_response(
+    e: TemplateContentErr,
+) -> (
+    StatusCode,
+    [(&'static str, &'static str); 2],
+    Json<serde_json::Value>,
+) {
+    match e {
+        TemplateContentErr::TemplateNotFound => {
+            log::warn!("reflection template not found");
+            (
+                StatusCode::BAD_REQUEST,
+                [("code", "400"), ("msg", "template not found")],
+                Json(json!({})),
+            )
+        }
+        TemplateContentErr::InvalidAnswers(e) => {
+            log::warn!("answers invalid: {}", e);
+            (
+                StatusCode::BAD_REQUEST,
+                [("code", "400"), ("msg", "invalid answers")],
+                Json(json!({})),
+            )
+        }
+        TemplateContentErr::Db(e) => {
+            log::error!("template content err: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "template content err")],
+                Json(json!({})),
+            )
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct UpsertTodayInputDto {
+    title: Option<String>,
+    content: String,
+}
+async fn upsert_today(
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<EDITOR>,
+    Json(upsert_today_input_dto): Json<UpsertTodayInputDto>,
+) -> impl IntoResponse {
+    let entry_date = match user_today(&app_state.db_conn, auth_user.id).await {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("user {} today err: {}", auth_user.id, e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_auth_user find err")],
+                Json(json!({})),
+            );
+        }
+    };
+    auth::insert_log(
+        &app_state,
+        format!(
+            "{} upsert entry {} by {:?}",
+            auth_user.username, entry_date, upsert_today_input_dto
+        ),
+    )
+    .await;
+    let result = match find_entry(&app_state.db_conn, auth_user.id, entry_date).await {
+        Ok(Some(model)) => {
+            // 模板文章只替换回答之后的额外正文, 回答保持不变
+            let content = match model.template_id {
+                Some(template_id) => match reflection::template_content(
+                    &app_state.db_conn,
+                    &model,
+                    template_id,
+                    None,
+                    Some(&upsert_today_input_dto.content),
+                )
+                .await
+                {
+                    Ok(v) => v,
+                    Err(e) => return template_content_err_response(e),
+                },
+                None => upsert_today_input_dto.content.clone(),
+            };
+            let file_ids = markdown::file_references(&content);
+            let previous = model.clone();
+            let mut tbl_article_am = model.into_active_model();
+            if let Some(title) = &upsert_today_input_dto.title {
+                tbl_article_am.title = Set(title.clone());
+            }
+            tbl_article_am.content = Set(content.clone());
+            tbl_article_am.updated_at = Set(chrono::Utc::now().naive_utc());
+            async {
+                let txn = app_state.db_conn.begin().await?;
+                article_revision::save_revision(
+                    &txn,
+                    &previous,
+                    upsert_today_input_dto.title.as_deref(),
+                    Some(&content),
+                    auth_user.id,
+                )
+                .await?;
+                let model = tbl_article::Entity::update(tbl_article_am)
+                    .exec(&txn)
+                    .await?;
+                attachment::link_files(&txn, model.id, auth_user.id, &file_ids).await?;
+                txn.commit().await?;
+                Ok(model)
+            }
+            .await
+        }
+        Ok(None) => {
+            let file_ids = markdown::file_references(&upsert_today_input_dto.content);
+            let tbl_article_am = tbl_article::ActiveModel {
+                title: Set(upsert_today_input_dto
+                    .title
+                    .unwrap_or_else(|| entry_date.to_string())),
+                content: Set(upsert_today_input_dto.content),
+                user_id: Set(auth_user.id),
+                entry_date: Set(Some(entry_date)),
+                ..Default::default()
+            };
+            async {
+                let txn = app_state.db_conn.begin().await?;
+                let model = tbl_article::Entity::insert(tbl_article_am)
+                    .exec_with_returning(&txn)
+                    .await?;
+                attachment::link_files(&txn, model.id, auth_user.id, &file_ids).await?;
+                txn.commit().await?;
+                Ok(model)
+            }
+            .await
+        }
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(model) => (
+            StatusCode::OK,
+            [("code", "200"), ("msg", "ok")],
+            Json(json!(EntryOutputDto::from(model))),
+        ),
+        Err(e) if is_unique_violation(&e) => {
+            log::warn!("tbl_article upsert conflict: {}", e);
+            (
+                StatusCode::CONFLICT,
+                [("code", "409"), ("msg", "entry exists")],
+                Json(json!({})),
+            )
+        }
+        Err(e) => {
+            log::error!("tbl_article upsert {} err: {}", entry_date, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "upsert db err")],
+                Json(json!({})),
+            )
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct CalendarInputDto {
+    // 格式为YYYY-MM, 为空时为用户时区的当月
+    month: Option<String>,
+}
+#[derive(Serialize, Debug)]
+struct CalendarDayOutputDto {
+    date: String,
+    has_entry: bool,
+    article_id: Option<i32>,
+}
+async fn calendar(
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<READER>,
+    Query(calendar_input_dto): Query<CalendarInputDto>,
+) -> impl IntoResponse {
+    let today = match user_today(&app_state.db_conn, auth_user.id).await {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("user {} today err: {}", auth_user.id, e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_auth_user find err")],
+                Json(json!({})),
+            );
+        }
+    };
+    let first_day = match calendar_input_dto.month {
+        Some(month) => match NaiveDate::parse_from_str(&format!("{month}-01"), "%Y-%m-%d") {
+            Ok(v) => v,
+            Err(e) => {
+                log::warn!("calendar month {} invalid: {}", month, e);
+                return (
+                    StatusCode::BAD_REQUEST,
+                    [("code", "400"), ("msg", "invalid month")],
+                    Json(json!({})),
+                );
+            }
+        },
+        None => today.with_day(1).unwrap_or(today),
+    };
+    let Some(next_month) = first_day.checked_add_months(Months::new(1)) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            [("code", "400"), ("msg", "invalid month")],
+            Json(json!({})),
+        );
+    };
+    let tbl_articles = match tbl_article::Entity::find()
+        .filter(tbl_article::Column::UserId.eq(auth_user.id))
+        .filter(tbl_article::Column::DeletedAt.is_null())
+        .filter(tbl_article::Column::EntryDate.gte(first_day))
+        .filter(tbl_article::Column::EntryDate.lt(next_month))
+        .all(&app_state.db_conn)
+        .await
+    {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("tbl_article find err: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_article find err")],
+                Json(json!({})),
+            );
+        }
+    };
+    let days: Vec<CalendarDayOutputDto> = first_day
+        .iter_days()
+        .take_while(|date| *date < next_month)
+        .map(|date| {
+            let article_id = tbl_articles
+                .iter()
+                .find(|tbl_article| tbl_article.entry_date == Some(date))
+                .map(|tbl_article| tbl_article.id);
+            CalendarDayOutputDto {
+                date: date.to_string(),
+                has_entry: article_id.is_some(),
+                article_id,
+            }
+        })
+        .collect();
+    (
+        StatusCode::OK,
+        [("code", "200"), ("msg", "ok")],
+        Json(json!({
+            "month": first_day.format("%Y-%m").to_string(),
+            "today": today.to_string(),
+            "days": days
+        })),
+    )
+}
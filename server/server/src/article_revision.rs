@@ -0,0 +1,660 @@
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, post},
+};
+use entity::{tbl_article, tbl_article_revision};
+use sea_orm::{
+    ActiveValue::Set, ColumnTrait, ConnectionTrait, DatabaseConnection, DbBackend, DbErr,
+    EntityTrait, IntoActiveModel, QueryFilter, QueryOrder, Statement, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use similar::{ChangeTag, TextDiff};
+
+use crate::{
+    AppState, attachment,
+    auth::{self, EDITOR, READER, RequireRole},
+    markdown,
+    reflection::{self, AnswerInputDto},
+};
+
+pub fn routers(state: AppState) -> Router {
+    Router::new()
+        .route("/articles/{id}/revisions", get(query))
+        .route("/articles/{id}/revisions/diff", get(diff))
+        .route("/articles/{id}/revisions/{revision}", get(detail))
+        .route("/articles/{id}/revisions/{revision}/restore", post(restore))
+        .with_state(state)
+}
+
+/// 保存文章修改前的版本和当时的回答, 需在修改文章和回答的同一事务中调用
+/// 标题和正文都未变化时不保存
+pub async fn save_revision<C: ConnectionTrait>(
+    db: &C,
+    tbl_article: &tbl_article::Model,
+    title: Option<&str>,
+    content: Option<&str>,
+    user_id: i32,
+) -> Result<(), DbErr> {
+    let title_changed = title.is_some_and(|v| v != tbl_article.title);
+    let content_changed = content.is_some_and(|v| v != tbl_article.content);
+    if !title_changed && !content_changed {
+        return Ok(());
+    }
+    // 版本号在INSERT中计算, 并发修改同一文章时不会算出相同的版本号
+    db.execute(Statement::from_sql_and_values(
+        DbBackend::Sqlite,
+        "INSERT INTO tbl_article_revision (article_id, revision, title, content, user_id, answers) SELECT ?, COALESCE(MAX(revision), 0) + 1, ?, ?, ?, (SELECT json_group_array(json_object('prompt_id', prompt_id, 'answer', answer)) FROM tbl_article_answer WHERE article_id = ?) FROM tbl_article_revision WHERE article_id = ?",
+        [
+            tbl_article.id.into(),
+            tbl_article.title.clone().into(),
+            tbl_article.content.clone().into(),
+            user_id.into(),
+            tbl_article.id.into(),
+            tbl_article.id.into(),
+        ],
+    ))
+    .await?;
+    Ok(())
+}
+
+async fn find_own_article(
+    db_conn: &DatabaseConnection,
+    id: i32,
+    user_id: i32,
+) -> Result<Option<tbl_article::Model>, DbErr> {
+    tbl_article::Entity::find_by_id(id)
+        .filter(tbl_article::Column::UserId.eq(user_id))
+        .filter(tbl_article::Column::DeletedAt.is_null())
+        .one(db_conn)
+        .await
+}
+
+async fn find_revision(
+    db_conn: &DatabaseConnection,
+    article_id: i32,
+    revision: i32,
+) -> Result<Option<tbl_article_revision::Model>, DbErr> {
+    tbl_article_revision::Entity::find()
+        .filter(tbl_article_revision::Column::ArticleId.eq(article_id))
+        .filter(tbl_article_revision::Column::Revision.eq(revision))
+        .one(db_conn)
+        .await
+}
+
+#[derive(Serialize, Debug)]
+struct RevisionOutputDto {
+    revision: i32,
+    title: String,
+    user_id: i32,
+    created_at: i64,
+}
+async fn query(
+    Path(id): Path<i32>,
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<READER>,
+) -> impl IntoResponse {
+    let tbl_article = match find_own_article(&app_state.db_conn, id, auth_user.id).await {
+        Ok(Some(v)) => v,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                [("code", "404"), ("msg", "not found")],
+                Json(json!({})),
+            );
+        }
+        Err(e) => {
+            log::error!("tbl_article find err: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_article find err")],
+                Json(json!({})),
+            );
+        }
+    };
+    let tbl_article_revisions = match tbl_article_revision::Entity::find()
+        .filter(tbl_article_revision::Column::ArticleId.eq(id))
+        .order_by_desc(tbl_article_revision::Column::Revision)
+        .all(&app_state.db_conn)
+        .await
+    {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("tbl_article_revision find err: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_article_revision find err")],
+                Json(json!({})),
+            );
+        }
+    };
+    let revision_output_dtos: Vec<RevisionOutputDto> = tbl_article_revisions
+        .into_iter()
+        .map(|tbl_article_revision| RevisionOutputDto {
+            revision: tbl_article_revision.revision,
+            title: tbl_article_revision.title,
+            user_id: tbl_article_revision.user_id,
+            created_at: tbl_article_revision.created_at.and_utc().timestamp_millis(),
+        })
+        .collect();
+    (
+        StatusCode::OK,
+        [("code", "200"), ("msg", "ok")],
+        Json(json!({
+            "current": {
+                "title": tbl_article.title,
+                "updated_at": tbl_article.updated_at.and_utc().timestamp_millis()
+            },
+            "revisions": revision_output_dtos
+        })),
+    )
+}
+
+async fn detail(
+    Path((id, revision)): Path<(i32, i32)>,
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<READER>,
+) -> impl IntoResponse {
+    match find_own_article(&app_state.db_conn, id, auth_user.id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                [("code", "404"), ("msg", "not found")],
+                Json(json!({})),
+            );
+        }
+        Err(e) => {
+            log::error!("tbl_article find err: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_article find err")],
+                Json(json!({})),
+            );
+        }
+    }
+    match find_revision(&app_state.db_conn, id, revision).await {
+        Ok(Some(tbl_article_revision)) => (
+            StatusCode::OK,
+            [("code", "200"), ("msg", "ok")],
+            Json(json!({
+                "revision": tbl_article_revision.revision,
+                "title": tbl_article_revision.title,
+                "content": tbl_article_revision.content,
+                "user_id": tbl_article_revision.user_id,
+                "created_at": tbl_article_revision.created_at.and_utc().timestamp_millis()
+            })),
+        ),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            [("code", "404"), ("msg", "revision not found")],
+            Json(json!({})),
+        ),
+        Err(e) => {
+            log::error!("tbl_article_revision find err: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_article_revision find err")],
+                Json(json!({})),
+            )
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct DiffInputDto {
+    from: i32,
+    // 为空时与当前版本比较
+    to: Option<i32>,
+}
+#[derive(Serialize, Debug)]
+struct DiffLineOutputDto {
+    tag: &'static str,
+    old_line: Option<usize>,
+    new_line: Option<usize>,
+    text: String,
+}
+async fn diff(
+    Path(id): Path<i32>,
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<READER>,
+    Query(diff_input_dto): Query<DiffInputDto>,
+) -> impl IntoResponse {
+    let tbl_article = match find_own_article(&app_state.db_conn, id, auth_user.id).await {
+        Ok(Some(v)) => v,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                [("code", "404"), ("msg", "not found")],
+                Json(json!({})),
+            );
+        }
+        Err(e) => {
+            log::error!("tbl_article find err: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_article find err")],
+                Json(json!({})),
+            );
+        }
+    };
+    // 版本号为空表示当前版本
+    let mut versions = Vec::new();
+    for revision in [Some(diff_input_dto.from), diff_input_dto.to] {
+        let Some(revision) = revision else {
+            versions.push((tbl_article.title.clone(), tbl_article.content.clone()));
+            continue;
+        };
+        match find_revision(&app_state.db_conn, id, revision).await {
+            Ok(Some(v)) => versions.push((v.title, v.content)),
+            Ok(None) => {
+                return (
+                    StatusCode::NOT_FOUND,
+                    [("code", "404"), ("msg", "revision not found")],
+                    Json(json!({})),
+                );
+            }
+            Err(e) => {
+                log::error!("tbl_article_revision find err: {}", e);
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    [("code", "500"), ("msg", "tbl_article_revision find err")],
+                    Json(json!({})),
+                );
+            }
+        }
+    }
+    let (old_title, old_content) = &versions[0];
+    let (new_title, new_content) = &versions[1];
+    // 统一以换行结尾, 避免最后一行因缺少换行被视为修改
+    let old_content = format!("{}\n", old_content.trim_end_matches(['\r', '\n']));
+    let new_content = format!("{}\n", new_content.trim_end_matches(['\r', '\n']));
+    let text_diff = TextDiff::from_lines(&old_content, &new_content);
+    let lines: Vec<DiffLineOutputDto> = text_diff
+        .iter_all_changes()
+        .map(|change| DiffLineOutputDto {
+            tag: match change.tag() {
+                ChangeTag::Equal => "equal",
+                ChangeTag::Delete => "delete",
+                ChangeTag::Insert => "insert",
+            },
+            old_line: change.old_index().map(|v| v + 1),
+            new_line: change.new_index().map(|v| v + 1),
+            text: change.value().trim_end_matches(['\r', '\n']).to_string(),
+        })
+        .collect();
+    let from_name = format!("r{}", diff_input_dto.from);
+    let to_name = diff_input_dto
+        .to
+        .map(|v| format!("r{v}"))
+        .unwrap_or_else(|| "current".to_string());
+    let unified = text_diff
+        .unified_diff()
+        .context_radius(3)
+        .header(&from_name, &to_name)
+        .to_string();
+    (
+        StatusCode::OK,
+        [("code", "200"), ("msg", "ok")],
+        Json(json!({
+            "from": diff_input_dto.from,
+            "to": diff_input_dto.to,
+            "title": {
+                "from": old_title,
+                "to": new_title,
+                "changed": old_title != new_title
+            },
+            "lines": lines,
+            "unified": unified
+        })),
+    )
+}
+
+#[derive(Debug)]
+enum RestoreErr {
+    // 模板文章的旧版本没有回答快照, 或回答的问题已被删除
+    Conflict(&'static str),
+    Db(DbErr),
+}
+
+impl From<DbErr> for RestoreErr {
+    fn from(e: DbErr) -> Self {
+        Self::Db(e)
+    }
+}
+
+/// 在同一事务中恢复标题、正文和模板文章的回答, 使正文与回答保持一致
+async fn restore_revision(
+    db_conn: &DatabaseConnection,
+    tbl_article: tbl_article::Model,
+    tbl_article_revision: tbl_article_revision::Model,
+    user_id: i32,
+) -> Result<tbl_article::Model, RestoreErr> {
+    let answers = match (tbl_article.template_id, &tbl_article_revision.answers) {
+        (None, _) => None,
+        (Some(_), None) => return Err(RestoreErr::Conflict("revision has no answers")),
+        (Some(template_id), Some(answers)) => {
+            let answers: Vec<AnswerInputDto> = serde_json::from_str(answers)
+                .map_err(|e| DbErr::Custom(format!("revision answers err: {e}")))?;
+            let prompts = reflection::template_prompts(db_conn, template_id)
+                .await?
+                .map(|(_, prompts)| prompts)
+                .unwrap_or_default();
+            if reflection::validate_answers(&prompts, &answers).is_err() {
+                return Err(RestoreErr::Conflict("prompt removed"));
+            }
+            Some(answers)
+        }
+    };
+    let id = tbl_article.id;
+    let txn = db_conn.begin().await?;
+    save_revision(
+        &txn,
+        &tbl_article,
+        Some(&tbl_article_revision.title),
+        Some(&tbl_article_revision.content),
+        user_id,
+    )
+    .await?;
+    let mut tbl_article_am = tbl_article.into_active_model();
+    tbl_article_am.title = Set(tbl_article_revision.title);
+    tbl_article_am.content = Set(tbl_article_revision.content);
+    tbl_article_am.updated_at = Set(chrono::Utc::now().naive_utc());
+    let model = tbl_article::Entity::update(tbl_article_am)
+        .exec(&txn)
+        .await?;
+    if let Some(answers) = &answers {
+        reflection::replace_answers(&txn, id, answers).await?;
+    }
+    attachment::link_files(
+        &txn,
+        id,
+        user_id,
+        &markdown::file_references(&model.content),
+    )
+    .await?;
+    txn.commit().await?;
+    Ok(model)
+}
+
+/// 恢复旧版本, 当前版本先保存为新的历史版本, 因此恢复操作本身也可撤销
+async fn restore(
+    Path((id, revision)): Path<(i32, i32)>,
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<EDITOR>,
+) -> impl IntoResponse {
+    let tbl_article = match find_own_article(&app_state.db_conn, id, auth_user.id).await {
+        Ok(Some(v)) => v,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                [("code", "404"), ("msg", "not found")],
+                Json(json!({})),
+            );
+        }
+        Err(e) => {
+            log::error!("tbl_article find err: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_article find err")],
+                Json(json!({})),
+            );
+        }
+    };
+    let tbl_article_revision = match find_revision(&app_state.db_conn, id, revision).await {
+        Ok(Some(v)) => v,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                [("code", "404"), ("msg", "revision not found")],
+                Json(json!({})),
+            );
+        }
+        Err(e) => {
+            log::error!("tbl_article_revision find err: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_article_revision find err")],
+                Json(json!({})),
+            );
+        }
+    };
+    let result = restore_revision(
+        &app_state.db_conn,
+        tbl_article,
+        tbl_article_revision,
+        auth_user.id,
+    )
+    .await;
+    match result {
+        Ok(model) => {
+            auth::insert_log(
+                &app_state,
+                format!(
+                    "{} restore article {} to revision {}",
+                    auth_user.username, id, revision
+                ),
+            )
+            .await;
+            (
+                StatusCode::OK,
+                [("code", "200"), ("msg", "ok")],
+                Json(json!({
+                    "id": model.id,
+                    "title": model.title,
+                    "updated_at": model.updated_at.and_utc().timestamp_millis()
+                })),
+            )
+        }
+        Err(RestoreErr::Conflict(msg)) => {
+            log::warn!("restore article {id} revision {revision} conflict: {msg}");
+            (
+                StatusCode::CONFLICT,
+                [("code", "409"), ("msg", msg)],
+                Json(json!({})),
+            )
+        }
+        Err(RestoreErr::Db(e)) => {
+            log::error!("restore article {id} revision {revision} err: {e}");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "restore db err")],
+                Json(json!({})),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use entity::{
+        tbl_article_answer, tbl_auth_user, tbl_reflection_prompt, tbl_reflection_template,
+    };
+    use migration::{Migrator, MigratorTrait};
+    use sea_orm::Database;
+
+    use super::*;
+
+    async fn test_db() -> DatabaseConnection {
+        let db_path = std::env::temp_dir().join(format!(
+            "article_revision_test_{}.sqlite",
+            uuid::Uuid::new_v4().simple()
+        ));
+        let db_conn = Database::connect(format!("sqlite://{}?mode=rwc", db_path.to_string_lossy()))
+            .await
+            .unwrap();
+        Migrator::up(&db_conn, None).await.unwrap();
+        db_conn
+    }
+
+    fn answer(prompt_id: i32, answer: &str) -> Vec<AnswerInputDto> {
+        vec![AnswerInputDto {
+            prompt_id,
+            answer: answer.to_string(),
+        }]
+    }
+
+    async fn saved_answers(db_conn: &DatabaseConnection, article_id: i32) -> Vec<String> {
+        tbl_article_answer::Entity::find()
+            .filter(tbl_article_answer::Column::ArticleId.eq(article_id))
+            .all(db_conn)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|v| v.answer)
+            .collect()
+    }
+
+    /// 创建只有一个问题的模板文章, 返回文章和问题
+    async fn template_article(
+        db_conn: &DatabaseConnection,
+        first_answer: &str,
+    ) -> (tbl_article::Model, tbl_reflection_prompt::Model) {
+        let user = tbl_auth_user::Entity::insert(tbl_auth_user::ActiveModel {
+            username: Set("bob".to_string()),
+            password: Set("not used".to_string()),
+            role: Set("editor".to_string()),
+            ..Default::default()
+        })
+        .exec_with_returning(db_conn)
+        .await
+        .unwrap();
+        let template =
+            tbl_reflection_template::Entity::insert(tbl_reflection_template::ActiveModel {
+                name: Set("daily".to_string()),
+                ..Default::default()
+            })
+            .exec_with_returning(db_conn)
+            .await
+            .unwrap();
+        let prompt = tbl_reflection_prompt::Entity::insert(tbl_reflection_prompt::ActiveModel {
+            template_id: Set(template.id),
+            position: Set(0),
+            content: Set("今天做了什么".to_string()),
+            ..Default::default()
+        })
+        .exec_with_returning(db_conn)
+        .await
+        .unwrap();
+        let answers = answer(prompt.id, first_answer);
+        let now = chrono::Utc::now().naive_utc();
+        let tbl_article = tbl_article::Entity::insert(tbl_article::ActiveModel {
+            title: Set("日记".to_string()),
+            content: Set(reflection::compose_content(
+                &reflection::render_content(std::slice::from_ref(&prompt), &answers),
+                "extra",
+            )),
+            user_id: Set(user.id),
+            template_id: Set(Some(template.id)),
+            created_at: Set(now),
+            updated_at: Set(now),
+            ..Default::default()
+        })
+        .exec_with_returning(db_conn)
+        .await
+        .unwrap();
+        reflection::replace_answers(db_conn, tbl_article.id, &answers)
+            .await
+            .unwrap();
+        (tbl_article, prompt)
+    }
+
+    // 与article::update相同, 在一个事务中保存版本、修改正文和回答
+    async fn update_answer(
+        db_conn: &DatabaseConnection,
+        tbl_article: tbl_article::Model,
+        prompt: &tbl_reflection_prompt::Model,
+        new_answer: &str,
+    ) -> tbl_article::Model {
+        let answers = answer(prompt.id, new_answer);
+        let content = reflection::compose_content(
+            &reflection::render_content(std::slice::from_ref(prompt), &answers),
+            "extra",
+        );
+        let txn = db_conn.begin().await.unwrap();
+        save_revision(
+            &txn,
+            &tbl_article,
+            None,
+            Some(&content),
+            tbl_article.user_id,
+        )
+        .await
+        .unwrap();
+        let mut tbl_article_am = tbl_article.into_active_model();
+        tbl_article_am.content = Set(content);
+        let model = tbl_article::Entity::update(tbl_article_am)
+            .exec(&txn)
+            .await
+            .unwrap();
+        reflection::replace_answers(&txn, model.id, &answers)
+            .await
+            .unwrap();
+        txn.commit().await.unwrap();
+        model
+    }
+
+    #[tokio::test]
+    async fn restore_template_article_restores_answers() {
+        let db_conn = test_db().await;
+        let (original, prompt) = template_article(&db_conn, "old answer").await;
+        let updated = update_answer(&db_conn, original.clone(), &prompt, "new answer").await;
+        assert_eq!(saved_answers(&db_conn, updated.id).await, ["new answer"]);
+
+        let revision = find_revision(&db_conn, updated.id, 1)
+            .await
+            .unwrap()
+            .unwrap();
+        let restored = restore_revision(&db_conn, updated, revision, original.user_id)
+            .await
+            .unwrap();
+        assert_eq!(restored.content, original.content);
+        assert_eq!(saved_answers(&db_conn, restored.id).await, ["old answer"]);
+        // 正文与回答一致, 再次修改时额外正文不会重复追加
+        let content = reflection::template_content(
+            &db_conn,
+            &restored,
+            original.template_id.unwrap(),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(content, restored.content);
+
+        // 恢复前的版本连同新回答一起保存, 恢复操作本身可撤销
+        let revision = find_revision(&db_conn, restored.id, 2)
+            .await
+            .unwrap()
+            .unwrap();
+        let answers: Vec<AnswerInputDto> =
+            serde_json::from_str(revision.answers.as_deref().unwrap()).unwrap();
+        assert_eq!(answers.len(), 1);
+        assert_eq!(answers[0].answer, "new answer");
+    }
+
+    #[tokio::test]
+    async fn restore_template_revision_without_answers_conflicts() {
+        let db_conn = test_db().await;
+        let (original, prompt) = template_article(&db_conn, "old answer").await;
+        let updated = update_answer(&db_conn, original, &prompt, "new answer").await;
+        let revision = find_revision(&db_conn, updated.id, 1)
+            .await
+            .unwrap()
+            .unwrap();
+        // 增加回答快照之前保存的版本
+        let mut tbl_article_revision_am = revision.into_active_model();
+        tbl_article_revision_am.answers = Set(None);
+        let revision = tbl_article_revision::Entity::update(tbl_article_revision_am)
+            .exec(&db_conn)
+            .await
+            .unwrap();
+
+        let result = restore_revision(&db_conn, updated.clone(), revision, updated.user_id).await;
+        assert!(matches!(result, Err(RestoreErr::Conflict(_))));
+        assert_eq!(saved_answers(&db_conn, updated.id).await, ["new answer"]);
+    }
+}
@@ -0,0 +1,323 @@
+use axum::{
+    Json, Router,
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::get,
+};
+use chrono::{NaiveDate, NaiveDateTime};
+use entity::tbl_article;
+use sea_orm::{
+    ColumnTrait, Condition, DbBackend, EntityTrait, FromQueryResult, PaginatorTrait, QueryFilter,
+    QueryOrder, Statement, Value, sea_query::Expr,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    AppState,
+    auth::{READER, RequireRole},
+};
+
+pub fn routers(state: AppState) -> Router {
+    Router::new()
+        .route("/articles/search", get(search))
+        .with_state(state)
+}
+
+// trigram分词只能匹配不少于3个字符的词, 更短的词用LIKE匹配
+const TRIGRAM_MIN_CHARS: usize = 3;
+// 高亮标记先用控制字符占位, 转义HTML后再替换为<mark>
+const MARK_START: char = '\u{2}';
+const MARK_END: char = '\u{3}';
+const SNIPPET_CHARS: usize = 64;
+
+#[derive(Deserialize, Debug)]
+struct SearchInputDto {
+    q: String,
+    size: u64,
+    page: u64,
+}
+
+#[derive(Serialize, Debug)]
+struct SearchOutputDto {
+    id: i32,
+    // 标题和摘要为HTML, 命中词用<mark>包裹, 其余内容已转义
+    title: String,
+    snippet: String,
+    entry_date: Option<String>,
+    // bm25得分, 越小越相关; LIKE匹配时为空
+    rank: Option<f64>,
+    updated_at: i64,
+}
+
+#[derive(FromQueryResult)]
+struct FtsHit {
+    id: i32,
+    title: String,
+    snippet: String,
+    entry_date: Option<NaiveDate>,
+    rank: f64,
+    updated_at: NaiveDateTime,
+}
+
+#[derive(FromQueryResult)]
+struct FtsCount {
+    count: i64,
+}
+
+/// 全文检索当前用户的文章, 按bm25排序并返回高亮摘要
+/// 多个词之间为AND关系, 所有词都短于3个字符时退化为LIKE匹配并按更新时间排序
+async fn search(
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<READER>,
+    Query(search_input_dto): Query<SearchInputDto>,
+) -> impl IntoResponse {
+    let terms: Vec<&str> = search_input_dto.q.split_whitespace().collect();
+    if terms.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            [("code", "400"), ("msg", "empty query")],
+            Json(json!({})),
+        );
+    }
+    let size = search_input_dto.size.clamp(1, 100);
+    let (fts_terms, like_terms): (Vec<&str>, Vec<&str>) = terms
+        .iter()
+        .partition(|term| term.chars().count() >= TRIGRAM_MIN_CHARS);
+    let result = if fts_terms.is_empty() {
+        like_search(
+            &app_state,
+            auth_user.id,
+            &like_terms,
+            size,
+            search_input_dto.page,
+        )
+        .await
+    } else {
+        fts_search(
+            &app_state,
+            auth_user.id,
+            &fts_terms,
+            &like_terms,
+            size,
+            search_input_dto.page,
+        )
+        .await
+    };
+    let (num_items, search_output_dtos) = match result {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("article search {:?} err: {}", search_input_dto.q, e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "search err")],
+                Json(json!({})),
+            );
+        }
+    };
+    (
+        StatusCode::OK,
+        [("code", "200"), ("msg", "ok")],
+        Json(json!({
+            "page": {
+                "size": size,
+                "total_elements": num_items,
+                "total_pages": num_items.div_ceil(size)
+            },
+            "_embedded": {
+                "article": search_output_dtos
+            }
+        })),
+    )
+}
+
+async fn fts_search(
+    app_state: &AppState,
+    user_id: i32,
+    fts_terms: &[&str],
+    like_terms: &[&str],
+    size: u64,
+    page: u64,
+) -> Result<(u64, Vec<SearchOutputDto>), sea_orm::DbErr> {
+    // 每个词作为短语查询, 避免用户输入被解析为FTS5语法
+    let match_query = fts_terms
+        .iter()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut where_sql =
+        "tbl_article_fts MATCH ? AND a.user_id = ? AND a.deleted_at IS NULL".to_string();
+    let mut values: Vec<Value> = vec![match_query.into(), user_id.into()];
+    for term in like_terms {
+        where_sql.push_str(" AND (a.title LIKE ? ESCAPE '\\' OR a.content LIKE ? ESCAPE '\\')");
+        let like_pattern = like_pattern(term);
+        values.push(like_pattern.clone().into());
+        values.push(like_pattern.into());
+    }
+    let count = FtsCount::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Sqlite,
+        format!(
+            "SELECT COUNT(*) AS count FROM tbl_article_fts JOIN tbl_article a ON a.id = tbl_article_fts.rowid WHERE {where_sql}"
+        ),
+        values.clone(),
+    ))
+    .one(&app_state.db_conn)
+    .await?
+    .map(|v| v.count as u64)
+    .unwrap_or_default();
+    let mark_start = MARK_START.to_string();
+    let mark_end = MARK_END.to_string();
+    let mut hit_values: Vec<Value> = vec![
+        mark_start.clone().into(),
+        mark_end.clone().into(),
+        mark_start.into(),
+        mark_end.into(),
+    ];
+    hit_values.extend(values);
+    hit_values.push((size as i64).into());
+    hit_values.push(((page * size) as i64).into());
+    // 标题权重高于正文
+    let fts_hits = FtsHit::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Sqlite,
+        format!(
+            "SELECT a.id, highlight(tbl_article_fts, 0, ?, ?) AS title, snippet(tbl_article_fts, 1, ?, ?, '…', 32) AS snippet, a.entry_date, bm25(tbl_article_fts, 10.0, 1.0) AS rank, a.updated_at FROM tbl_article_fts JOIN tbl_article a ON a.id = tbl_article_fts.rowid WHERE {where_sql} ORDER BY rank LIMIT ? OFFSET ?"
+        ),
+        hit_values,
+    ))
+    .all(&app_state.db_conn)
+    .await?;
+    let search_output_dtos = fts_hits
+        .into_iter()
+        .map(|fts_hit| SearchOutputDto {
+            id: fts_hit.id,
+            title: marked_html(&fts_hit.title),
+            snippet: marked_html(&fts_hit.snippet),
+            entry_date: fts_hit.entry_date.map(|v| v.to_string()),
+            rank: Some(fts_hit.rank),
+            updated_at: fts_hit.updated_at.and_utc().timestamp_millis(),
+        })
+        .collect();
+    Ok((count, search_output_dtos))
+}
+
+async fn like_search(
+    app_state: &AppState,
+    user_id: i32,
+    like_terms: &[&str],
+    size: u64,
+    page: u64,
+) -> Result<(u64, Vec<SearchOutputDto>), sea_orm::DbErr> {
+    let mut condition = Condition::all()
+        .add(tbl_article::Column::UserId.eq(user_id))
+        .add(tbl_article::Column::DeletedAt.is_null());
+    for term in like_terms {
+        let like_pattern = like_pattern(term);
+        condition = condition.add(
+            Condition::any()
+                .add(Expr::cust_with_values(
+                    "title LIKE ? ESCAPE '\\'",
+                    [like_pattern.clone()],
+                ))
+                .add(Expr::cust_with_values(
+                    "content LIKE ? ESCAPE '\\'",
+                    [like_pattern],
+                )),
+        );
+    }
+    let paginator = tbl_article::Entity::find()
+        .filter(condition)
+        .order_by_desc(tbl_article::Column::UpdatedAt)
+        .paginate(&app_state.db_conn, size);
+    let num_items = paginator.num_items().await?;
+    let tbl_articles = paginator.fetch_page(page).await?;
+    let terms: Vec<String> = like_terms
+        .iter()
+        .map(|term| term.to_ascii_lowercase())
+        .collect();
+    let search_output_dtos = tbl_articles
+        .into_iter()
+        .map(|tbl_article| SearchOutputDto {
+            id: tbl_article.id,
+            title: marked_html(&mark_terms(&tbl_article.title, &terms)),
+            snippet: marked_html(&mark_terms(
+                &snippet_window(&tbl_article.content, &terms),
+                &terms,
+            )),
+            entry_date: tbl_article.entry_date.map(|v| v.to_string()),
+            rank: None,
+            updated_at: tbl_article.updated_at.and_utc().timestamp_millis(),
+        })
+        .collect();
+    Ok((num_items, search_output_dtos))
+}
+
+fn like_pattern(term: &str) -> String {
+    let escaped = term
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{escaped}%")
+}
+
+/// 截取第一个命中词附近的正文, 与SQLite的LIKE一致只对ASCII忽略大小写
+fn snippet_window(content: &str, terms: &[String]) -> String {
+    let lower = content.to_ascii_lowercase();
+    let pos = terms
+        .iter()
+        .filter_map(|term| lower.find(term.as_str()))
+        .min()
+        .unwrap_or(0);
+    let start = content[..pos]
+        .chars()
+        .count()
+        .saturating_sub(SNIPPET_CHARS / 4);
+    let total = content.chars().count();
+    let mut window: String = content.chars().skip(start).take(SNIPPET_CHARS).collect();
+    if start > 0 {
+        window.insert(0, '…');
+    }
+    if start + SNIPPET_CHARS < total {
+        window.push('…');
+    }
+    window
+}
+
+fn mark_terms(text: &str, terms: &[String]) -> String {
+    let lower = text.to_ascii_lowercase();
+    let mut marked = String::new();
+    let mut i = 0;
+    while let Some(ch) = text[i..].chars().next() {
+        if let Some(term) = terms
+            .iter()
+            .find(|term| !term.is_empty() && lower[i..].starts_with(term.as_str()))
+        {
+            marked.push(MARK_START);
+            marked.push_str(&text[i..i + term.len()]);
+            marked.push(MARK_END);
+            i += term.len();
+        } else {
+            marked.push(ch);
+            i += ch.len_utf8();
+        }
+    }
+    marked
+}
+
+/// 转义HTML并把高亮占位符替换为<mark>
+fn marked_html(text: &str) -> String {
+    let mut html = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#39;"),
+            MARK_START => html.push_str("<mark>"),
+            MARK_END => html.push_str("</mark>"),
+            _ => html.push(ch),
+        }
+    }
+    html
+}
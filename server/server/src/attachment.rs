@@ -0,0 +1,280 @@
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{delete, get},
+};
+use chrono::NaiveDateTime;
+use entity::{tbl_article, tbl_article_file, tbl_file};
+use sea_orm::{
+    ActiveValue::Set,
+    ColumnTrait, Condition, ConnectionTrait, DbErr, EntityTrait, FromQueryResult, QueryFilter,
+    QueryOrder, QuerySelect,
+    sea_query::{Expr, OnConflict},
+};
+use serde::Serialize;
+use serde_json::json;
+
+use crate::{
+    AppState,
+    auth::{self, AuthUser, EDITOR, READER, RequireRole, Role},
+};
+
+pub fn routers(state: AppState) -> Router {
+    Router::new()
+        .route("/articles/{id}/files", get(query))
+        .route("/articles/{id}/files/{file_id}", delete(unlink))
+        .with_state(state)
+}
+
+/// 把user_id上传的文件关联到文章, 不存在、在回收站中或别人上传的文件忽略, 已关联的不重复关联
+pub async fn link_files<C: ConnectionTrait>(
+    db: &C,
+    article_id: i32,
+    user_id: i32,
+    file_ids: &[i32],
+) -> Result<(), DbErr> {
+    if file_ids.is_empty() {
+        return Ok(());
+    }
+    let file_ids: Vec<i32> = tbl_file::Entity::find()
+        .select_only()
+        .column(tbl_file::Column::Id)
+        .filter(tbl_file::Column::Id.is_in(file_ids.iter().copied()))
+        .filter(tbl_file::Column::UserId.eq(user_id))
+        .filter(tbl_file::Column::DeletedAt.is_null())
+        .into_tuple()
+        .all(db)
+        .await?;
+    if file_ids.is_empty() {
+        return Ok(());
+    }
+    let tbl_article_file_ams = file_ids
+        .into_iter()
+        .map(|file_id| tbl_article_file::ActiveModel {
+            article_id: Set(article_id),
+            file_id: Set(file_id),
+            ..Default::default()
+        });
+    tbl_article_file::Entity::insert_many(tbl_article_file_ams)
+        .on_conflict(
+            OnConflict::columns([
+                tbl_article_file::Column::ArticleId,
+                tbl_article_file::Column::FileId,
+            ])
+            .do_nothing()
+            .to_owned(),
+        )
+        .do_nothing()
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// 文章删除时, 把为该文章上传且没有被其他未删除文章使用的附件一起放入回收站
+pub async fn trash_orphan_files<C: ConnectionTrait>(
+    db: &C,
+    article_id: i32,
+    deleted_at: NaiveDateTime,
+) -> Result<u64, DbErr> {
+    Ok(tbl_file::Entity::update_many()
+        .col_expr(tbl_file::Column::DeletedAt, Expr::value(deleted_at))
+        .filter(tbl_file::Column::ArticleId.eq(article_id))
+        .filter(tbl_file::Column::DeletedAt.is_null())
+        .filter(Expr::cust_with_values(
+            "tbl_file.id IN (SELECT file_id FROM tbl_article_file WHERE article_id = ?)",
+            [article_id],
+        ))
+        .filter(Expr::cust_with_values(
+            "NOT EXISTS (SELECT 1 FROM tbl_article_file af JOIN tbl_article a ON a.id = af.article_id WHERE af.file_id = tbl_file.id AND a.id <> ? AND a.deleted_at IS NULL)",
+            [article_id],
+        ))
+        .exec(db)
+        .await?
+        .rows_affected)
+}
+
+/// 文章从回收站恢复时, 一起恢复为它上传的附件
+pub async fn restore_files<C: ConnectionTrait>(db: &C, article_id: i32) -> Result<u64, DbErr> {
+    Ok(tbl_file::Entity::update_many()
+        .col_expr(
+            tbl_file::Column::DeletedAt,
+            Expr::value(None::<NaiveDateTime>),
+        )
+        .filter(tbl_file::Column::ArticleId.eq(article_id))
+        .filter(tbl_file::Column::DeletedAt.is_not_null())
+        .filter(Expr::cust_with_values(
+            "tbl_file.id IN (SELECT file_id FROM tbl_article_file WHERE article_id = ?)",
+            [article_id],
+        ))
+        .exec(db)
+        .await?
+        .rows_affected)
+}
+
+/// 文件库中的文件所有用户可见; 为文章上传的文件只有上传者和引用它的未删除文章的作者可见
+pub fn file_visible_to(user_id: i32) -> Condition {
+    Condition::any()
+        .add(tbl_file::Column::ArticleId.is_null())
+        .add(tbl_file::Column::UserId.eq(user_id))
+        .add(Expr::cust_with_values(
+            "EXISTS (SELECT 1 FROM tbl_article_file af JOIN tbl_article a ON a.id = af.article_id WHERE af.file_id = tbl_file.id AND a.user_id = ? AND a.deleted_at IS NULL)",
+            [user_id],
+        ))
+}
+
+/// 删除和恢复文件只限上传者, 管理员不受限制; 历史文件没有上传者, 只有管理员可以处理
+pub fn file_managed_by(auth_user: &AuthUser) -> Condition {
+    if auth_user.role >= Role::Admin {
+        Condition::all()
+    } else {
+        Condition::all().add(tbl_file::Column::UserId.eq(auth_user.id))
+    }
+}
+
+async fn own_article_exists(app_state: &AppState, id: i32, user_id: i32) -> Result<bool, DbErr> {
+    Ok(tbl_article::Entity::find_by_id(id)
+        .filter(tbl_article::Column::UserId.eq(user_id))
+        .filter(tbl_article::Column::DeletedAt.is_null())
+        .one(&app_state.db_conn)
+        .await?
+        .is_some())
+}
+
+#[derive(Serialize, Debug, FromQueryResult)]
+struct QueryOutputDto {
+    id: i32,
+    name: String,
+    content_type: Option<String>,
+}
+
+async fn query(
+    Path(id): Path<i32>,
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<READER>,
+) -> impl IntoResponse {
+    match own_article_exists(&app_state, id, auth_user.id).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return (
+                StatusCode::NOT_FOUND,
+                [("code", "404"), ("msg", "not found")],
+                Json(json!({})),
+            );
+        }
+        Err(e) => {
+            log::error!("tbl_article find err: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_article find err")],
+                Json(json!({})),
+            );
+        }
+    }
+    match tbl_file::Entity::find()
+        .select_only()
+        .column(tbl_file::Column::Id)
+        .column(tbl_file::Column::Name)
+        .column(tbl_file::Column::ContentType)
+        .inner_join(tbl_article_file::Entity)
+        .filter(tbl_article_file::Column::ArticleId.eq(id))
+        .filter(tbl_file::Column::DeletedAt.is_null())
+        .order_by_asc(tbl_article_file::Column::CreatedAt)
+        .into_model::<QueryOutputDto>()
+        .all(&app_state.db_conn)
+        .await
+    {
+        Ok(files) => {
+            let files: Vec<_> = files
+                .into_iter()
+                .map(|file| {
+                    json!({
+                        "id": file.id,
+                        "name": file.name,
+                        "content_type": file.content_type,
+                        // 正文中引用该文件的写法
+                        "markdown": format!("file:{}", file.id),
+                        "url": format!("/api/files/{}", file.id)
+                    })
+                })
+                .collect();
+            (
+                StatusCode::OK,
+                [("code", "200"), ("msg", "ok")],
+                Json(json!({
+                    "_embedded": {
+                        "file": files
+                    }
+                })),
+            )
+        }
+        Err(e) => {
+            log::error!("tbl_article_file find err: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_article_file find err")],
+                Json(json!({})),
+            )
+        }
+    }
+}
+
+/// 取消关联, 文件本身保留
+async fn unlink(
+    Path((id, file_id)): Path<(i32, i32)>,
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<EDITOR>,
+) -> impl IntoResponse {
+    match own_article_exists(&app_state, id, auth_user.id).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return (
+                StatusCode::NOT_FOUND,
+                [("code", "404"), ("msg", "not found")],
+                Json(json!({})),
+            );
+        }
+        Err(e) => {
+            log::error!("tbl_article find err: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_article find err")],
+                Json(json!({})),
+            );
+        }
+    }
+    auth::insert_log(
+        &app_state,
+        format!(
+            "{} unlink file {} from article {}",
+            auth_user.username, file_id, id
+        ),
+    )
+    .await;
+    match tbl_article_file::Entity::delete_many()
+        .filter(tbl_article_file::Column::ArticleId.eq(id))
+        .filter(tbl_article_file::Column::FileId.eq(file_id))
+        .exec(&app_state.db_conn)
+        .await
+    {
+        Ok(delete_result) if delete_result.rows_affected > 0 => (
+            StatusCode::OK,
+            [("code", "200"), ("msg", "ok")],
+            Json(json!({})),
+        ),
+        Ok(_) => (
+            StatusCode::NOT_FOUND,
+            [("code", "404"), ("msg", "file not linked")],
+            Json(json!({})),
+        ),
+        Err(e) => {
+            log::error!("tbl_article_file delete err: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_article_file delete err")],
+                Json(json!({})),
+            )
+        }
+    }
+}
@@ -1,35 +1,100 @@
-use std::{collections::HashSet, net::SocketAddr, ops::Deref};
+use std::{net::SocketAddr, ops::Deref, str::FromStr};
 
+use argon2::{
+    Argon2, PasswordHash, PasswordHasher, PasswordVerifier,
+    password_hash::{SaltString, rand_core::OsRng},
+};
 use axum::{
     Json, Router,
-    extract::{ConnectInfo, FromRequestParts, Path, State},
-    http::{Method, StatusCode, header, request::Parts},
+    extract::{ConnectInfo, FromRequestParts, MatchedPath, State},
+    http::{HeaderMap, Method, StatusCode, header, request::Parts},
     response::IntoResponse,
-    routing::post,
+    routing::{MethodRouter, get, post},
 };
-use entity::tbl_auth_user;
+use entity::{tbl_auth_user, tbl_log};
 use once_cell::sync::Lazy;
-use sea_orm::{ActiveValue::Set, ColumnTrait, EntityTrait, QueryFilter};
-use serde::Deserialize;
-use serde_json::json;
+use sea_orm::{
+    ActiveValue::Set, ColumnTrait, EntityTrait, IntoActiveModel, PaginatorTrait, QueryFilter,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use subtle::ConstantTimeEq;
+use tokio::sync::Mutex;
 use validator::Validate;
 
-use crate::AppState;
+use crate::{
+    AppState, api_token,
+    config::SERVER_TOML,
+    login_limit,
+    mtls::{self, ClientCert},
+    oidc,
+    session::{self, ClientInfo},
+    totp,
+};
 pub fn routers(state: AppState) -> Router {
     Router::new()
-        .route("/login", post(login))
-        .route("/logout/{token}", post(logout))
+        .route("/logout", post(logout))
         .with_state(state)
 }
+
+/// 公开接口的方法、路由和handler, 挂载和启动日志共用同一份列表
+pub type PublicRoute = (Method, &'static str, MethodRouter<AppState>);
+
+/// 挂载无需登录即可访问的接口, 不挂载RequireAuth, 逐条记录到日志便于核对公开范围
+pub fn public_router(state: AppState, prefix: &str, public_routes: Vec<PublicRoute>) -> Router {
+    let mut router = Router::new();
+    for (method, path, method_router) in public_routes {
+        log::info!("public api {} {}{}", method, prefix, path);
+        router = router.route(&format!("{prefix}{path}"), method_router);
+    }
+    router.with_state(state)
+}
+
+/// 无需登录即可访问的接口
+pub fn public_routes() -> Vec<PublicRoute> {
+    vec![
+        (Method::POST, "/login", post(login)),
+        (Method::POST, "/login/totp", post(login_totp)),
+        (Method::GET, "/setup", get(setup_status)),
+        (Method::POST, "/setup", post(setup)),
+        (Method::POST, "/refresh", post(refresh)),
+    ]
+}
 #[derive(Deserialize, Debug, Validate)]
 struct LoginInputDto {
     username: String,
     password: String,
+    #[serde(default)]
+    remember_me: bool,
 }
 async fn login(
     app_state: State<AppState>,
+    ConnectInfo(socket_addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Json(login_input_dto): Json<LoginInputDto>,
 ) -> impl IntoResponse {
+    let src_ip = socket_addr.ip().to_string();
+    let attempt_keys = [
+        login_limit::ip_key(&src_ip),
+        login_limit::username_key(&login_input_dto.username),
+    ];
+    let locked_secs = login_limit::locked_secs(&app_state.sled_db, &attempt_keys);
+    if locked_secs > 0 {
+        log::warn!(
+            "login locked {} from {}, retry after {}s",
+            login_input_dto.username,
+            src_ip,
+            locked_secs
+        );
+        // 锁定期间的尝试只写应用日志, 进入锁定时已经在login_failed中记录
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            [("code", "429"), ("msg", "TOO_MANY_REQUESTS")],
+            Json(json!({
+                "retry_after": locked_secs
+            })),
+        );
+    }
     match tbl_auth_user::Entity::find()
         .filter(tbl_auth_user::Column::Username.eq(&login_input_dto.username))
         .one(&app_state.db_conn)
@@ -37,75 +102,95 @@ async fn login(
     {
         Ok(tbl_auth_user_op) => match tbl_auth_user_op {
             Some(tbl_auth_user) => {
-                if tbl_auth_user.password.eq(&login_input_dto.password) {
-                    let token = uuid::Uuid::new_v4().to_string();
-                    if let Err(e) = app_state
-                        .sled_db
-                        .insert(token.clone(), &chrono::Utc::now().timestamp().to_be_bytes())
-                    {
-                        log::error!("sled db insert err: {}", e);
-                    }
-                    (
-                        StatusCode::OK,
-                        [("code", "200"), ("msg", "ok")],
-                        Json(json!({
-                            "token": token
-                        })),
-                    )
-                } else {
-                    (
-                        StatusCode::UNAUTHORIZED,
-                        [("code", "401"), ("msg", "UNAUTHORIZED")],
-                        Json(json!({})),
+                if !tbl_auth_user.enabled {
+                    log::warn!("user {} disabled", login_input_dto.username);
+                    verify_dummy_password(&login_input_dto.password);
+                    return login_failed(
+                        &app_state,
+                        &attempt_keys,
+                        &login_input_dto.username,
+                        &src_ip,
+                        "user disabled",
                     )
+                    .await;
                 }
-            }
-            None => {
-                // 初始化admin数据
-                if login_input_dto.username.eq("admin")
-                    && login_input_dto.password.eq("123qwe!@#QWE")
-                {
-                    let tbl_auth_user_am = tbl_auth_user::ActiveModel {
-                        username: Set(login_input_dto.username),
-                        password: Set(login_input_dto.password),
-                        ..Default::default()
-                    };
-                    match tbl_auth_user::Entity::insert(tbl_auth_user_am)
-                        .exec(&app_state.db_conn)
-                        .await
-                    {
-                        Ok(_) => {
-                            let token = uuid::Uuid::new_v4().to_string();
-                            if let Err(e) = app_state.sled_db.insert(
-                                token.clone(),
-                                &chrono::Utc::now().timestamp().to_be_bytes(),
-                            ) {
-                                log::error!("sled db insert err: {}", e);
+                let user_id = tbl_auth_user.id;
+                let totp_enabled = tbl_auth_user.totp_enabled;
+                if verify_password(&login_input_dto.password, &tbl_auth_user.password) {
+                    // 只清除用户名计数, 避免攻击者用自己的账号登录来重置来源ip计数
+                    login_limit::clear_failures(
+                        &app_state.sled_db,
+                        &[login_limit::username_key(&login_input_dto.username)],
+                    );
+                    if !is_password_hash(&tbl_auth_user.password) {
+                        // 历史明文密码, 登录成功后重新计算hash
+                        match hash_password(&login_input_dto.password) {
+                            Ok(password_hash) => {
+                                let mut tbl_auth_user_am =
+                                    tbl_auth_user.clone().into_active_model();
+                                tbl_auth_user_am.password = Set(password_hash);
+                                if let Err(e) = tbl_auth_user::Entity::update(tbl_auth_user_am)
+                                    .exec(&app_state.db_conn)
+                                    .await
+                                {
+                                    log::error!("tbl_auth_user update password err: {}", e);
+                                }
+                            }
+                            Err(e) => {
+                                log::error!("hash password err: {}", e);
                             }
-                            return (
+                        }
+                    }
+                    if totp_enabled {
+                        // 开启两步验证时先返回登录挑战, 校验验证码后再发放token
+                        return match totp::new_challenge(
+                            &app_state.sled_db,
+                            user_id,
+                            login_input_dto.remember_me,
+                        ) {
+                            Some(challenge) => (
                                 StatusCode::OK,
                                 [("code", "200"), ("msg", "ok")],
                                 Json(json!({
-                                    "token": token
+                                    "totp_required": true,
+                                    "challenge": challenge
                                 })),
-                            );
-                        }
-                        Err(e) => {
-                            log::error!("tbl_auth_user insert err: {}", e);
-                            return (
+                            ),
+                            None => (
                                 StatusCode::INTERNAL_SERVER_ERROR,
-                                [("code", "500"), ("msg", "tbl_auth_user insert err")],
+                                [("code", "500"), ("msg", "login challenge err")],
                                 Json(json!({})),
-                            );
-                        }
+                            ),
+                        };
                     }
+                    token_response(
+                        &app_state.sled_db,
+                        &tbl_auth_user,
+                        &ClientInfo::new(&socket_addr, &headers),
+                        login_input_dto.remember_me,
+                    )
+                } else {
+                    login_failed(
+                        &app_state,
+                        &attempt_keys,
+                        &login_input_dto.username,
+                        &src_ip,
+                        "wrong password",
+                    )
+                    .await
                 }
+            }
+            None => {
                 log::warn!("user {} not exists", login_input_dto.username);
-                (
-                    StatusCode::UNAUTHORIZED,
-                    [("code", "401"), ("msg", "UNAUTHORIZED")],
-                    Json(json!({})),
+                verify_dummy_password(&login_input_dto.password);
+                login_failed(
+                    &app_state,
+                    &attempt_keys,
+                    &login_input_dto.username,
+                    &src_ip,
+                    "user not exists",
                 )
+                .await
             }
         },
         Err(e) => {
@@ -119,34 +204,495 @@ async fn login(
     }
 }
 
-async fn logout(Path(token): Path<String>, app_state: State<AppState>) -> impl IntoResponse {
-    log::info!("{token} logout");
-    match app_state.sled_db.remove(&token) {
-        Ok(op) => match op {
-            Some(_) => {
-                return (StatusCode::OK, Json(json!({})));
-            }
-            None => {
-                log::warn!("token {token} not exists");
-                return (StatusCode::OK, Json(json!({})));
-            }
-        },
+#[derive(Deserialize)]
+struct LoginTotpInputDto {
+    challenge: String,
+    code: Option<String>,
+    recovery_code: Option<String>,
+}
+/// 两步验证的第二步, 使用验证码或恢复码换取token
+async fn login_totp(
+    app_state: State<AppState>,
+    ConnectInfo(socket_addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(login_totp_input_dto): Json<LoginTotpInputDto>,
+) -> impl IntoResponse {
+    let src_ip = socket_addr.ip().to_string();
+    let Some((user_id, remember_me)) =
+        totp::get_challenge_user(&app_state.sled_db, &login_totp_input_dto.challenge)
+    else {
+        log::warn!("login challenge invalid from {}", src_ip);
+        return (
+            StatusCode::UNAUTHORIZED,
+            [("code", "401"), ("msg", "UNAUTHORIZED")],
+            Json(json!({})),
+        );
+    };
+    let tbl_auth_user = match tbl_auth_user::Entity::find_by_id(user_id)
+        .one(&app_state.db_conn)
+        .await
+    {
+        Ok(Some(v)) if v.enabled && v.totp_enabled => v,
+        Ok(_) => {
+            log::warn!("user {} of login challenge not available", user_id);
+            totp::remove_challenge(&app_state.sled_db, &login_totp_input_dto.challenge);
+            return (
+                StatusCode::UNAUTHORIZED,
+                [("code", "401"), ("msg", "UNAUTHORIZED")],
+                Json(json!({})),
+            );
+        }
+        Err(e) => {
+            log::error!("tbl_auth_user find err: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_auth_user find err")],
+                Json(json!({})),
+            );
+        }
+    };
+    let attempt_keys = [
+        login_limit::ip_key(&src_ip),
+        login_limit::username_key(&tbl_auth_user.username),
+    ];
+    let locked_secs = login_limit::locked_secs(&app_state.sled_db, &attempt_keys);
+    if locked_secs > 0 {
+        log::warn!(
+            "login locked {} from {}, retry after {}s",
+            tbl_auth_user.username,
+            src_ip,
+            locked_secs
+        );
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            [("code", "429"), ("msg", "TOO_MANY_REQUESTS")],
+            Json(json!({
+                "retry_after": locked_secs
+            })),
+        );
+    }
+    let verified = if let Some(code) = &login_totp_input_dto.code {
+        totp::verify_code(&app_state.sled_db, &tbl_auth_user, code)
+    } else if let Some(recovery_code) = &login_totp_input_dto.recovery_code {
+        totp::verify_recovery_code(&app_state, &tbl_auth_user, recovery_code).await
+    } else {
+        false
+    };
+    if !verified {
+        totp::fail_challenge(&app_state.sled_db, &login_totp_input_dto.challenge);
+        return login_failed(
+            &app_state,
+            &attempt_keys,
+            &tbl_auth_user.username,
+            &src_ip,
+            "wrong totp code",
+        )
+        .await;
+    }
+    totp::remove_challenge(&app_state.sled_db, &login_totp_input_dto.challenge);
+    login_limit::clear_failures(
+        &app_state.sled_db,
+        &[login_limit::username_key(&tbl_auth_user.username)],
+    );
+    token_response(
+        &app_state.sled_db,
+        &tbl_auth_user,
+        &ClientInfo::new(&socket_addr, &headers),
+        remember_me,
+    )
+}
+
+pub async fn insert_log(app_state: &AppState, content: String) {
+    let tbl_log_am = tbl_log::ActiveModel {
+        content: Set(content),
+        ..Default::default()
+    };
+    if let Err(e) = tbl_log::Entity::insert(tbl_log_am)
+        .exec(&app_state.db_conn)
+        .await
+    {
+        log::error!("tbl_log insert err: {}", e);
+    }
+}
+
+/// 记录登录失败次数并写入操作日志, 返回401
+async fn login_failed(
+    app_state: &AppState,
+    attempt_keys: &[String],
+    username: &str,
+    src_ip: &str,
+    reason: &str,
+) -> (StatusCode, [(&'static str, &'static str); 2], Json<Value>) {
+    let failures = login_limit::record_failure(&app_state.sled_db, attempt_keys);
+    let locked_secs = login_limit::locked_secs(&app_state.sled_db, attempt_keys);
+    let mut content =
+        format!("login failed {username} from {src_ip}: {reason}, failures: {failures}");
+    if locked_secs > 0 {
+        content.push_str(&format!(", locked {locked_secs}s"));
+    }
+    insert_log(app_state, content).await;
+    (
+        StatusCode::UNAUTHORIZED,
+        [("code", "401"), ("msg", "UNAUTHORIZED")],
+        Json(json!({})),
+    )
+}
+
+/// 创建会话并返回access token和refresh token
+pub fn token_response(
+    sled_db: &sled::Db,
+    tbl_auth_user: &tbl_auth_user::Model,
+    client_info: &ClientInfo,
+    remember_me: bool,
+) -> (StatusCode, [(&'static str, &'static str); 2], Json<Value>) {
+    match session::issue(sled_db, tbl_auth_user, client_info, remember_me) {
+        Some(token_pair) => (
+            StatusCode::OK,
+            [("code", "200"), ("msg", "ok")],
+            Json(json!(token_pair)),
+        ),
+        None => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            [("code", "500"), ("msg", "issue token err")],
+            Json(json!({})),
+        ),
+    }
+}
+
+#[derive(Deserialize)]
+struct RefreshInputDto {
+    refresh_token: String,
+}
+/// 使用refresh token换取新的token, 重新读取用户状态和角色
+async fn refresh(
+    app_state: State<AppState>,
+    ConnectInfo(socket_addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(refresh_input_dto): Json<RefreshInputDto>,
+) -> impl IntoResponse {
+    let Some(user_id) =
+        session::refresh_token_user(&app_state.sled_db, &refresh_input_dto.refresh_token)
+    else {
+        return (
+            StatusCode::UNAUTHORIZED,
+            [("code", "401"), ("msg", "UNAUTHORIZED")],
+            Json(json!({})),
+        );
+    };
+    let tbl_auth_user = match tbl_auth_user::Entity::find_by_id(user_id)
+        .one(&app_state.db_conn)
+        .await
+    {
+        Ok(Some(v)) if v.enabled => v,
+        Ok(_) => {
+            log::warn!("user {} of session not available", user_id);
+            session::revoke_user(&app_state.sled_db, user_id);
+            return (
+                StatusCode::UNAUTHORIZED,
+                [("code", "401"), ("msg", "UNAUTHORIZED")],
+                Json(json!({})),
+            );
+        }
+        Err(e) => {
+            log::error!("tbl_auth_user find err: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_auth_user find err")],
+                Json(json!({})),
+            );
+        }
+    };
+    match session::refresh(
+        &app_state.sled_db,
+        &refresh_input_dto.refresh_token,
+        &tbl_auth_user,
+        &ClientInfo::new(&socket_addr, &headers),
+    ) {
+        Some(token_pair) => (
+            StatusCode::OK,
+            [("code", "200"), ("msg", "ok")],
+            Json(json!(token_pair)),
+        ),
+        None => (
+            StatusCode::UNAUTHORIZED,
+            [("code", "401"), ("msg", "UNAUTHORIZED")],
+            Json(json!({})),
+        ),
+    }
+}
+
+// 串行化初始化请求, 避免并发创建多个管理员
+static SETUP_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+async fn setup_status(app_state: State<AppState>) -> impl IntoResponse {
+    match tbl_auth_user::Entity::find()
+        .count(&app_state.db_conn)
+        .await
+    {
+        Ok(count) => (
+            StatusCode::OK,
+            [("code", "200"), ("msg", "ok")],
+            Json(json!({
+                "setup_required": count == 0
+            })),
+        ),
+        Err(e) => {
+            log::error!("tbl_auth_user count err: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_auth_user count err")],
+                Json(json!({})),
+            )
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Validate)]
+struct SetupInputDto {
+    #[validate(length(min = 1, max = 64))]
+    username: String,
+    #[validate(length(min = 8, max = 128))]
+    password: String,
+}
+/// 首次启动时创建管理员, 用户表非空后该接口永久关闭
+async fn setup(
+    app_state: State<AppState>,
+    ConnectInfo(socket_addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(setup_input_dto): Json<SetupInputDto>,
+) -> impl IntoResponse {
+    if let Err(e) = setup_input_dto.validate() {
+        log::warn!("setup input invalid: {}", e);
+        return (
+            StatusCode::BAD_REQUEST,
+            [("code", "400"), ("msg", "invalid input")],
+            Json(json!({})),
+        );
+    }
+    let _guard = SETUP_LOCK.lock().await;
+    match tbl_auth_user::Entity::find()
+        .count(&app_state.db_conn)
+        .await
+    {
+        Ok(0) => {}
+        Ok(_) => {
+            log::warn!("setup already done, reject {}", setup_input_dto.username);
+            return (
+                StatusCode::FORBIDDEN,
+                [("code", "403"), ("msg", "setup already done")],
+                Json(json!({})),
+            );
+        }
         Err(e) => {
-            log::error!("sled remove {token} err: {}", e);
-            return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({})));
+            log::error!("tbl_auth_user count err: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_auth_user count err")],
+                Json(json!({})),
+            );
         }
     }
+    let password_hash = match hash_password(&setup_input_dto.password) {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("hash password err: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "hash password err")],
+                Json(json!({})),
+            );
+        }
+    };
+    let tbl_auth_user_am = tbl_auth_user::ActiveModel {
+        username: Set(setup_input_dto.username.clone()),
+        password: Set(password_hash),
+        role: Set(Role::Admin.as_str().to_string()),
+        ..Default::default()
+    };
+    match tbl_auth_user::Entity::insert(tbl_auth_user_am)
+        .exec_with_returning(&app_state.db_conn)
+        .await
+    {
+        Ok(tbl_auth_user) => {
+            log::info!("setup admin {} success", setup_input_dto.username);
+            token_response(
+                &app_state.sled_db,
+                &tbl_auth_user,
+                &ClientInfo::new(&socket_addr, &headers),
+                false,
+            )
+        }
+        Err(e) => {
+            log::error!("tbl_auth_user insert err: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_auth_user insert err")],
+                Json(json!({})),
+            )
+        }
+    }
+}
+
+/// 使用Argon2id计算密码hash, 输出PHC格式字符串, 盐值随机生成
+pub fn hash_password(password: &str) -> Result<String, argon2::password_hash::Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    let password_hash = Argon2::default().hash_password(password.as_bytes(), &salt)?;
+    Ok(password_hash.to_string())
+}
+
+fn is_password_hash(stored: &str) -> bool {
+    stored.starts_with("$argon2")
 }
 
-static WHITE_API_SET: Lazy<HashSet<(Method, &'static str)>> = Lazy::new(|| {
-    HashSet::from([
-        (Method::POST, "/api/login"),
-        (Method::GET, "/api/pdf_articles"),
-        (Method::GET, "/api/home"),
-    ])
+// 用户不存在或已停用时用来校验的hash, 使响应时间与密码错误时一致
+static DUMMY_PASSWORD_HASH: Lazy<String> = Lazy::new(|| {
+    hash_password(&uuid::Uuid::new_v4().to_string()).unwrap_or_else(|e| {
+        log::error!("hash dummy password err: {}", e);
+        String::new()
+    })
 });
+
+/// 不关心结果, 只为消耗与正常校验相同的时间, 避免通过响应时间判断用户名是否存在
+fn verify_dummy_password(password: &str) {
+    let _ = verify_password(password, &DUMMY_PASSWORD_HASH);
+}
+
+/// 校验密码, 兼容尚未迁移的明文密码, 比较过程为常量时间
+pub fn verify_password(password: &str, stored: &str) -> bool {
+    if is_password_hash(stored) {
+        match PasswordHash::new(stored) {
+            Ok(password_hash) => Argon2::default()
+                .verify_password(password.as_bytes(), &password_hash)
+                .is_ok(),
+            Err(e) => {
+                log::error!("parse password hash err: {}", e);
+                false
+            }
+        }
+    } else {
+        password.as_bytes().ct_eq(stored.as_bytes()).into()
+    }
+}
+
+/// 退出登录, 删除会话并将其加入denylist, 已签发的access token立即失效
+async fn logout(
+    RequireRole(auth_user): RequireRole<READER>,
+    RequireSession(session_id): RequireSession,
+    app_state: State<AppState>,
+) -> impl IntoResponse {
+    log::info!("{} logout", auth_user.username);
+    session::revoke_session(&app_state.sled_db, &session_id.0);
+    (StatusCode::OK, Json(json!({})))
+}
+
 pub struct RequireAuth;
 
+/// 认证通过后由RequireAuth写入请求扩展, handler通过Extension<AuthUser>获取当前用户
+#[derive(Clone, Debug)]
+pub struct AuthUser {
+    pub id: i32,
+    pub username: String,
+    pub role: Role,
+}
+
+/// 当前请求所属的会话id, 用于退出登录等场景
+#[derive(Clone, Debug)]
+pub struct SessionId(pub String);
+
+/// 用户角色, 高级别角色拥有低级别角色的全部权限
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[repr(u8)]
+pub enum Role {
+    Reader = 0,
+    Editor = 1,
+    Admin = 2,
+}
+
+pub const READER: u8 = Role::Reader as u8;
+pub const EDITOR: u8 = Role::Editor as u8;
+pub const ADMIN: u8 = Role::Admin as u8;
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Reader => "reader",
+            Role::Editor => "editor",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reader" => Ok(Role::Reader),
+            "editor" => Ok(Role::Editor),
+            "admin" => Ok(Role::Admin),
+            _ => Err(format!("unknown role: {s}")),
+        }
+    }
+}
+
+/// 要求请求来自登录会话, 通过API token访问时返回403
+/// 用于退出登录、会话和凭据管理等接口
+pub struct RequireSession(pub SessionId);
+
+impl<S> FromRequestParts<S> for RequireSession
+where
+    S: Send + Sync,
+{
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        match parts.extensions.get::<SessionId>() {
+            Some(v) => Ok(Self(v.clone())),
+            None => {
+                log::warn!(
+                    "session required, api {} {}",
+                    parts.method,
+                    parts.uri.path()
+                );
+                Err(StatusCode::FORBIDDEN)
+            }
+        }
+    }
+}
+
+/// 路由级权限校验, 例如 `RequireRole(auth_user): RequireRole<ADMIN>`
+/// 未登录返回401, 角色不足返回403
+pub struct RequireRole<const ROLE: u8>(pub AuthUser);
+
+impl<S, const ROLE: u8> FromRequestParts<S> for RequireRole<ROLE>
+where
+    S: Send + Sync,
+{
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let auth_user = match parts.extensions.get::<AuthUser>() {
+            Some(v) => v.clone(),
+            None => {
+                log::warn!("no auth user, api {} {}", parts.method, parts.uri.path());
+                return Err(StatusCode::UNAUTHORIZED);
+            }
+        };
+        if (auth_user.role as u8) < ROLE {
+            log::warn!(
+                "forbidden {} {} {} {}",
+                auth_user.username,
+                auth_user.role.as_str(),
+                parts.method,
+                parts.uri.path()
+            );
+            return Err(StatusCode::FORBIDDEN);
+        }
+        Ok(Self(auth_user))
+    }
+}
+
 impl<S> FromRequestParts<S> for RequireAuth
 where
     S: Send + Sync + Deref<Target = AppState>,
@@ -165,62 +711,63 @@ where
             }
         };
 
-        if !parts.uri.path().starts_with("/api") {
-            log::info!(
-                "allow no api access: {} {} {}",
-                src_ip,
-                parts.method,
-                parts.uri.path()
-            );
-            return Ok(Self);
-        }
-        for (method, api) in WHITE_API_SET.iter() {
-            if method == parts.method {
-                if parts.uri.path().starts_with(api) {
-                    log::info!(
-                        "white list api {} {} {}",
-                        src_ip,
-                        parts.method,
-                        parts.uri.path()
-                    );
-                    return Ok(Self);
-                }
-            }
-        }
-
         log::info!("auth api {} {} {}", src_ip, parts.method, parts.uri.path());
         if let Some(authorization) = parts
             .headers
             .get(header::AUTHORIZATION)
             .and_then(|value| value.to_str().ok())
+            && let Some((_, token)) = authorization.split_once(" ")
         {
-            if let Some((_, token)) = authorization.split_once(" ") {
-                match state.sled_db.contains_key(token) {
-                    Ok(is_contains) => {
-                        if is_contains {
-                            if let Err(e) = state
-                                .sled_db
-                                .insert(token, &chrono::Utc::now().timestamp().to_be_bytes())
-                            {
-                                log::error!("sled db insert err: {}", e);
-                            }
-                            log::info!(
-                                "auth success {} {} {}",
-                                src_ip,
-                                parts.method,
-                                parts.uri.path()
-                            );
-                            return Ok(Self);
-                        } else {
-                            log::warn!("sled db not contains token: {}", token);
-                            return Err(StatusCode::UNAUTHORIZED);
-                        }
-                    }
-                    Err(e) => {
-                        log::error!("sled db contains key err: {}", e);
-                    }
-                }
+            if api_token::is_api_token(token) {
+                // API token没有会话, 无法访问需要RequireSession的账号管理接口
+                let matched_path = parts
+                    .extensions
+                    .get::<MatchedPath>()
+                    .map(|v| v.as_str().to_string())
+                    .unwrap_or_default();
+                let auth_user =
+                    api_token::authenticate(state, token, &parts.method, &matched_path).await?;
+                log::info!(
+                    "api token auth success {} {} {} {}",
+                    auth_user.username,
+                    src_ip,
+                    parts.method,
+                    parts.uri.path()
+                );
+                parts.extensions.insert(auth_user);
+                return Ok(Self);
             }
+            // 只校验签名、有效期和denylist, 用户和角色信息取自token, 不读写数据库
+            let Some(claims) = session::verify_access_token(&state.sled_db, token) else {
+                return Err(StatusCode::UNAUTHORIZED);
+            };
+            log::info!(
+                "auth success {} {} {} {}",
+                claims.username,
+                src_ip,
+                parts.method,
+                parts.uri.path()
+            );
+            parts.extensions.insert(SessionId(claims.sid));
+            parts.extensions.insert(AuthUser {
+                id: claims.sub,
+                username: claims.username,
+                role: claims.role,
+            });
+            return Ok(Self);
+        }
+        // 没有token时使用TLS握手阶段校验过的客户端证书
+        if let Some(ClientCert(Some(common_name))) = parts.extensions.get::<ClientCert>() {
+            let auth_user = mtls::authenticate(state, common_name).await?;
+            log::info!(
+                "client cert auth success {} {} {} {}",
+                auth_user.username,
+                src_ip,
+                parts.method,
+                parts.uri.path()
+            );
+            parts.extensions.insert(auth_user);
+            return Ok(Self);
         }
         log::warn!(
             "not has auth info, api {} {} {}",
@@ -233,30 +780,18 @@ where
 }
 
 pub async fn token_expired_task(sled_db: sled::Db) -> anyhow::Result<()> {
-    let expired_time = 6 * 60 * 60; // 6小时
+    session::init(&sled_db)?;
     tokio::spawn(async move {
         log::info!("token_expired_task running");
         loop {
-            for r in sled_db.iter() {
-                if let Ok((k, v)) = r {
-                    let ts_now = chrono::Utc::now().timestamp();
-                    let ts = match v.as_ref().try_into() {
-                        Ok(bytes) => i64::from_be_bytes(bytes),
-                        Err(e) => {
-                            log::error!("v.as_ref().try_into() err: {}", e);
-                            0
-                        }
-                    };
-
-                    if ts_now - ts >= expired_time {
-                        if let Err(e) = sled_db.remove(&k) {
-                            log::error!("sled remove err: {}", e);
-                        }
-                        log::info!("token expired {}", String::from_utf8_lossy(&k));
-                    }
-                }
-            }
-            tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
+            session::purge_expired(&sled_db);
+            login_limit::purge_expired(&sled_db);
+            totp::purge_expired(&sled_db);
+            oidc::purge_expired(&sled_db);
+            tokio::time::sleep(tokio::time::Duration::from_secs(
+                SERVER_TOML.auth.sweep_interval,
+            ))
+            .await;
         }
     });
     Ok(())
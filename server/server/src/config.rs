@@ -1,6 +1,8 @@
 use once_cell::sync::Lazy;
 use serde::Deserialize;
 
+use crate::auth::Role;
+
 pub static SERVER_TOML: Lazy<ServerToml> = Lazy::new(|| {
     config::Config::builder()
         .add_source(config::File::with_name("./config/server.toml"))
@@ -13,9 +15,122 @@ pub static SERVER_TOML: Lazy<ServerToml> = Lazy::new(|| {
 #[derive(Debug, Deserialize)]
 pub struct ServerToml {
     pub server: Server,
+    #[serde(default)]
+    pub auth: Auth,
+    // 未配置时不启用OIDC登录
+    pub oidc: Option<Oidc>,
+    // 未配置时不校验客户端证书
+    pub mtls: Option<Mtls>,
+    #[serde(default)]
+    pub trash: Trash,
+    #[serde(default)]
+    pub article: Article,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct Server {
     pub addr: String,
 }
+
+/// 会话相关配置, 时间单位均为秒, 未配置时使用默认值
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Auth {
+    // 会话闲置超过该时长后失效
+    pub idle_timeout: i64,
+    // 会话自创建起的最长有效期, 持续使用也会失效
+    pub absolute_timeout: i64,
+    // 清理过期会话的间隔
+    pub sweep_interval: u64,
+    // 登录时勾选"记住我"的会话有效期, 同时作为闲置时长和最长有效期
+    pub remember_me_timeout: i64,
+}
+
+impl Default for Auth {
+    fn default() -> Self {
+        Self {
+            idle_timeout: 6 * 60 * 60,
+            absolute_timeout: 7 * 24 * 60 * 60,
+            sweep_interval: 60,
+            remember_me_timeout: 30 * 24 * 60 * 60,
+        }
+    }
+}
+
+/// OIDC登录配置, 使用授权码模式和PKCE
+#[derive(Clone, Debug, Deserialize)]
+pub struct Oidc {
+    // 身份提供方地址, 通过{issuer_url}/.well-known/openid-configuration发现端点
+    // 发现结果在首次登录时缓存, 身份提供方更换签名密钥后需重启服务
+    pub issuer_url: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    // 回调地址, 需与身份提供方中登记的一致, 例如 https://host:8080/api/oidc/callback
+    pub redirect_url: String,
+    // 自动创建用户时作为用户名的claim: sub, preferred_username或email
+    // 登录时只按sub匹配tbl_auth_user.oidc_subject, 不按用户名匹配已有用户
+    #[serde(default = "default_username_claim")]
+    pub username_claim: String,
+    // 没有关联该sub的用户时是否自动创建, 用户名已被占用时拒绝登录
+    #[serde(default)]
+    pub auto_provision: bool,
+    // 自动创建用户的角色
+    #[serde(default = "default_role")]
+    pub default_role: Role,
+}
+
+/// 客户端证书认证配置, 证书主题CN对应tbl_auth_user.username
+#[derive(Debug, Deserialize)]
+pub struct Mtls {
+    // 信任的客户端CA证书, PEM格式, 可包含多张证书
+    pub client_ca: String,
+    // 为true时拒绝不带客户端证书的TLS连接, 否则仍可使用token登录
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// 回收站配置, 未配置时使用默认值
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Trash {
+    // 删除超过该天数后物理删除, 为0时不自动清理
+    pub retention_days: u32,
+    // 清理任务的执行间隔, 单位秒
+    pub purge_interval: u64,
+}
+
+impl Default for Trash {
+    fn default() -> Self {
+        Self {
+            retention_days: 30,
+            purge_interval: 60 * 60,
+        }
+    }
+}
+
+/// 文章接口配置, 未配置时使用默认值
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Article {
+    // 列表中正文摘要的字符数
+    pub preview_length: usize,
+    // 删除文章时, 把为该文章上传且没有被其他文章使用的附件一起放入回收站; 为false时保留附件
+    pub trash_orphan_files: bool,
+}
+
+impl Default for Article {
+    fn default() -> Self {
+        Self {
+            preview_length: 120,
+            trash_orphan_files: true,
+        }
+    }
+}
+
+fn default_username_claim() -> String {
+    "sub".to_string()
+}
+
+fn default_role() -> Role {
+    Role::Reader
+}
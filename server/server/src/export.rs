@@ -0,0 +1,537 @@
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    sync::{Arc, Mutex},
+};
+
+use anyhow::anyhow;
+use axum::{
+    Json, Router,
+    body::Body,
+    extract::{Query, State},
+    http::{StatusCode, header},
+    response::IntoResponse,
+    routing::get,
+};
+use chrono::{Datelike, NaiveDate, NaiveDateTime, SecondsFormat, Timelike, Utc};
+use entity::tbl_article;
+use sea_orm::{
+    ColumnTrait, DbErr, EntityTrait, Order, QueryFilter, QueryOrder, QuerySelect, sea_query::Expr,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::mpsc::{self, Sender};
+use tokio_stream::wrappers::ReceiverStream;
+use zip::{
+    CompressionMethod, ZipWriter,
+    write::{SimpleFileOptions, StreamWriter},
+};
+
+use crate::{
+    AppState,
+    article::ARTICLE_DATE,
+    auth::{self, READER, RequireRole},
+    pdf_book::{self, BookArticle, PdfWriter, TocEntry},
+    tag::{self, TagMode},
+};
+
+pub fn routers(state: AppState) -> Router {
+    Router::new()
+        .route("/articles/export", get(export))
+        .with_state(state)
+}
+
+// 每次从数据库读取的文章数, 导出时内存中最多保留这么多篇
+const CHUNK_SIZE: usize = 100;
+
+#[derive(Deserialize, Debug, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum ExportFormat {
+    // 按日期命名、带YAML头的Markdown文件打包
+    #[default]
+    Zip,
+    Json,
+    // 带目录的PDF书
+    Pdf,
+}
+
+#[derive(Deserialize, Debug)]
+struct ExportInputDto {
+    #[serde(default)]
+    format: ExportFormat,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    // 逗号分隔的标签
+    tags: Option<String>,
+    #[serde(default)]
+    tag_mode: TagMode,
+}
+
+#[derive(Serialize, Debug)]
+struct ExportArticle {
+    id: i32,
+    title: String,
+    // 文章日期, 优先取entry_date, 普通文章取创建日期
+    date: String,
+    entry_date: Option<String>,
+    tags: Vec<String>,
+    created_at: String,
+    updated_at: String,
+    content: String,
+}
+
+impl ExportArticle {
+    fn new(model: tbl_article::Model, tags: Vec<String>) -> Self {
+        Self {
+            id: model.id,
+            title: model.title,
+            date: model
+                .entry_date
+                .unwrap_or_else(|| model.created_at.date())
+                .to_string(),
+            entry_date: model.entry_date.map(|v| v.to_string()),
+            tags,
+            created_at: rfc3339(&model.created_at),
+            updated_at: rfc3339(&model.updated_at),
+            content: model.content,
+        }
+    }
+
+    /// 带YAML头的Markdown, 字符串按JSON转义, 也是合法的YAML
+    fn markdown(&self) -> String {
+        let quote = |v: &str| serde_json::to_string(v).unwrap_or_default();
+        let mut markdown = String::with_capacity(self.content.len() + 256);
+        markdown.push_str("---\n");
+        markdown.push_str(&format!("id: {}\n", self.id));
+        markdown.push_str(&format!("title: {}\n", quote(&self.title)));
+        markdown.push_str(&format!("date: {}\n", self.date));
+        if let Some(entry_date) = &self.entry_date {
+            markdown.push_str(&format!("entry_date: {entry_date}\n"));
+        }
+        markdown.push_str(&format!(
+            "tags: [{}]\n",
+            self.tags
+                .iter()
+                .map(|v| quote(v))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+        markdown.push_str(&format!("created_at: {}\n", self.created_at));
+        markdown.push_str(&format!("updated_at: {}\n", self.updated_at));
+        markdown.push_str("---\n\n");
+        markdown.push_str(&self.content);
+        if !self.content.ends_with('\n') {
+            markdown.push('\n');
+        }
+        markdown
+    }
+}
+
+fn rfc3339(v: &NaiveDateTime) -> String {
+    v.and_utc().to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// 导出自己的文章, 先查出全部文章id, 再分批读取并边生成边发送, 不在内存中生成整个文件
+async fn export(
+    Query(export_input_dto): Query<ExportInputDto>,
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<READER>,
+) -> impl IntoResponse {
+    let mut select = tbl_article::Entity::find()
+        .select_only()
+        .column(tbl_article::Column::Id)
+        .filter(tbl_article::Column::UserId.eq(auth_user.id))
+        .filter(tbl_article::Column::DeletedAt.is_null());
+    if let Some(from) = export_input_dto.from {
+        select = select.filter(Expr::cust_with_values(
+            format!("{ARTICLE_DATE} >= ?"),
+            [from.to_string()],
+        ));
+    }
+    if let Some(to) = export_input_dto.to {
+        select = select.filter(Expr::cust_with_values(
+            format!("{ARTICLE_DATE} <= ?"),
+            [to.to_string()],
+        ));
+    }
+    if let Some(tags) = &export_input_dto.tags {
+        let tags = tag::parse_tags(tags);
+        if !tags.is_empty() {
+            select = select.filter(tag::article_filter(&tags, export_input_dto.tag_mode));
+        }
+    }
+    let article_ids: Vec<i32> = match select
+        .order_by(Expr::cust(ARTICLE_DATE), Order::Asc)
+        .order_by_asc(tbl_article::Column::Id)
+        .into_tuple()
+        .all(&app_state.db_conn)
+        .await
+    {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("tbl_article find err: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_article find err")],
+                Json(json!({})),
+            )
+                .into_response();
+        }
+    };
+    let format = export_input_dto.format;
+    auth::insert_log(
+        &app_state,
+        format!(
+            "{} export {} articles as {:?}",
+            auth_user.username,
+            article_ids.len(),
+            format
+        ),
+    )
+    .await;
+    let (content_type, extension) = match format {
+        ExportFormat::Zip => ("application/zip", "zip"),
+        ExportFormat::Json => ("application/json", "json"),
+        ExportFormat::Pdf => ("application/pdf", "pdf"),
+    };
+    let filename = format!(
+        "articles-{}.{}",
+        Utc::now().format("%Y%m%d%H%M%S"),
+        extension
+    );
+    let (tx, rx) = mpsc::channel(4);
+    let username = auth_user.username;
+    tokio::spawn(async move {
+        let result = match format {
+            ExportFormat::Zip => write_zip(&app_state, &article_ids, &tx).await,
+            ExportFormat::Json => write_json(&app_state, &article_ids, &tx).await,
+            ExportFormat::Pdf => write_pdf(&app_state, &article_ids, &username, &tx).await,
+        };
+        // 响应头已经发出, 只能中断传输让客户端知道文件不完整
+        if let Err(e) = result {
+            log::error!("user {} export {:?} err: {}", username, format, e);
+            let _ = tx.send(Err(io::Error::other(e.to_string()))).await;
+        }
+    });
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{filename}\""),
+            ),
+        ],
+        Body::from_stream(ReceiverStream::new(rx)),
+    )
+        .into_response()
+}
+
+type ChunkSender = Sender<io::Result<Vec<u8>>>;
+
+async fn send(tx: &ChunkSender, bytes: Vec<u8>) -> anyhow::Result<()> {
+    if bytes.is_empty() {
+        return Ok(());
+    }
+    tx.send(Ok(bytes))
+        .await
+        .map_err(|_| anyhow!("client disconnected"))
+}
+
+/// 按id顺序读取一批文章及其标签, 期间被删除的文章跳过
+async fn load_chunk(
+    app_state: &AppState,
+    article_ids: &[i32],
+) -> Result<Vec<ExportArticle>, DbErr> {
+    let mut models: HashMap<i32, tbl_article::Model> = tbl_article::Entity::find()
+        .filter(tbl_article::Column::Id.is_in(article_ids.iter().copied()))
+        .filter(tbl_article::Column::DeletedAt.is_null())
+        .all(&app_state.db_conn)
+        .await?
+        .into_iter()
+        .map(|model| (model.id, model))
+        .collect();
+    let mut tags = tag::article_tags(&app_state.db_conn, article_ids).await?;
+    Ok(article_ids
+        .iter()
+        .filter_map(|id| {
+            let model = models.remove(id)?;
+            Some(ExportArticle::new(
+                model,
+                tags.remove(id).unwrap_or_default(),
+            ))
+        })
+        .collect())
+}
+
+async fn write_json(
+    app_state: &AppState,
+    article_ids: &[i32],
+    tx: &ChunkSender,
+) -> anyhow::Result<()> {
+    let mut first = true;
+    send(tx, b"[".to_vec()).await?;
+    for chunk in article_ids.chunks(CHUNK_SIZE) {
+        let mut bytes = Vec::new();
+        for export_article in load_chunk(app_state, chunk).await? {
+            bytes.extend_from_slice(if first { b"\n" } else { b",\n" });
+            serde_json::to_writer(&mut bytes, &export_article)?;
+            first = false;
+        }
+        send(tx, bytes).await?;
+    }
+    send(tx, b"\n]\n".to_vec()).await
+}
+
+/// 压缩包的输出缓冲区, 每写完一篇取走已生成的数据发送, 内存中只保留压缩包的目录
+#[derive(Clone, Default)]
+struct ZipBuf(Arc<Mutex<Vec<u8>>>);
+
+impl ZipBuf {
+    fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut *self.0.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+impl Write for ZipBuf {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// 压缩包中文件的修改时间, 超出DOS时间的范围(1980-2107年)时取边界
+fn zip_date_time(v: NaiveDateTime) -> zip::DateTime {
+    let min = NaiveDate::from_ymd_opt(1980, 1, 1).unwrap_or_default();
+    let max = NaiveDate::from_ymd_opt(2107, 12, 31)
+        .and_then(|v| v.and_hms_opt(23, 59, 58))
+        .unwrap_or_default();
+    let v = v.clamp(min.and_time(Default::default()), max);
+    zip::DateTime::from_date_and_time(
+        v.year() as u16,
+        v.month() as u8,
+        v.day() as u8,
+        v.hour() as u8,
+        v.minute() as u8,
+        v.second() as u8,
+    )
+    .unwrap_or_default()
+}
+
+/// 每篇文章一个文件, 按年份分目录, 文件名为日期加文章id
+async fn write_zip(
+    app_state: &AppState,
+    article_ids: &[i32],
+    tx: &ChunkSender,
+) -> anyhow::Result<()> {
+    let zip_buf = ZipBuf::default();
+    let mut zip_writer = ZipWriter::new_stream(zip_buf.clone());
+    for chunk in article_ids.chunks(CHUNK_SIZE) {
+        for export_article in load_chunk(app_state, chunk).await? {
+            add_zip_file(&mut zip_writer, &export_article)?;
+            send(tx, zip_buf.take()).await?;
+        }
+    }
+    zip_writer.finish()?;
+    send(tx, zip_buf.take()).await
+}
+
+fn add_zip_file(
+    zip_writer: &mut ZipWriter<StreamWriter<ZipBuf>>,
+    export_article: &ExportArticle,
+) -> anyhow::Result<()> {
+    let name = format!(
+        "{}/{}-{}.md",
+        &export_article.date[..4],
+        export_article.date,
+        export_article.id
+    );
+    let modified = chrono::DateTime::parse_from_rfc3339(&export_article.updated_at)
+        .map(|v| v.naive_utc())
+        .unwrap_or_default();
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .last_modified_time(zip_date_time(modified))
+        .unix_permissions(0o644);
+    zip_writer.start_file(name, options)?;
+    zip_writer.write_all(export_article.markdown().as_bytes())?;
+    Ok(())
+}
+
+/// 文章页边生成边发送, 目录页最后写出并排在最前面; 目录页数按文章数预先算出, 以确定页码
+async fn write_pdf(
+    app_state: &AppState,
+    article_ids: &[i32],
+    username: &str,
+    tx: &ChunkSender,
+) -> anyhow::Result<()> {
+    let toc_page_count = pdf_book::toc_page_count(article_ids.len());
+    let mut toc_entries = Vec::new();
+    let mut page_no = toc_page_count + 1;
+    let mut pdf_writer = PdfWriter::new();
+    for chunk in article_ids.chunks(CHUNK_SIZE) {
+        for export_article in load_chunk(app_state, chunk).await? {
+            let pages = pdf_book::layout_article(&book_article(&export_article));
+            toc_entries.push(TocEntry {
+                date: export_article.date.clone(),
+                title: export_article.title.clone(),
+                page_no,
+            });
+            for (i, page) in pages.iter().enumerate() {
+                let page_id = pdf_writer.add_page(page, page_no);
+                if i == 0 {
+                    pdf_writer.add_outline(
+                        &format!("{} {}", export_article.date, export_article.title),
+                        page_id,
+                    );
+                }
+                page_no += 1;
+            }
+            send(tx, pdf_writer.take()).await?;
+        }
+    }
+    // 导出期间有文章被删除时目录变短, 用空页补齐
+    let mut toc_pages = pdf_book::layout_toc(&toc_entries);
+    toc_pages.resize_with(toc_page_count.max(toc_pages.len()), Default::default);
+    for (i, page) in toc_pages.iter().enumerate() {
+        pdf_writer.add_front_page(page, i + 1);
+    }
+    send(tx, pdf_writer.finish(&format!("{username} 的文章"))).await
+}
+
+fn book_article(export_article: &ExportArticle) -> BookArticle<'_> {
+    BookArticle {
+        date: &export_article.date,
+        title: &export_article.title,
+        tags: &export_article.tags,
+        content: &export_article.content,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use zip::ZipArchive;
+
+    use super::*;
+
+    fn datetime(v: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(v, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    fn export_article(id: i32, date: &str, updated_at: &str, content: &str) -> ExportArticle {
+        ExportArticle {
+            id,
+            title: format!("标题 {id}"),
+            date: date.to_string(),
+            entry_date: Some(date.to_string()),
+            tags: vec!["旅行".to_string(), "a\"b".to_string()],
+            created_at: updated_at.to_string(),
+            updated_at: updated_at.to_string(),
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn zip_round_trip() {
+        let export_articles = [
+            export_article(1, "2025-12-31", "2026-01-02T03:04:05Z", "第一篇\n"),
+            export_article(
+                2,
+                "2026-10-18",
+                "2026-10-18T23:59:59Z",
+                &"长文".repeat(50000),
+            ),
+            export_article(3, "1975-06-01", "1975-06-01T00:00:00Z", ""),
+        ];
+        let zip_buf = ZipBuf::default();
+        let mut zip_writer = ZipWriter::new_stream(zip_buf.clone());
+        // 模拟边写边发送
+        let mut bytes = Vec::new();
+        for export_article in &export_articles {
+            add_zip_file(&mut zip_writer, export_article).unwrap();
+            bytes.extend(zip_buf.take());
+        }
+        zip_writer.finish().unwrap();
+        bytes.extend(zip_buf.take());
+
+        let mut archive = ZipArchive::new(io::Cursor::new(bytes)).unwrap();
+        assert_eq!(archive.len(), export_articles.len());
+        let expected = [
+            ("2025/2025-12-31-1.md", datetime("2026-01-02 03:04:04")),
+            ("2026/2026-10-18-2.md", datetime("2026-10-18 23:59:58")),
+            ("1975/1975-06-01-3.md", datetime("1980-01-01 00:00:00")),
+        ];
+        for (i, (name, modified)) in expected.iter().enumerate() {
+            let mut file = archive.by_index(i).unwrap();
+            assert_eq!(file.name(), *name);
+            assert_eq!(file.compression(), CompressionMethod::Deflated);
+            assert_eq!(file.unix_mode(), Some(0o100644));
+            let last_modified = file.last_modified().unwrap();
+            assert_eq!(
+                (
+                    last_modified.year(),
+                    last_modified.month(),
+                    last_modified.day(),
+                    last_modified.hour(),
+                    last_modified.minute(),
+                    last_modified.second()
+                ),
+                (
+                    modified.year() as u16,
+                    modified.month() as u8,
+                    modified.day() as u8,
+                    modified.hour() as u8,
+                    modified.minute() as u8,
+                    modified.second() as u8
+                )
+            );
+            let mut content = String::new();
+            file.read_to_string(&mut content).unwrap();
+            assert_eq!(content, export_articles[i].markdown());
+        }
+    }
+
+    #[test]
+    fn zip_date_time_range() {
+        let check = |v: &str, expected: (u16, u8, u8, u8, u8, u8)| {
+            let zip_date_time = zip_date_time(datetime(v));
+            assert_eq!(
+                (
+                    zip_date_time.year(),
+                    zip_date_time.month(),
+                    zip_date_time.day(),
+                    zip_date_time.hour(),
+                    zip_date_time.minute(),
+                    zip_date_time.second()
+                ),
+                expected,
+                "{v}"
+            );
+        };
+        check("1970-01-01 00:00:00", (1980, 1, 1, 0, 0, 0));
+        check("1979-12-31 23:59:59", (1980, 1, 1, 0, 0, 0));
+        check("1980-01-01 00:00:00", (1980, 1, 1, 0, 0, 0));
+        check("2024-02-29 12:34:56", (2024, 2, 29, 12, 34, 56));
+        check("2107-12-31 23:59:58", (2107, 12, 31, 23, 59, 58));
+        check("2108-01-01 00:00:00", (2107, 12, 31, 23, 59, 58));
+        check("2200-06-15 08:00:00", (2107, 12, 31, 23, 59, 58));
+    }
+
+    #[test]
+    fn markdown_front_matter() {
+        let markdown = export_article(7, "2026-10-18", "2026-10-18T01:02:03Z", "正文").markdown();
+        assert_eq!(
+            markdown,
+            "---\nid: 7\ntitle: \"标题 7\"\ndate: 2026-10-18\nentry_date: 2026-10-18\ntags: [\"旅行\", \"a\\\"b\"]\ncreated_at: 2026-10-18T01:02:03Z\nupdated_at: 2026-10-18T01:02:03Z\n---\n\n正文\n"
+        );
+    }
+}
@@ -1,29 +1,84 @@
 use axum::{
     Json, Router,
     extract::{DefaultBodyLimit, Multipart, Path, Query, State},
-    http::{HeaderMap, HeaderValue, StatusCode, header},
-    response::IntoResponse,
+    http::{HeaderMap, HeaderValue, Method, StatusCode, header},
+    response::{IntoResponse, Response},
     routing::{get, post},
 };
-use entity::tbl_file;
+use entity::{tbl_article, tbl_file};
 use sea_orm::{
     ActiveValue::Set, ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder,
+    sea_query::Expr,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use validator::Validate;
 
-use crate::AppState;
+use crate::{
+    AppState, attachment,
+    auth::{self, AuthUser, EDITOR, PublicRoute, READER, RequireRole, SessionId},
+    session,
+};
 
 pub fn routers(state: AppState) -> Router {
     Router::new()
         .route("/files", post(upload).get(query))
-        .route("/files/{id}", get(download))
+        .route("/files/{id}", get(download).delete(delete))
         .layer(DefaultBodyLimit::max(1024 * 1024 * 1024 * 4))
         .with_state(state)
 }
 
-async fn upload(app_state: State<AppState>, mut multipart: Multipart) -> impl IntoResponse {
+/// 通过地址中的签名授权, 供文章中的<img>和链接使用
+pub fn public_routes() -> Vec<PublicRoute> {
+    vec![(Method::GET, "/files/{id}/content", get(signed_download))]
+}
+
+/// 文章正文中file:ID的地址; 登录会话中使用短期签名地址, API token调用方可自行携带Authorization头
+pub fn file_url(auth_user: &AuthUser, session_id: Option<&SessionId>, file_id: i32) -> String {
+    session_id
+        .and_then(|session_id| session::sign_file_token(auth_user.id, &session_id.0, file_id))
+        .map(|token| format!("/api/files/{file_id}/content?token={token}"))
+        .unwrap_or_else(|| format!("/api/files/{file_id}"))
+}
+
+#[derive(Deserialize, Debug)]
+struct UploadInputDto {
+    // 从文章编辑页上传时关联到该文章
+    article_id: Option<i32>,
+}
+
+async fn upload(
+    app_state: State<AppState>,
+    RequireRole(auth_user): RequireRole<EDITOR>,
+    Query(upload_input_dto): Query<UploadInputDto>,
+    mut multipart: Multipart,
+) -> impl IntoResponse {
+    if let Some(article_id) = upload_input_dto.article_id {
+        match tbl_article::Entity::find_by_id(article_id)
+            .filter(tbl_article::Column::UserId.eq(auth_user.id))
+            .filter(tbl_article::Column::DeletedAt.is_null())
+            .one(&app_state.db_conn)
+            .await
+        {
+            Ok(Some(_)) => {}
+            Ok(None) => {
+                log::warn!("upload to article {} not found", article_id);
+                return (
+                    StatusCode::BAD_REQUEST,
+                    [("code", "400"), ("msg", "article not found")],
+                    Json(json!({})),
+                );
+            }
+            Err(e) => {
+                log::error!("tbl_article find err: {}", e);
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    [("code", "500"), ("msg", "tbl_article find err")],
+                    Json(json!({})),
+                );
+            }
+        }
+    }
     let mut file_ids = Vec::new();
     while let Some(field) = match multipart.next_field().await {
         Ok(v) => v,
@@ -42,7 +97,7 @@ async fn upload(app_state: State<AppState>, mut multipart: Multipart) -> impl In
         let file_name = field.file_name().unwrap_or_default().to_string();
         log::info!("file_name: {file_name}");
 
-        if let Some(content_type) = field.content_type() {
+        if let Some(content_type) = field.content_type().map(str::to_string) {
             log::info!("content_type: {content_type}");
             let content_bytes = if content_type.eq("application/json") {
                 match field.text().await {
@@ -72,6 +127,9 @@ async fn upload(app_state: State<AppState>, mut multipart: Multipart) -> impl In
             let tbl_file_am = tbl_file::ActiveModel {
                 name: Set(file_name),
                 content: Set(content_bytes),
+                content_type: Set(Some(content_type)),
+                user_id: Set(Some(auth_user.id)),
+                article_id: Set(upload_input_dto.article_id),
                 ..Default::default()
             };
             match tbl_file::Entity::insert(tbl_file_am)
@@ -92,6 +150,17 @@ async fn upload(app_state: State<AppState>, mut multipart: Multipart) -> impl In
             }
         }
     }
+    if let Some(article_id) = upload_input_dto.article_id
+        && let Err(e) =
+            attachment::link_files(&app_state.db_conn, article_id, auth_user.id, &file_ids).await
+    {
+        log::error!("tbl_article_file insert err: {}", e);
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            [("code", "500"), ("msg", "tbl_article_file insert err")],
+            Json(json!({})),
+        );
+    }
 
     (
         StatusCode::OK,
@@ -117,15 +186,18 @@ struct QueryOutputDto {
 }
 async fn query(
     app_state: State<AppState>,
+    RequireRole(auth_user): RequireRole<READER>,
     Query(query_input_dto): Query<QueryInputDto>,
 ) -> impl IntoResponse {
-    let mut select = tbl_file::Entity::find();
+    let mut select = tbl_file::Entity::find()
+        .filter(tbl_file::Column::DeletedAt.is_null())
+        .filter(attachment::file_visible_to(auth_user.id));
 
-    if let Some(name) = query_input_dto.name {
-        if !name.is_empty() {
-            let like_pattern = format!("%{name}%");
-            select = select.filter(tbl_file::Column::Name.like(like_pattern));
-        }
+    if let Some(name) = query_input_dto.name
+        && !name.is_empty()
+    {
+        let like_pattern = format!("%{name}%");
+        select = select.filter(tbl_file::Column::Name.like(like_pattern));
     }
     let paginator = select
         .order_by_desc(tbl_file::Column::CreatedAt)
@@ -198,23 +270,79 @@ async fn query(
     )
 }
 
-async fn download(Path(id): Path<i32>, State(app_state): State<AppState>) -> impl IntoResponse {
+async fn download(
+    Path(id): Path<i32>,
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<READER>,
+) -> impl IntoResponse {
+    file_response(&app_state, id, auth_user.id).await
+}
+
+#[derive(Deserialize)]
+struct SignedDownloadInputDto {
+    token: String,
+}
+/// 签名只证明签发时有权查看, 仍按当前的可见性校验
+async fn signed_download(
+    Path(id): Path<i32>,
+    State(app_state): State<AppState>,
+    Query(signed_download_input_dto): Query<SignedDownloadInputDto>,
+) -> Response {
+    match session::verify_file_token(&app_state.sled_db, &signed_download_input_dto.token, id) {
+        Some(user_id) => file_response(&app_state, id, user_id).await,
+        None => (
+            StatusCode::UNAUTHORIZED,
+            [("code", "401"), ("msg", "UNAUTHORIZED")],
+            Json(json!({})),
+        )
+            .into_response(),
+    }
+}
+
+async fn file_response(app_state: &AppState, id: i32, user_id: i32) -> Response {
+    // 无权查看的文件与不存在的返回相同结果
     match tbl_file::Entity::find_by_id(id)
+        .filter(tbl_file::Column::DeletedAt.is_null())
+        .filter(attachment::file_visible_to(user_id))
         .one(&app_state.db_conn)
         .await
     {
         Ok(tbl_file_op) => match tbl_file_op {
             Some(tbl_file) => {
                 let mut headers = HeaderMap::new();
-                headers.insert(
-                    header::CONTENT_TYPE,
-                    HeaderValue::from_static("application/octet-stream"),
-                );
-                headers.insert(
-                    header::CONTENT_DISPOSITION,
-                    HeaderValue::from_str(&format!("attachment; filename=\"{}\"", tbl_file.name))
-                        .unwrap_or_else(|_| HeaderValue::from_static("attachment")),
-                );
+                // 图片直接显示, 以便文章中通过file:ID引用; 其他文件仍作为附件下载
+                match tbl_file
+                    .content_type
+                    .as_deref()
+                    .filter(|v| v.starts_with("image/") && !v.contains("svg"))
+                    .and_then(|v| HeaderValue::from_str(v).ok())
+                {
+                    Some(content_type) => {
+                        headers.insert(header::CONTENT_TYPE, content_type);
+                        headers.insert(
+                            header::CONTENT_DISPOSITION,
+                            HeaderValue::from_static("inline"),
+                        );
+                        headers.insert(
+                            header::X_CONTENT_TYPE_OPTIONS,
+                            HeaderValue::from_static("nosniff"),
+                        );
+                    }
+                    None => {
+                        headers.insert(
+                            header::CONTENT_TYPE,
+                            HeaderValue::from_static("application/octet-stream"),
+                        );
+                        headers.insert(
+                            header::CONTENT_DISPOSITION,
+                            HeaderValue::from_str(&format!(
+                                "attachment; filename=\"{}\"",
+                                tbl_file.name
+                            ))
+                            .unwrap_or_else(|_| HeaderValue::from_static("attachment")),
+                        );
+                    }
+                }
                 (StatusCode::OK, headers, tbl_file.content).into_response()
             }
             None => {
@@ -238,3 +366,53 @@ async fn download(Path(id): Path<i32>, State(app_state): State<AppState>) -> imp
         }
     }
 }
+
+async fn delete(
+    Path(id): Path<i32>,
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<EDITOR>,
+) -> impl IntoResponse {
+    auth::insert_log(
+        &app_state,
+        format!("{} delete file {}", auth_user.username, id),
+    )
+    .await;
+    // 只标记删除时间放入回收站, 由trash模块恢复或到期清理
+    match tbl_file::Entity::update_many()
+        .col_expr(
+            tbl_file::Column::DeletedAt,
+            Expr::value(chrono::Utc::now().naive_utc()),
+        )
+        .filter(tbl_file::Column::Id.eq(id))
+        .filter(tbl_file::Column::DeletedAt.is_null())
+        .filter(attachment::file_managed_by(&auth_user))
+        .exec(&app_state.db_conn)
+        .await
+    {
+        Ok(update_result) => {
+            if update_result.rows_affected == 1 {
+                log::info!("delete file {id} success");
+                (
+                    StatusCode::OK,
+                    [("code", "200"), ("msg", "ok")],
+                    Json(json!({})),
+                )
+            } else {
+                log::warn!("not find file_id: {}", id);
+                (
+                    StatusCode::NOT_FOUND,
+                    [("code", "404"), ("msg", "not find file id")],
+                    Json(json!({})),
+                )
+            }
+        }
+        Err(e) => {
+            log::error!("delete file {id} err: {e}");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "delete db err")],
+                Json(json!({})),
+            )
+        }
+    }
+}
@@ -1,23 +1,45 @@
-use axum::{Json, Router, extract::State, http::StatusCode, response::IntoResponse, routing::get};
-use chrono::NaiveDate;
-use entity::{tbl_pdf_article, tbl_pdf_article_access_log};
+use std::collections::{BTreeMap, HashMap};
+
+use axum::{
+    Json, Router,
+    extract::{Query, State},
+    http::{Method, StatusCode},
+    response::IntoResponse,
+    routing::get,
+};
+use chrono::{Datelike, Days, Months, NaiveDate, NaiveDateTime};
+use entity::{tbl_article, tbl_pdf_article, tbl_pdf_article_access_log};
 use sea_orm::{
-    ColumnTrait, EntityTrait, FromQueryResult, Order, PaginatorTrait, QueryOrder, QuerySelect,
-    prelude::Expr,
+    ColumnTrait, EntityTrait, FromQueryResult, Order, PaginatorTrait, QueryFilter, QueryOrder,
+    QuerySelect, prelude::Expr,
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 
-use crate::AppState;
+use crate::{
+    AppState,
+    auth::{PublicRoute, READER, RequireRole},
+    user,
+};
 
 pub fn routers(state: AppState) -> Router {
     Router::new()
-        .route("/home/pdf_article_stat", get(pdf_article_stat))
+        .route("/home/reflection_stat", get(reflection_stat))
         .with_state(state)
 }
 
+/// 无需登录即可访问的接口
+pub fn public_routes() -> Vec<PublicRoute> {
+    vec![(Method::GET, "/home/pdf_article_stat", get(pdf_article_stat))]
+}
+
+// 回收站中的PDF的访问记录不计入统计
+const PDF_ARTICLE_NOT_DELETED: &str =
+    "pdf_article_id IN (SELECT id FROM tbl_pdf_article WHERE deleted_at IS NULL)";
+
 async fn pdf_article_stat(app_state: State<AppState>) -> impl IntoResponse {
     let pdf_article_count = match tbl_pdf_article::Entity::find()
+        .filter(tbl_pdf_article::Column::DeletedAt.is_null())
         .count(&app_state.db_conn)
         .await
     {
@@ -28,6 +50,7 @@ async fn pdf_article_stat(app_state: State<AppState>) -> impl IntoResponse {
         }
     };
     let pdf_article_access_log_count = match tbl_pdf_article_access_log::Entity::find()
+        .filter(Expr::cust(PDF_ARTICLE_NOT_DELETED))
         .count(&app_state.db_conn)
         .await
     {
@@ -47,6 +70,7 @@ async fn pdf_article_stat(app_state: State<AppState>) -> impl IntoResponse {
         .select_only()
         .column_as(Expr::cust("DATE(created_at)"), "day")
         .column_as(tbl_pdf_article_access_log::Column::Id.count(), "count")
+        .filter(Expr::cust(PDF_ARTICLE_NOT_DELETED))
         .group_by(Expr::cust("DATE(created_at)"))
         .order_by(Expr::cust("DATE(created_at)"), Order::Asc)
         .limit(7)
@@ -68,12 +92,165 @@ async fn pdf_article_stat(app_state: State<AppState>) -> impl IntoResponse {
             "count":daily_access_stat.count
         }));
     }
-    return (
+    (
         StatusCode::OK,
         Json(json!( {
                 "pdf_article_count": pdf_article_count,
                 "pdf_article_access_log_count": pdf_article_access_log_count,
                 "daily_access_stats": daily_access_stat_output
         })),
-    );
+    )
+}
+
+#[derive(Deserialize, Debug)]
+struct ReflectionStatInputDto {
+    // 热力图年份, 为空时为用户时区的今年
+    year: Option<i32>,
+}
+
+/// 写作习惯统计, 按用户时区划分日期, 文章日期优先取entry_date
+async fn reflection_stat(
+    app_state: State<AppState>,
+    RequireRole(auth_user): RequireRole<READER>,
+    Query(reflection_stat_input_dto): Query<ReflectionStatInputDto>,
+) -> impl IntoResponse {
+    let time_zone = match user::user_time_zone(&app_state.db_conn, auth_user.id).await {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("user {} time zone err: {}", auth_user.id, e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({})));
+        }
+    };
+    let today = chrono::Utc::now().with_timezone(&time_zone).date_naive();
+
+    #[derive(FromQueryResult)]
+    struct ArticleStat {
+        entry_date: Option<NaiveDate>,
+        created_at: NaiveDateTime,
+        content_length: i64,
+    }
+    let article_stats = match tbl_article::Entity::find()
+        .select_only()
+        .column(tbl_article::Column::EntryDate)
+        .column(tbl_article::Column::CreatedAt)
+        .column_as(Expr::cust("LENGTH(content)"), "content_length")
+        .filter(tbl_article::Column::UserId.eq(auth_user.id))
+        .filter(tbl_article::Column::DeletedAt.is_null())
+        .into_model::<ArticleStat>()
+        .all(&app_state.db_conn)
+        .await
+    {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("tbl_article reflection stat err: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({})));
+        }
+    };
+    // 每天的文章数
+    let mut daily_counts: BTreeMap<NaiveDate, i64> = BTreeMap::new();
+    let mut total_length = 0;
+    for article_stat in &article_stats {
+        let date = article_stat.entry_date.unwrap_or_else(|| {
+            article_stat
+                .created_at
+                .and_utc()
+                .with_timezone(&time_zone)
+                .date_naive()
+        });
+        *daily_counts.entry(date).or_default() += 1;
+        total_length += article_stat.content_length;
+    }
+    let entry_count = article_stats.len() as i64;
+    let average_length = if entry_count == 0 {
+        0
+    } else {
+        total_length / entry_count
+    };
+
+    // 今天还没写时, 截至昨天的连续天数仍算当前连续
+    let mut current_streak = 0;
+    let mut day = if daily_counts.contains_key(&today) {
+        Some(today)
+    } else {
+        today.pred_opt()
+    };
+    while let Some(date) = day
+        && daily_counts.contains_key(&date)
+    {
+        current_streak += 1;
+        day = date.pred_opt();
+    }
+    let mut longest_streak = 0;
+    let mut streak = 0;
+    let mut prev_date: Option<NaiveDate> = None;
+    for date in daily_counts.keys() {
+        streak = match prev_date {
+            Some(prev) if prev.succ_opt() == Some(*date) => streak + 1,
+            _ => 1,
+        };
+        longest_streak = longest_streak.max(streak);
+        prev_date = Some(*date);
+    }
+
+    // 最近12周(周一开始)和最近12个月的文章数
+    let this_week = today - Days::new(today.weekday().num_days_from_monday() as u64);
+    let this_month = today.with_day(1).unwrap_or(today);
+    let mut weekly_counts: HashMap<NaiveDate, i64> = HashMap::new();
+    let mut monthly_counts: HashMap<NaiveDate, i64> = HashMap::new();
+    for (date, count) in &daily_counts {
+        let week = *date - Days::new(date.weekday().num_days_from_monday() as u64);
+        *weekly_counts.entry(week).or_default() += count;
+        let month = date.with_day(1).unwrap_or(*date);
+        *monthly_counts.entry(month).or_default() += count;
+    }
+    let weekly: Vec<_> = (0..12)
+        .rev()
+        .filter_map(|i| this_week.checked_sub_days(Days::new(i * 7)))
+        .map(|week| {
+            json!({
+                "week_start": week.to_string(),
+                "count": weekly_counts.get(&week).copied().unwrap_or_default()
+            })
+        })
+        .collect();
+    let monthly: Vec<_> = (0..12)
+        .rev()
+        .filter_map(|i| this_month.checked_sub_months(Months::new(i)))
+        .map(|month| {
+            json!({
+                "month": month.format("%Y-%m").to_string(),
+                "count": monthly_counts.get(&month).copied().unwrap_or_default()
+            })
+        })
+        .collect();
+
+    let year = reflection_stat_input_dto.year.unwrap_or(today.year());
+    let heatmap: Vec<_> = daily_counts
+        .iter()
+        .filter(|(date, _)| date.year() == year)
+        .map(|(date, count)| {
+            json!({
+                "day": date.format("%Y-%m-%d").to_string(),
+                "count": count
+            })
+        })
+        .collect();
+    (
+        StatusCode::OK,
+        Json(json!({
+            "time_zone": time_zone.name(),
+            "today": today.to_string(),
+            "entry_count": entry_count,
+            "entry_days": daily_counts.len(),
+            "current_streak": current_streak,
+            "longest_streak": longest_streak,
+            "average_length": average_length,
+            "weekly": weekly,
+            "monthly": monthly,
+            "heatmap": {
+                "year": year,
+                "days": heatmap
+            }
+        })),
+    )
 }
@@ -0,0 +1,927 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::{Cursor, Read},
+    str::FromStr,
+};
+
+use axum::{
+    Json, Router,
+    body::Bytes,
+    extract::{DefaultBodyLimit, Multipart, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::post,
+};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use chrono_tz::Tz;
+use entity::tbl_article;
+use sea_orm::{
+    ActiveValue::Set, ColumnTrait, DbErr, EntityTrait, QueryFilter, QuerySelect, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use zip::ZipArchive;
+
+use crate::{
+    AppState, article,
+    auth::{self, EDITOR, RequireRole},
+    tag, user,
+};
+
+pub fn routers(state: AppState) -> Router {
+    Router::new()
+        .route("/articles/import", post(import))
+        .layer(DefaultBodyLimit::max(MAX_UPLOAD_BYTES))
+        .with_state(state)
+}
+
+const MAX_UPLOAD_BYTES: usize = 256 * 1024 * 1024;
+// 压缩包中单个文件和全部文件解压后的上限, 防止压缩炸弹
+const MAX_FILE_BYTES: u64 = 16 * 1024 * 1024;
+const MAX_UNZIPPED_BYTES: u64 = 256 * 1024 * 1024;
+const MAX_ENTRIES: usize = 10000;
+const MAX_TITLE_CHARS: usize = 100;
+
+#[derive(Deserialize, Debug)]
+struct ImportInputDto {
+    // 只检查不写入
+    #[serde(default)]
+    dry_run: bool,
+}
+
+/// 解析出的一篇日记, 日期即entry_date
+struct ImportEntry {
+    entry_date: NaiveDate,
+    title: String,
+    content: String,
+    tags: Vec<String>,
+    created_at: NaiveDateTime,
+    updated_at: NaiveDateTime,
+}
+
+struct Parsed {
+    // 来源文件, 压缩包内的文件为 压缩包/路径, Day One为 文件#序号
+    source: String,
+    result: Result<ImportEntry, String>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum ImportStatus {
+    New,
+    // 当天已有日记, 或导入内容中有同一天的日记
+    Duplicate,
+    Error,
+}
+
+#[derive(Serialize, Debug)]
+struct ImportItemDto {
+    source: String,
+    status: ImportStatus,
+    entry_date: Option<String>,
+    title: Option<String>,
+    message: Option<String>,
+}
+
+/// 批量导入日记, 支持带YAML头的Markdown、Day One导出的JSON和以日期命名的txt文件,
+/// 可以直接上传或打包成zip; 有解析错误时不写入, 重复的日期跳过, 全部在一个事务中写入
+async fn import(
+    Query(import_input_dto): Query<ImportInputDto>,
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<EDITOR>,
+    mut multipart: Multipart,
+) -> impl IntoResponse {
+    let mut uploads = Vec::new();
+    while let Some(field) = match multipart.next_field().await {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("multipart.next_field err: {}", e);
+            return (
+                StatusCode::BAD_REQUEST,
+                [("code", "400"), ("msg", "multipart.next_field err")],
+                Json(json!({})),
+            );
+        }
+    } {
+        let file_name = field.file_name().unwrap_or_default().to_string();
+        match field.bytes().await {
+            Ok(bytes) => uploads.push((file_name, bytes)),
+            Err(e) => {
+                log::error!("get field bytes err: {}", e);
+                return (
+                    StatusCode::BAD_REQUEST,
+                    [("code", "400"), ("msg", "get field bytes err")],
+                    Json(json!({})),
+                );
+            }
+        }
+    }
+    if uploads.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            [("code", "400"), ("msg", "no file")],
+            Json(json!({})),
+        );
+    }
+    // Day One中没有时区的日记按用户时区取日期
+    let time_zone = match user::user_time_zone(&app_state.db_conn, auth_user.id).await {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("user_time_zone err: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "user_time_zone err")],
+                Json(json!({})),
+            );
+        }
+    };
+    let parsed = match tokio::task::spawn_blocking(move || parse_uploads(uploads, time_zone)).await
+    {
+        Ok(Ok(v)) => v,
+        Ok(Err(msg)) => {
+            log::warn!("user {} import rejected: {}", auth_user.username, msg);
+            return (
+                StatusCode::BAD_REQUEST,
+                [("code", "400"), ("msg", msg)],
+                Json(json!({})),
+            );
+        }
+        Err(e) => {
+            log::error!("import parse err: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "import parse err")],
+                Json(json!({})),
+            );
+        }
+    };
+    if parsed.len() > MAX_ENTRIES {
+        log::warn!(
+            "user {} import {} entries, more than {}",
+            auth_user.username,
+            parsed.len(),
+            MAX_ENTRIES
+        );
+        return (
+            StatusCode::BAD_REQUEST,
+            [("code", "400"), ("msg", "too many entries")],
+            Json(json!({})),
+        );
+    }
+    let entry_dates: HashSet<NaiveDate> = parsed
+        .iter()
+        .filter_map(|v| v.result.as_ref().ok().map(|entry| entry.entry_date))
+        .collect();
+    let existing_dates: HashSet<NaiveDate> = match tbl_article::Entity::find()
+        .select_only()
+        .column(tbl_article::Column::EntryDate)
+        .filter(tbl_article::Column::UserId.eq(auth_user.id))
+        .filter(tbl_article::Column::DeletedAt.is_null())
+        .filter(tbl_article::Column::EntryDate.is_in(entry_dates))
+        .into_tuple()
+        .all(&app_state.db_conn)
+        .await
+    {
+        Ok(v) => v.into_iter().collect(),
+        Err(e) => {
+            log::error!("tbl_article find err: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_article find err")],
+                Json(json!({})),
+            );
+        }
+    };
+
+    let (items, new_entries) = classify(parsed, &existing_dates);
+    let count = |status| items.iter().filter(|v| v.status == status).count();
+    let (new_count, duplicate_count, error_count) = (
+        count(ImportStatus::New),
+        count(ImportStatus::Duplicate),
+        count(ImportStatus::Error),
+    );
+    let report = |created: usize, items: Vec<ImportItemDto>| {
+        Json(json!({
+            "dry_run": import_input_dto.dry_run,
+            "created": created,
+            "new": new_count,
+            "duplicate": duplicate_count,
+            "error": error_count,
+            "_embedded": {
+                "entry": items
+            }
+        }))
+    };
+    if import_input_dto.dry_run {
+        return (
+            StatusCode::OK,
+            [("code", "200"), ("msg", "ok")],
+            report(0, items),
+        );
+    }
+    if error_count > 0 {
+        log::warn!(
+            "user {} import has {} errors",
+            auth_user.username,
+            error_count
+        );
+        return (
+            StatusCode::BAD_REQUEST,
+            [("code", "400"), ("msg", "import has errors")],
+            report(0, items),
+        );
+    }
+    let result = async {
+        let txn = app_state.db_conn.begin().await?;
+        for entry in &new_entries {
+            let tbl_article_am = tbl_article::ActiveModel {
+                title: Set(entry.title.clone()),
+                content: Set(entry.content.clone()),
+                user_id: Set(auth_user.id),
+                entry_date: Set(Some(entry.entry_date)),
+                created_at: Set(entry.created_at),
+                updated_at: Set(entry.updated_at),
+                ..Default::default()
+            };
+            let article_id = tbl_article::Entity::insert(tbl_article_am)
+                .exec(&txn)
+                .await?
+                .last_insert_id;
+            tag::replace_article_tags(&txn, article_id, &entry.tags).await?;
+        }
+        txn.commit().await?;
+        Ok::<_, DbErr>(())
+    }
+    .await;
+    match result {
+        Ok(()) => {
+            // 事务提交后才记录, 失败的导入不留下成功的审计记录
+            auth::insert_log(
+                &app_state,
+                format!(
+                    "{} import {} entries, {} duplicates skipped",
+                    auth_user.username, new_count, duplicate_count
+                ),
+            )
+            .await;
+            (
+                StatusCode::OK,
+                [("code", "200"), ("msg", "ok")],
+                report(new_count, items),
+            )
+        }
+        // 检查之后同一天又写了日记
+        Err(e) if article::is_unique_violation(&e) => {
+            log::warn!("import conflict: {}", e);
+            (
+                StatusCode::CONFLICT,
+                [("code", "409"), ("msg", "entry exists")],
+                report(0, items),
+            )
+        }
+        Err(e) => {
+            log::error!("import err: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "import db err")],
+                Json(json!({})),
+            )
+        }
+    }
+}
+
+/// 按日期判断重复, 当天已有日记或与本次导入中更早的文件同一天的跳过
+fn classify(
+    parsed: Vec<Parsed>,
+    existing_dates: &HashSet<NaiveDate>,
+) -> (Vec<ImportItemDto>, Vec<ImportEntry>) {
+    let mut items = Vec::with_capacity(parsed.len());
+    let mut new_entries = Vec::new();
+    // 本次导入中各日期第一次出现的来源
+    let mut seen: HashMap<NaiveDate, String> = HashMap::new();
+    for Parsed { source, result } in parsed {
+        match result {
+            Ok(entry) => {
+                let message = if existing_dates.contains(&entry.entry_date) {
+                    Some("entry exists".to_string())
+                } else if let Some(first) = seen.get(&entry.entry_date) {
+                    Some(format!("same date as {first}"))
+                } else {
+                    seen.insert(entry.entry_date, source.clone());
+                    None
+                };
+                let status = if message.is_some() {
+                    ImportStatus::Duplicate
+                } else {
+                    ImportStatus::New
+                };
+                items.push(ImportItemDto {
+                    source,
+                    status,
+                    entry_date: Some(entry.entry_date.to_string()),
+                    title: Some(entry.title.clone()),
+                    message,
+                });
+                if status == ImportStatus::New {
+                    new_entries.push(entry);
+                }
+            }
+            Err(message) => items.push(ImportItemDto {
+                source,
+                status: ImportStatus::Error,
+                entry_date: None,
+                title: None,
+                message: Some(message),
+            }),
+        }
+    }
+    (items, new_entries)
+}
+
+/// 压缩包文件过多或解压后过大时拒绝整个上传, 错误为返回的msg
+fn parse_uploads(
+    uploads: Vec<(String, Bytes)>,
+    time_zone: Tz,
+) -> Result<Vec<Parsed>, &'static str> {
+    let mut parsed = Vec::new();
+    let mut unzipped_bytes = 0;
+    for (file_name, bytes) in uploads {
+        if extension(&file_name) == "zip" {
+            parse_zip(
+                &file_name,
+                bytes,
+                time_zone,
+                &mut unzipped_bytes,
+                &mut parsed,
+            )?;
+        } else {
+            parse_file(
+                &file_name,
+                &file_name,
+                &bytes,
+                time_zone,
+                false,
+                &mut parsed,
+            );
+        }
+    }
+    Ok(parsed)
+}
+
+/// 压缩包中不认识的文件跳过, 例如Day One导出的照片;
+/// unzipped_bytes累计本次上传解压出的字节数, 按实际读出的字节计算, 不信任文件头中的大小
+fn parse_zip(
+    zip_name: &str,
+    bytes: Bytes,
+    time_zone: Tz,
+    unzipped_bytes: &mut u64,
+    parsed: &mut Vec<Parsed>,
+) -> Result<(), &'static str> {
+    let mut archive = match ZipArchive::new(Cursor::new(bytes)) {
+        Ok(v) => v,
+        Err(e) => {
+            parsed.push(Parsed {
+                source: zip_name.to_string(),
+                result: Err(format!("invalid zip: {e}")),
+            });
+            return Ok(());
+        }
+    };
+    if archive.len() > MAX_ENTRIES {
+        log::warn!(
+            "zip {} has {} files, more than {}",
+            zip_name,
+            archive.len(),
+            MAX_ENTRIES
+        );
+        return Err("too many entries");
+    }
+    for i in 0..archive.len() {
+        let result = archive
+            .by_index(i)
+            .map_err(|e| e.to_string())
+            .and_then(|file| {
+                let name = file.name().to_string();
+                let file_name = name.rsplit('/').next().unwrap_or_default().to_string();
+                // 目录、隐藏文件和macOS生成的元数据
+                if file.is_dir() || file_name.starts_with('.') || name.starts_with("__MACOSX/") {
+                    return Ok(None);
+                }
+                if !matches!(
+                    extension(&file_name).as_str(),
+                    "md" | "markdown" | "txt" | "json"
+                ) {
+                    return Ok(None);
+                }
+                if file.size() > MAX_FILE_BYTES {
+                    return Err(format!("{name} larger than {MAX_FILE_BYTES} bytes"));
+                }
+                let mut content = Vec::new();
+                let read = file.take(MAX_FILE_BYTES + 1).read_to_end(&mut content);
+                *unzipped_bytes += content.len() as u64;
+                read.map_err(|e| e.to_string())?;
+                // 文件头中的大小可能是假的
+                if content.len() as u64 > MAX_FILE_BYTES {
+                    return Err(format!("{name} larger than {MAX_FILE_BYTES} bytes"));
+                }
+                Ok(Some((name, file_name, content)))
+            });
+        if *unzipped_bytes > MAX_UNZIPPED_BYTES {
+            log::warn!(
+                "zip {} unzipped more than {} bytes",
+                zip_name,
+                MAX_UNZIPPED_BYTES
+            );
+            return Err("unzipped too large");
+        }
+        match result {
+            Ok(Some((name, file_name, content))) => parse_file(
+                &format!("{zip_name}/{name}"),
+                &file_name,
+                &content,
+                time_zone,
+                true,
+                parsed,
+            ),
+            Ok(None) => {}
+            Err(message) => parsed.push(Parsed {
+                source: format!("{zip_name}#{i}"),
+                result: Err(message),
+            }),
+        }
+    }
+    Ok(())
+}
+
+fn parse_file(
+    source: &str,
+    file_name: &str,
+    bytes: &[u8],
+    time_zone: Tz,
+    in_zip: bool,
+    parsed: &mut Vec<Parsed>,
+) {
+    let text = match std::str::from_utf8(bytes) {
+        Ok(v) => v.trim_start_matches('\u{feff}'),
+        Err(_) => {
+            parsed.push(Parsed {
+                source: source.to_string(),
+                result: Err("not utf-8 text".to_string()),
+            });
+            return;
+        }
+    };
+    match extension(file_name).as_str() {
+        "md" | "markdown" => parsed.push(Parsed {
+            source: source.to_string(),
+            result: parse_markdown(file_name, text),
+        }),
+        "txt" => parsed.push(Parsed {
+            source: source.to_string(),
+            result: parse_text(file_name, text),
+        }),
+        "json" => parse_day_one(source, text, time_zone, parsed),
+        _ if in_zip => {}
+        _ => parsed.push(Parsed {
+            source: source.to_string(),
+            result: Err("unsupported file type".to_string()),
+        }),
+    }
+}
+
+fn extension(file_name: &str) -> String {
+    file_name
+        .rsplit_once('.')
+        .map(|(_, v)| v.to_lowercase())
+        .unwrap_or_default()
+}
+
+/// 文件名开头的日期, 例如 2026-10-18.txt 或 2026-10-18-12.md
+fn file_name_date(file_name: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(file_name.get(..10)?, "%Y-%m-%d").ok()
+}
+
+/// 支持RFC 3339、不带时区的日期时间和纯日期, 不带时区的按UTC处理
+fn parse_datetime(v: &str) -> Option<NaiveDateTime> {
+    let v = v.trim();
+    if let Ok(v) = DateTime::parse_from_rfc3339(v) {
+        return Some(v.naive_utc());
+    }
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"] {
+        if let Ok(v) = NaiveDateTime::parse_from_str(v, format) {
+            return Some(v);
+        }
+    }
+    NaiveDate::parse_from_str(v, "%Y-%m-%d")
+        .ok()
+        .map(|v| v.and_time(Default::default()))
+}
+
+/// 第一行非空文字作为标题, 去掉Markdown标题符号
+fn first_line_title(text: &str) -> Option<String> {
+    let line = text.lines().map(str::trim).find(|v| !v.is_empty())?;
+    let title = line.trim_start_matches('#').trim();
+    (!title.is_empty()).then(|| title.chars().take(MAX_TITLE_CHARS).collect())
+}
+
+fn normalize_tags(tags: Vec<String>) -> Result<Vec<String>, String> {
+    tag::normalize_tags(&tags).map_err(|e| format!("invalid tags: {e}"))
+}
+
+/// 以日期命名的纯文本, 标题为日期, 与今日日记的默认标题一致
+fn parse_text(file_name: &str, text: &str) -> Result<ImportEntry, String> {
+    let entry_date =
+        file_name_date(file_name).ok_or("file name should be YYYY-MM-DD.txt".to_string())?;
+    let created_at = entry_date.and_time(Default::default());
+    Ok(ImportEntry {
+        entry_date,
+        title: entry_date.to_string(),
+        content: text.to_string(),
+        tags: Vec::new(),
+        created_at,
+        updated_at: created_at,
+    })
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct FrontMatter {
+    title: Option<String>,
+    date: Option<String>,
+    entry_date: Option<String>,
+    tags: Option<FrontMatterTags>,
+    created_at: Option<String>,
+    updated_at: Option<String>,
+}
+
+/// 标签可以是列表, 也可以是逗号分隔的字符串
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum FrontMatterTags {
+    List(Vec<String>),
+    Text(String),
+}
+
+/// 拆出开头 --- 之间的YAML头
+fn split_front_matter(text: &str) -> (Option<&str>, &str) {
+    let Some(rest) = text
+        .strip_prefix("---\n")
+        .or_else(|| text.strip_prefix("---\r\n"))
+    else {
+        return (None, text);
+    };
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            let body = rest[offset + line.len()..].trim_start_matches(['\r', '\n']);
+            return (Some(&rest[..offset]), body);
+        }
+        offset += line.len();
+    }
+    (None, text)
+}
+
+/// 日期依次取YAML头的entry_date、date, 文件名开头的日期, YAML头的created_at
+fn parse_markdown(file_name: &str, text: &str) -> Result<ImportEntry, String> {
+    let (front_matter, body) = split_front_matter(text);
+    let front_matter: FrontMatter = match front_matter {
+        Some(v) if !v.trim().is_empty() => {
+            serde_yaml::from_str(v).map_err(|e| format!("invalid front matter: {e}"))?
+        }
+        _ => FrontMatter::default(),
+    };
+    let created_at = match &front_matter.created_at {
+        Some(v) => Some(parse_datetime(v).ok_or(format!("invalid created_at: {v}"))?),
+        None => None,
+    };
+    let entry_date = match front_matter
+        .entry_date
+        .as_ref()
+        .or(front_matter.date.as_ref())
+    {
+        Some(v) => parse_datetime(v)
+            .map(|v| v.date())
+            .ok_or(format!("invalid date: {v}"))?,
+        None => file_name_date(file_name)
+            .or(created_at.map(|v| v.date()))
+            .ok_or("no date in front matter or file name".to_string())?,
+    };
+    let created_at = created_at.unwrap_or_else(|| entry_date.and_time(Default::default()));
+    let updated_at = match &front_matter.updated_at {
+        Some(v) => parse_datetime(v).ok_or(format!("invalid updated_at: {v}"))?,
+        None => created_at,
+    };
+    let tags = match front_matter.tags {
+        Some(FrontMatterTags::List(v)) => v,
+        Some(FrontMatterTags::Text(v)) => tag::parse_tags(&v),
+        None => Vec::new(),
+    };
+    let title = front_matter
+        .title
+        .map(|v| v.trim().chars().take(MAX_TITLE_CHARS).collect::<String>())
+        .filter(|v| !v.is_empty())
+        .or_else(|| {
+            body.lines()
+                .find(|v| v.starts_with("# "))
+                .and_then(first_line_title)
+        })
+        .unwrap_or_else(|| entry_date.to_string());
+    Ok(ImportEntry {
+        entry_date,
+        title,
+        content: body.to_string(),
+        tags: normalize_tags(tags)?,
+        created_at,
+        updated_at,
+    })
+}
+
+#[derive(Deserialize, Debug)]
+struct DayOneExport {
+    entries: Vec<DayOneEntry>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct DayOneEntry {
+    creation_date: String,
+    modified_date: Option<String>,
+    time_zone: Option<String>,
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// Day One导出的JSON, 日期按日记记录的时区计算, 标题取正文第一行
+fn parse_day_one(source: &str, text: &str, time_zone: Tz, parsed: &mut Vec<Parsed>) {
+    let day_one_export: DayOneExport = match serde_json::from_str(text) {
+        Ok(v) => v,
+        Err(e) => {
+            parsed.push(Parsed {
+                source: source.to_string(),
+                result: Err(format!("invalid Day One json: {e}")),
+            });
+            return;
+        }
+    };
+    for (i, day_one_entry) in day_one_export.entries.into_iter().enumerate() {
+        parsed.push(Parsed {
+            source: format!("{source}#{}", i + 1),
+            result: parse_day_one_entry(day_one_entry, time_zone),
+        });
+    }
+}
+
+fn parse_day_one_entry(day_one_entry: DayOneEntry, time_zone: Tz) -> Result<ImportEntry, String> {
+    let created_at = parse_datetime(&day_one_entry.creation_date).ok_or(format!(
+        "invalid creationDate: {}",
+        day_one_entry.creation_date
+    ))?;
+    let time_zone = day_one_entry
+        .time_zone
+        .as_deref()
+        .and_then(|v| Tz::from_str(v).ok())
+        .unwrap_or(time_zone);
+    let entry_date = created_at.and_utc().with_timezone(&time_zone).date_naive();
+    let updated_at = day_one_entry
+        .modified_date
+        .as_deref()
+        .and_then(parse_datetime)
+        .unwrap_or(created_at);
+    Ok(ImportEntry {
+        entry_date,
+        title: first_line_title(&day_one_entry.text).unwrap_or_else(|| entry_date.to_string()),
+        content: day_one_entry.text,
+        tags: normalize_tags(day_one_entry.tags)?,
+        created_at,
+        updated_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+
+    use super::*;
+
+    fn date(v: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(v, "%Y-%m-%d").unwrap()
+    }
+
+    fn datetime(v: &str) -> NaiveDateTime {
+        parse_datetime(v).unwrap()
+    }
+
+    fn zip_bytes(files: &[(&str, &[u8])]) -> Bytes {
+        let mut zip_writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in files {
+            zip_writer
+                .start_file(
+                    *name,
+                    SimpleFileOptions::default().compression_method(CompressionMethod::Stored),
+                )
+                .unwrap();
+            zip_writer.write_all(data).unwrap();
+        }
+        Bytes::from(zip_writer.finish().unwrap().into_inner())
+    }
+
+    fn entry(source: &str, entry_date: &str) -> Parsed {
+        let created_at = date(entry_date).and_time(Default::default());
+        Parsed {
+            source: source.to_string(),
+            result: Ok(ImportEntry {
+                entry_date: date(entry_date),
+                title: source.to_string(),
+                content: String::new(),
+                tags: Vec::new(),
+                created_at,
+                updated_at: created_at,
+            }),
+        }
+    }
+
+    #[test]
+    fn split_front_matter_lf_and_crlf() {
+        assert_eq!(
+            split_front_matter("---\ntitle: a\n---\n\nbody\n"),
+            (Some("title: a\n"), "body\n")
+        );
+        assert_eq!(
+            split_front_matter("---\r\ntitle: a\r\n---\r\n\r\nbody\r\n"),
+            (Some("title: a\r\n"), "body\r\n")
+        );
+        // 没有结束行时整篇都是正文
+        assert_eq!(
+            split_front_matter("---\ntitle: a\nbody\n"),
+            (None, "---\ntitle: a\nbody\n")
+        );
+        assert_eq!(split_front_matter("---\n---\nbody"), (Some(""), "body"));
+        assert_eq!(split_front_matter("---title\n"), (None, "---title\n"));
+    }
+
+    #[test]
+    fn parse_markdown_crlf_front_matter() {
+        let entry = parse_markdown(
+            "note.md",
+            "---\r\ntitle: Trip\r\ndate: 2026-10-01\r\ntags: [a, b]\r\n---\r\n\r\nbody\r\n",
+        )
+        .unwrap();
+        assert_eq!(entry.entry_date, date("2026-10-01"));
+        assert_eq!(entry.title, "Trip");
+        assert_eq!(entry.tags, ["a", "b"]);
+        assert_eq!(entry.content, "body\r\n");
+    }
+
+    #[test]
+    fn parse_file_strips_bom() {
+        let mut parsed = Vec::new();
+        parse_file(
+            "a.md",
+            "a.md",
+            "\u{feff}---\ndate: 2026-10-02\n---\nbody".as_bytes(),
+            Tz::UTC,
+            false,
+            &mut parsed,
+        );
+        let entry = parsed.pop().unwrap().result.unwrap();
+        assert_eq!(entry.entry_date, date("2026-10-02"));
+        assert_eq!(entry.content, "body");
+    }
+
+    #[test]
+    fn parse_markdown_date_fallbacks() {
+        // 文件名中的日期
+        let entry = parse_markdown("2026-10-03-trip.md", "# Title\nbody").unwrap();
+        assert_eq!(entry.entry_date, date("2026-10-03"));
+        assert_eq!(entry.title, "Title");
+        assert_eq!(entry.created_at, datetime("2026-10-03"));
+        // YAML头的created_at
+        let entry = parse_markdown(
+            "note.md",
+            "---\ncreated_at: 2026-10-04T08:30:00Z\n---\nbody",
+        )
+        .unwrap();
+        assert_eq!(entry.entry_date, date("2026-10-04"));
+        assert_eq!(entry.created_at, datetime("2026-10-04 08:30:00"));
+        assert_eq!(entry.title, "2026-10-04");
+        // 没有日期
+        assert!(parse_markdown("note.md", "body").is_err());
+        assert!(parse_markdown("note.md", "---\ndate: soon\n---\nbody").is_err());
+    }
+
+    #[test]
+    fn parse_markdown_truncates_front_matter_title() {
+        let text = format!("---\ndate: 2026-10-05\ntitle: {}\n---\n", "长".repeat(150));
+        let entry = parse_markdown("note.md", &text).unwrap();
+        assert_eq!(entry.title.chars().count(), MAX_TITLE_CHARS);
+    }
+
+    #[test]
+    fn parse_text_requires_dated_file_name() {
+        let entry = parse_text("2026-10-06.txt", "hello").unwrap();
+        assert_eq!(entry.entry_date, date("2026-10-06"));
+        assert_eq!(entry.title, "2026-10-06");
+        assert!(parse_text("hello.txt", "hello").is_err());
+    }
+
+    #[test]
+    fn day_one_time_zone() {
+        let day_one_entry = |time_zone: Option<&str>| DayOneEntry {
+            creation_date: "2026-10-07T20:00:00Z".to_string(),
+            modified_date: None,
+            time_zone: time_zone.map(str::to_string),
+            text: "# First line\nbody".to_string(),
+            tags: Vec::new(),
+        };
+        // 日记记录的时区优先
+        let entry = parse_day_one_entry(day_one_entry(Some("Asia/Shanghai")), Tz::UTC).unwrap();
+        assert_eq!(entry.entry_date, date("2026-10-08"));
+        assert_eq!(entry.title, "First line");
+        assert_eq!(entry.updated_at, entry.created_at);
+        // 没有时区时按用户时区
+        let entry = parse_day_one_entry(day_one_entry(None), Tz::UTC).unwrap();
+        assert_eq!(entry.entry_date, date("2026-10-07"));
+        let entry = parse_day_one_entry(day_one_entry(None), Tz::Asia__Tokyo).unwrap();
+        assert_eq!(entry.entry_date, date("2026-10-08"));
+        // 无法识别的时区按用户时区
+        let entry = parse_day_one_entry(day_one_entry(Some("Mars/Base")), Tz::UTC).unwrap();
+        assert_eq!(entry.entry_date, date("2026-10-07"));
+    }
+
+    #[test]
+    fn classify_same_day_duplicates() {
+        let existing_dates = HashSet::from([date("2026-10-01")]);
+        let parsed = vec![
+            entry("a.md", "2026-10-01"),
+            entry("b.md", "2026-10-02"),
+            entry("c.md", "2026-10-02"),
+            Parsed {
+                source: "d.md".to_string(),
+                result: Err("bad".to_string()),
+            },
+            entry("e.md", "2026-10-03"),
+        ];
+        let (items, new_entries) = classify(parsed, &existing_dates);
+        let statuses: Vec<_> = items.iter().map(|v| v.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                ImportStatus::Duplicate,
+                ImportStatus::New,
+                ImportStatus::Duplicate,
+                ImportStatus::Error,
+                ImportStatus::New,
+            ]
+        );
+        assert_eq!(items[0].message.as_deref(), Some("entry exists"));
+        assert_eq!(items[2].message.as_deref(), Some("same date as b.md"));
+        let titles: Vec<_> = new_entries.iter().map(|v| v.title.as_str()).collect();
+        assert_eq!(titles, ["b.md", "e.md"]);
+    }
+
+    #[test]
+    fn parse_zip_skips_unknown_files() {
+        let bytes = zip_bytes(&[
+            ("2026/2026-10-01.txt", b"one"),
+            ("2026/photo.jpg", b"jpg"),
+            ("__MACOSX/2026/._2026-10-01.txt", b"meta"),
+        ]);
+        let parsed = parse_uploads(vec![("a.zip".to_string(), bytes)], Tz::UTC).unwrap();
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].source, "a.zip/2026/2026-10-01.txt");
+        assert_eq!(parsed[0].result.as_ref().unwrap().content, "one");
+    }
+
+    #[test]
+    fn parse_zip_rejects_too_many_files() {
+        let names: Vec<String> = (0..=MAX_ENTRIES).map(|i| format!("{i}.jpg")).collect();
+        let files: Vec<(&str, &[u8])> = names.iter().map(|v| (v.as_str(), &b""[..])).collect();
+        let result = parse_uploads(vec![("a.zip".to_string(), zip_bytes(&files))], Tz::UTC);
+        assert_eq!(result.err(), Some("too many entries"));
+    }
+
+    #[test]
+    fn parse_zip_rejects_large_files() {
+        let large = vec![b'a'; MAX_FILE_BYTES as usize + 1];
+        let bytes = zip_bytes(&[("2026-10-01.txt", &large), ("2026-10-02.txt", b"two")]);
+        let parsed = parse_uploads(vec![("a.zip".to_string(), bytes)], Tz::UTC).unwrap();
+        assert!(
+            parsed[0]
+                .result
+                .as_ref()
+                .is_err_and(|v| v.contains("larger"))
+        );
+        assert!(parsed[1].result.is_ok());
+        // 多个文件累计超过解压总量上限
+        let file = vec![b'a'; MAX_FILE_BYTES as usize];
+        let names: Vec<String> = (0..=MAX_UNZIPPED_BYTES / MAX_FILE_BYTES)
+            .map(|i| format!("{i}.txt"))
+            .collect();
+        let files: Vec<(&str, &[u8])> = names.iter().map(|v| (v.as_str(), &file[..])).collect();
+        let result = parse_uploads(vec![("a.zip".to_string(), zip_bytes(&files))], Tz::UTC);
+        assert_eq!(result.err(), Some("unzipped too large"));
+    }
+}
@@ -1,13 +1,30 @@
 use sea_orm::DatabaseConnection;
 
+pub mod api_token;
 pub mod article;
+pub mod article_revision;
+pub mod article_search;
+pub mod attachment;
 pub mod auth;
 pub mod config;
+pub mod export;
 pub mod file;
 pub mod home;
+pub mod import;
 pub mod log;
+pub mod login_limit;
+pub mod markdown;
+pub mod mtls;
+pub mod oidc;
 pub mod pdf_article;
 pub mod pdf_article_access_log;
+pub mod pdf_book;
+pub mod reflection;
+pub mod session;
+pub mod tag;
+pub mod totp;
+pub mod trash;
+pub mod user;
 
 #[derive(Clone)]
 pub struct AppState {
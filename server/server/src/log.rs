@@ -11,7 +11,10 @@ use serde::{Deserialize, Serialize};
 use serde_json::json;
 use validator::Validate;
 
-use crate::AppState;
+use crate::{
+    AppState,
+    auth::{ADMIN, RequireRole},
+};
 
 pub fn routers(state: AppState) -> Router {
     Router::new().route("/logs", get(query)).with_state(state)
@@ -32,15 +35,16 @@ struct QueryOutputDto {
 }
 async fn query(
     app_state: State<AppState>,
+    _: RequireRole<ADMIN>,
     Query(query_input_dto): Query<QueryInputDto>,
 ) -> impl IntoResponse {
     let mut select = tbl_log::Entity::find();
 
-    if let Some(content) = query_input_dto.content {
-        if !content.is_empty() {
-            let like_pattern = format!("%{content}%");
-            select = select.filter(tbl_log::Column::Content.like(like_pattern));
-        }
+    if let Some(content) = query_input_dto.content
+        && !content.is_empty()
+    {
+        let like_pattern = format!("%{content}%");
+        select = select.filter(tbl_log::Column::Content.like(like_pattern));
     }
     let paginator = select
         .order_by_desc(tbl_log::Column::CreatedAt)
@@ -0,0 +1,257 @@
+use serde::{Deserialize, Serialize};
+
+// 登录失败计数保存在sled独立的tree中, 与token分开
+const LOGIN_ATTEMPT_TREE: &str = "login_attempt";
+// 允许连续失败的次数, 超过后开始锁定
+const FREE_FAILURES: u32 = 3;
+// 首次锁定时长, 之后每次失败翻倍
+const BASE_LOCK_SECS: i64 = 30;
+const MAX_LOCK_SECS: i64 = 60 * 60;
+// 超过该时长没有失败记录则清除计数
+const FAILURE_WINDOW_SECS: i64 = 24 * 60 * 60;
+// 无需登录即可触发写入的接口按固定窗口限制次数
+const REQUEST_RATE_TREE: &str = "request_rate";
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct LoginAttempt {
+    failures: u32,
+    last_failure: i64,
+    locked_until: i64,
+}
+
+pub fn ip_key(src_ip: &str) -> String {
+    format!("ip:{src_ip}")
+}
+
+pub fn username_key(username: &str) -> String {
+    format!("user:{username}")
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct RequestRate {
+    count: u32,
+    window_start: i64,
+    window_secs: i64,
+}
+
+fn get_attempt(tree: &sled::Tree, key: &str) -> LoginAttempt {
+    match tree.get(key) {
+        Ok(Some(v)) => serde_json::from_slice(&v).unwrap_or_default(),
+        Ok(None) => LoginAttempt::default(),
+        Err(e) => {
+            log::error!("sled get login attempt err: {}", e);
+            LoginAttempt::default()
+        }
+    }
+}
+
+/// 返回剩余锁定秒数, 0表示未锁定
+pub fn locked_secs(sled_db: &sled::Db, keys: &[String]) -> i64 {
+    let tree = match sled_db.open_tree(LOGIN_ATTEMPT_TREE) {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("sled open_tree err: {}", e);
+            return 0;
+        }
+    };
+    let ts_now = chrono::Utc::now().timestamp();
+    keys.iter()
+        .map(|key| get_attempt(&tree, key).locked_until - ts_now)
+        .max()
+        .unwrap_or(0)
+        .max(0)
+}
+
+fn lock_secs(failures: u32) -> i64 {
+    let exp = (failures - FREE_FAILURES - 1).min(16);
+    (BASE_LOCK_SECS << exp).min(MAX_LOCK_SECS)
+}
+
+/// 记录一次登录失败, 返回各key中最大的连续失败次数
+/// 计数通过update_and_fetch原子递增, 并发的失败请求不会读到同一个旧值
+pub fn record_failure(sled_db: &sled::Db, keys: &[String]) -> u32 {
+    let tree = match sled_db.open_tree(LOGIN_ATTEMPT_TREE) {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("sled open_tree err: {}", e);
+            return 0;
+        }
+    };
+    let ts_now = chrono::Utc::now().timestamp();
+    let mut max_failures = 0;
+    for key in keys {
+        // 闭包可能因并发冲突被多次调用, 只做计算
+        let result = tree.update_and_fetch(key, |old| {
+            let mut login_attempt: LoginAttempt = old
+                .and_then(|v| serde_json::from_slice(v).ok())
+                .unwrap_or_default();
+            if ts_now - login_attempt.last_failure >= FAILURE_WINDOW_SECS {
+                login_attempt = LoginAttempt::default();
+            }
+            login_attempt.failures += 1;
+            login_attempt.last_failure = ts_now;
+            if login_attempt.failures > FREE_FAILURES {
+                login_attempt.locked_until = ts_now + lock_secs(login_attempt.failures);
+            }
+            match serde_json::to_vec(&login_attempt) {
+                Ok(v) => Some(v),
+                Err(e) => {
+                    log::error!("login_attempt to_vec err: {}", e);
+                    old.map(|v| v.to_vec())
+                }
+            }
+        });
+        let login_attempt: LoginAttempt = match result {
+            Ok(Some(v)) => serde_json::from_slice(&v).unwrap_or_default(),
+            Ok(None) => LoginAttempt::default(),
+            Err(e) => {
+                log::error!("sled update login attempt err: {}", e);
+                continue;
+            }
+        };
+        if login_attempt.failures > FREE_FAILURES {
+            log::warn!("{key} locked {}s", login_attempt.locked_until - ts_now);
+        }
+        max_failures = max_failures.max(login_attempt.failures);
+    }
+    max_failures
+}
+
+pub fn clear_failures(sled_db: &sled::Db, keys: &[String]) {
+    let tree = match sled_db.open_tree(LOGIN_ATTEMPT_TREE) {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("sled open_tree err: {}", e);
+            return;
+        }
+    };
+    for key in keys {
+        if let Err(e) = tree.remove(key) {
+            log::error!("sled remove login attempt err: {}", e);
+        }
+    }
+}
+
+/// 在window_secs窗口内计数一次请求, 超过limit时返回false
+pub fn allow_request(sled_db: &sled::Db, key: &str, limit: u32, window_secs: i64) -> bool {
+    let tree = match sled_db.open_tree(REQUEST_RATE_TREE) {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("sled open_tree err: {}", e);
+            return false;
+        }
+    };
+    let ts_now = chrono::Utc::now().timestamp();
+    let result = tree.update_and_fetch(key, |old| {
+        let mut request_rate: RequestRate = old
+            .and_then(|v| serde_json::from_slice(v).ok())
+            .unwrap_or_default();
+        if ts_now - request_rate.window_start >= window_secs {
+            request_rate = RequestRate {
+                count: 0,
+                window_start: ts_now,
+                window_secs,
+            };
+        }
+        request_rate.count = request_rate.count.saturating_add(1);
+        match serde_json::to_vec(&request_rate) {
+            Ok(v) => Some(v),
+            Err(e) => {
+                log::error!("request_rate to_vec err: {}", e);
+                old.map(|v| v.to_vec())
+            }
+        }
+    });
+    let request_rate: RequestRate = match result {
+        Ok(Some(v)) => serde_json::from_slice(&v).unwrap_or_default(),
+        Ok(None) => RequestRate::default(),
+        Err(e) => {
+            log::error!("sled update request rate err: {}", e);
+            return false;
+        }
+    };
+    if request_rate.count > limit {
+        log::warn!("{key} exceeded {limit} requests in {window_secs}s");
+        return false;
+    }
+    true
+}
+
+/// 清理已过期的失败记录和请求计数, 由token_expired_task周期调用
+pub fn purge_expired(sled_db: &sled::Db) {
+    match sled_db.open_tree(REQUEST_RATE_TREE) {
+        Ok(tree) => {
+            let ts_now = chrono::Utc::now().timestamp();
+            for (k, v) in tree.iter().flatten() {
+                let request_rate: RequestRate = serde_json::from_slice(&v).unwrap_or_default();
+                if ts_now - request_rate.window_start >= request_rate.window_secs
+                    && let Err(e) = tree.remove(&k)
+                {
+                    log::error!("sled remove request rate err: {}", e);
+                }
+            }
+        }
+        Err(e) => log::error!("sled open_tree err: {}", e),
+    }
+
+    let tree = match sled_db.open_tree(LOGIN_ATTEMPT_TREE) {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("sled open_tree err: {}", e);
+            return;
+        }
+    };
+    let ts_now = chrono::Utc::now().timestamp();
+    for (k, v) in tree.iter().flatten() {
+        let login_attempt: LoginAttempt = serde_json::from_slice(&v).unwrap_or_default();
+        if ts_now - login_attempt.last_failure >= FAILURE_WINDOW_SECS
+            && login_attempt.locked_until <= ts_now
+            && let Err(e) = tree.remove(&k)
+        {
+            log::error!("sled remove login attempt err: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concurrent_failures_are_all_counted() {
+        let sled_db = sled::Config::new().temporary(true).open().unwrap();
+        let keys = [ip_key("127.0.0.1"), username_key("root")];
+        std::thread::scope(|scope| {
+            for _ in 0..16 {
+                scope.spawn(|| {
+                    for _ in 0..4 {
+                        record_failure(&sled_db, &keys);
+                    }
+                });
+            }
+        });
+        let tree = sled_db.open_tree(LOGIN_ATTEMPT_TREE).unwrap();
+        for key in &keys {
+            assert_eq!(get_attempt(&tree, key).failures, 64);
+        }
+        assert!(locked_secs(&sled_db, &keys) > 0);
+    }
+
+    #[test]
+    fn requests_over_limit_are_rejected() {
+        let sled_db = sled::Config::new().temporary(true).open().unwrap();
+        let key = ip_key("127.0.0.1");
+        for _ in 0..5 {
+            assert!(allow_request(&sled_db, &key, 5, 60));
+        }
+        assert!(!allow_request(&sled_db, &key, 5, 60));
+        assert!(allow_request(&sled_db, &ip_key("127.0.0.2"), 5, 60));
+    }
+
+    #[test]
+    fn lock_doubles_up_to_max() {
+        assert_eq!(lock_secs(FREE_FAILURES + 1), BASE_LOCK_SECS);
+        assert_eq!(lock_secs(FREE_FAILURES + 2), BASE_LOCK_SECS * 2);
+        assert_eq!(lock_secs(100), MAX_LOCK_SECS);
+    }
+}
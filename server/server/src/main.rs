@@ -14,6 +14,7 @@ use sea_orm::Database;
 use server::{
     auth::{self, RequireAuth},
     config::SERVER_TOML,
+    mtls::{self, MtlsAcceptor},
 };
 use tower_http::services::{ServeDir, ServeFile};
 
@@ -42,6 +43,7 @@ async fn main() -> anyhow::Result<()> {
     let sled_db = sled::open("./data/sled_db")?;
     auth::token_expired_task(sled_db.clone()).await?;
     let app_state = server::AppState { db_conn, sled_db };
+    server::trash::purge_task(app_state.clone());
     let dist_path = if Path::new("../../ui/dist").exists() {
         // 工程目录
         "../../ui/dist"
@@ -49,36 +51,79 @@ async fn main() -> anyhow::Result<()> {
         // 部署目录
         "./html"
     };
-    let app = Router::new()
-        .fallback_service(
-            ServeDir::new(dist_path).fallback(ServeFile::new(format!("{dist_path}/index.html"))),
-        )
+    // 公开接口单独组成路由, 不经过RequireAuth
+    let public_api = auth::public_router(
+        app_state.clone(),
+        "/api",
+        [
+            server::auth::public_routes(),
+            server::pdf_article::public_routes(),
+            server::home::public_routes(),
+            server::file::public_routes(),
+            server::oidc::public_routes(),
+        ]
+        .concat(),
+    );
+    let api = Router::new()
         .nest("/api", server::article::routers(app_state.clone()))
+        .nest("/api", server::article_revision::routers(app_state.clone()))
+        .nest("/api", server::article_search::routers(app_state.clone()))
+        .nest("/api", server::attachment::routers(app_state.clone()))
+        .nest("/api", server::export::routers(app_state.clone()))
+        .nest("/api", server::import::routers(app_state.clone()))
         .nest("/api", server::log::routers(app_state.clone()))
         .nest("/api", server::file::routers(app_state.clone()))
         .nest("/api", server::pdf_article::routers(app_state.clone()))
+        .nest("/api", server::tag::routers(app_state.clone()))
+        .nest("/api", server::trash::routers(app_state.clone()))
         .nest(
             "/api",
             server::pdf_article_access_log::routers(app_state.clone()),
         )
         .nest("/api", server::home::routers(app_state.clone()))
         .nest("/api", server::auth::routers(app_state.clone()))
-        .layer(from_extractor_with_state::<RequireAuth, _>(Arc::new(
+        .nest("/api", server::user::routers(app_state.clone()))
+        .nest("/api", server::totp::routers(app_state.clone()))
+        .nest("/api", server::session::routers(app_state.clone()))
+        .nest("/api", server::api_token::routers(app_state.clone()))
+        .nest("/api", server::reflection::routers(app_state.clone()))
+        .route_layer(from_extractor_with_state::<RequireAuth, _>(Arc::new(
             app_state,
         )));
+    let app = Router::new()
+        .fallback_service(
+            ServeDir::new(dist_path).fallback(ServeFile::new(format!("{dist_path}/index.html"))),
+        )
+        .merge(api)
+        .merge(public_api);
 
     if let Err(e) = crypto::ring::default_provider().install_default() {
         log::error!("default_provider install err: {:?}", e);
     }
-    let config = RustlsConfig::from_pem_file(
-        PathBuf::from("./config").join("zhaogj-ca.crt"),
-        PathBuf::from("./config").join("zhaogj-ca.key"),
-    )
-    .await?;
+    let cert_path = PathBuf::from("./config").join("zhaogj-ca.crt");
+    let key_path = PathBuf::from("./config").join("zhaogj-ca.key");
     let addr = SocketAddr::from_str(&SERVER_TOML.server.addr)?;
     log::info!("listening on {}", addr);
-    axum_server::bind_rustls(addr, config)
-        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
-        .await?;
+    let make_service = app.into_make_service_with_connect_info::<SocketAddr>();
+    match &SERVER_TOML.mtls {
+        Some(mtls) => {
+            let config = mtls::rustls_config(&cert_path, &key_path, mtls)?;
+            log::info!(
+                "mtls enabled, client ca: {}, required: {}",
+                mtls.client_ca,
+                mtls.required
+            );
+            axum_server::bind(addr)
+                .acceptor(MtlsAcceptor::new(config))
+                .serve(make_service)
+                .await?;
+        }
+        None => {
+            let config = RustlsConfig::from_pem_file(cert_path, key_path).await?;
+            axum_server::bind_rustls(addr, config)
+                .serve(make_service)
+                .await?;
+        }
+    }
     Ok(())
 }
@@ -0,0 +1,206 @@
+use pulldown_cmark::{CowStr, Event, Options, Parser, Tag, TagEnd, html};
+
+// 引用已上传文件的链接前缀, 例如 ![](file:12)
+const FILE_SCHEME: &str = "file:";
+
+fn options() -> Options {
+    Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_FOOTNOTES
+}
+
+/// 把Markdown渲染为HTML, 并清除脚本、事件属性等不安全内容
+/// file:ID通过file_url改写为可直接访问的地址, 例如带签名的下载地址
+pub fn render_html(markdown: &str, file_url: impl Fn(i32) -> String) -> String {
+    let mut unsafe_html = String::with_capacity(markdown.len() * 3 / 2);
+    let parser = Parser::new_ext(markdown, options()).map(|event| match event {
+        Event::Start(Tag::Image {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => Event::Start(Tag::Image {
+            link_type,
+            dest_url: rewrite_file_url(dest_url, &file_url),
+            title,
+            id,
+        }),
+        Event::Start(Tag::Link {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => Event::Start(Tag::Link {
+            link_type,
+            dest_url: rewrite_file_url(dest_url, &file_url),
+            title,
+            id,
+        }),
+        _ => event,
+    });
+    html::push_html(&mut unsafe_html, parser);
+    // 任务列表渲染为禁用的复选框, 只放行复选框的这几个属性
+    ammonia::Builder::default()
+        .add_tags(["input"])
+        .add_tag_attributes("input", ["type", "checked", "disabled"])
+        .attribute_filter(|element, attribute, value| {
+            if element == "input" && attribute == "type" && value != "checkbox" {
+                None
+            } else {
+                Some(value.into())
+            }
+        })
+        .clean(&unsafe_html)
+        .to_string()
+}
+
+fn rewrite_file_url<'a>(dest_url: CowStr<'a>, file_url: &impl Fn(i32) -> String) -> CowStr<'a> {
+    match file_id(&dest_url) {
+        Some(file_id) => file_url(file_id).into(),
+        None => dest_url,
+    }
+}
+
+fn file_id(dest_url: &str) -> Option<i32> {
+    dest_url.strip_prefix(FILE_SCHEME)?.parse().ok()
+}
+
+/// 正文中通过file:ID引用的文件, 按出现顺序去重
+pub fn file_references(markdown: &str) -> Vec<i32> {
+    let mut file_ids = Vec::new();
+    for event in Parser::new_ext(markdown, options()) {
+        if let Event::Start(Tag::Image { dest_url, .. } | Tag::Link { dest_url, .. }) = event
+            && let Some(file_id) = file_id(&dest_url)
+            && !file_ids.contains(&file_id)
+        {
+            file_ids.push(file_id);
+        }
+    }
+    file_ids
+}
+
+/// 去掉Markdown语法后的纯文本摘要, 空白合并为一个空格, 超过max_chars时截断并加省略号
+pub fn plain_text_excerpt(markdown: &str, max_chars: usize) -> String {
+    let mut text = String::new();
+    for event in Parser::new_ext(markdown, options()) {
+        match event {
+            Event::Text(v) | Event::Code(v) => text.push_str(&v),
+            // 内嵌HTML可能是脚本或样式, 摘要中不保留
+            Event::SoftBreak | Event::HardBreak | Event::Rule => text.push(' '),
+            Event::TaskListMarker(done) => text.push_str(if done { "[x] " } else { "[ ] " }),
+            Event::Start(Tag::Item) | Event::End(TagEnd::TableCell) => text.push(' '),
+            Event::End(
+                TagEnd::Paragraph
+                | TagEnd::Heading(_)
+                | TagEnd::Item
+                | TagEnd::CodeBlock
+                | TagEnd::TableRow
+                | TagEnd::TableHead,
+            ) => text.push(' '),
+            _ => {}
+        }
+    }
+    let mut excerpt = String::new();
+    let mut count = 0;
+    for word in text.split_whitespace() {
+        if count > 0 {
+            excerpt.push(' ');
+            count += 1;
+        }
+        for ch in word.chars() {
+            if count >= max_chars {
+                excerpt.truncate(excerpt.trim_end().len());
+                excerpt.push('…');
+                return excerpt;
+            }
+            excerpt.push(ch);
+            count += 1;
+        }
+    }
+    excerpt
+}
+
+/// 排版用的文本块, 用于生成PDF等不支持HTML的格式
+#[derive(Debug, PartialEq)]
+pub enum TextBlock {
+    Heading(String),
+    Paragraph(String),
+    // 列表项及其层级, 从1开始
+    ListItem(usize, String),
+    // 代码块中的一行
+    Code(String),
+    Rule,
+}
+
+/// 把Markdown拆成文本块, 行内格式去掉, 图片保留替代文字
+pub fn text_blocks(markdown: &str) -> Vec<TextBlock> {
+    let mut blocks = Vec::new();
+    let mut text = String::new();
+    let mut list_depth = 0;
+    fn flush(text: &mut String, list_depth: usize, blocks: &mut Vec<TextBlock>) {
+        let v = text.trim().to_string();
+        text.clear();
+        if v.is_empty() {
+            return;
+        }
+        blocks.push(if list_depth > 0 {
+            TextBlock::ListItem(list_depth, v)
+        } else {
+            TextBlock::Paragraph(v)
+        });
+    }
+    for event in Parser::new_ext(markdown, options()) {
+        match event {
+            Event::Text(v) | Event::Code(v) => text.push_str(&v),
+            Event::SoftBreak => text.push(' '),
+            Event::HardBreak => text.push('\n'),
+            Event::TaskListMarker(done) => text.push_str(if done { "[x] " } else { "[ ] " }),
+            Event::FootnoteReference(v) => text.push_str(&format!("[{v}]")),
+            Event::Rule => blocks.push(TextBlock::Rule),
+            Event::Start(Tag::Image { .. }) => text.push('['),
+            Event::End(TagEnd::Image) => text.push(']'),
+            Event::Start(Tag::CodeBlock(_)) => {
+                flush(&mut text, list_depth, &mut blocks);
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                for line in text.trim_end_matches('\n').lines() {
+                    blocks.push(TextBlock::Code(line.to_string()));
+                }
+                text.clear();
+            }
+            Event::Start(Tag::List(_)) => {
+                flush(&mut text, list_depth, &mut blocks);
+                list_depth += 1;
+            }
+            Event::End(TagEnd::List(_)) => list_depth -= 1,
+            Event::Start(Tag::FootnoteDefinition(v)) => {
+                flush(&mut text, list_depth, &mut blocks);
+                text.push_str(&format!("[{v}] "));
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                let v = text.trim().to_string();
+                text.clear();
+                if !v.is_empty() {
+                    blocks.push(TextBlock::Heading(v));
+                }
+            }
+            Event::End(TagEnd::TableCell) => text.push_str(" | "),
+            Event::End(
+                TagEnd::Paragraph
+                | TagEnd::Item
+                | TagEnd::TableRow
+                | TagEnd::TableHead
+                | TagEnd::FootnoteDefinition,
+            ) => {
+                let v = text.trim_end_matches([' ', '|']).to_string();
+                text.clear();
+                text.push_str(&v);
+                flush(&mut text, list_depth, &mut blocks);
+            }
+            _ => {}
+        }
+    }
+    flush(&mut text, list_depth, &mut blocks);
+    blocks
+}
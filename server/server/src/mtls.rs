@@ -0,0 +1,138 @@
+use std::{future::Future, io, path::Path, pin::Pin, str::FromStr, sync::Arc};
+
+use axum::http::StatusCode;
+use axum_server::{
+    accept::Accept,
+    tls_rustls::{RustlsAcceptor, RustlsConfig},
+};
+use entity::tbl_auth_user;
+use openssl::{nid::Nid, x509::X509};
+use rustls::{
+    RootCertStore, ServerConfig,
+    pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject},
+    server::WebPkiClientVerifier,
+};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tower_http::add_extension::AddExtension;
+
+use crate::{
+    AppState,
+    auth::{AuthUser, Role},
+    config::Mtls,
+};
+
+/// TLS握手时客户端提供并通过CA校验的证书主题CN, 未提供证书时为None
+/// 由MtlsAcceptor按连接写入请求扩展
+#[derive(Clone, Debug)]
+pub struct ClientCert(pub Option<String>);
+
+/// 构建校验客户端证书的TLS配置, required为false时允许不带证书的连接
+pub fn rustls_config(cert: &Path, key: &Path, mtls: &Mtls) -> anyhow::Result<RustlsConfig> {
+    let certs = CertificateDer::pem_file_iter(cert)?.collect::<Result<Vec<_>, _>>()?;
+    let key = PrivateKeyDer::from_pem_file(key)?;
+    let mut roots = RootCertStore::empty();
+    for ca in CertificateDer::pem_file_iter(&mtls.client_ca)? {
+        roots.add(ca?)?;
+    }
+    let builder = WebPkiClientVerifier::builder(Arc::new(roots));
+    let verifier = if mtls.required {
+        builder.build()?
+    } else {
+        builder.allow_unauthenticated().build()?
+    };
+    let mut config = ServerConfig::builder()
+        .with_client_cert_verifier(verifier)
+        .with_single_cert(certs, key)?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(RustlsConfig::from_config(Arc::new(config)))
+}
+
+/// 在RustlsAcceptor完成握手后读取客户端证书, 以ClientCert扩展附加到该连接的所有请求
+#[derive(Clone)]
+pub struct MtlsAcceptor {
+    inner: RustlsAcceptor,
+}
+
+impl MtlsAcceptor {
+    pub fn new(config: RustlsConfig) -> Self {
+        Self {
+            inner: RustlsAcceptor::new(config),
+        }
+    }
+}
+
+impl<I, S> Accept<I, S> for MtlsAcceptor
+where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    S: Send + 'static,
+{
+    type Stream = <RustlsAcceptor as Accept<I, S>>::Stream;
+    type Service = AddExtension<S, ClientCert>;
+    type Future = Pin<Box<dyn Future<Output = io::Result<(Self::Stream, Self::Service)>> + Send>>;
+
+    fn accept(&self, stream: I, service: S) -> Self::Future {
+        let acceptor = self.inner.clone();
+        Box::pin(async move {
+            let (stream, service) = acceptor.accept(stream, service).await?;
+            // 证书链已由WebPkiClientVerifier校验, 第一张为客户端证书
+            let common_name = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .and_then(|certs| certs.first())
+                .and_then(common_name);
+            Ok((stream, AddExtension::new(service, ClientCert(common_name))))
+        })
+    }
+}
+
+fn common_name(cert: &CertificateDer) -> Option<String> {
+    let x509 = match X509::from_der(cert) {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("client cert parse err: {}", e);
+            return None;
+        }
+    };
+    let entry = x509.subject_name().entries_by_nid(Nid::COMMONNAME).next()?;
+    match entry.data().to_string() {
+        Ok(v) => Some(v),
+        Err(e) => {
+            log::error!("client cert common name err: {}", e);
+            None
+        }
+    }
+}
+
+/// 客户端证书CN对应tbl_auth_user.username, 用户不存在或已禁用返回401
+/// 证书认证没有会话, 与API token一样无法访问需要RequireSession的接口
+pub async fn authenticate(app_state: &AppState, common_name: &str) -> Result<AuthUser, StatusCode> {
+    let tbl_auth_user = match tbl_auth_user::Entity::find()
+        .filter(tbl_auth_user::Column::Username.eq(common_name))
+        .one(&app_state.db_conn)
+        .await
+    {
+        Ok(Some(v)) if v.enabled => v,
+        Ok(_) => {
+            log::warn!("user {} of client cert not available", common_name);
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+        Err(e) => {
+            log::error!("tbl_auth_user find err: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    let role = match Role::from_str(&tbl_auth_user.role) {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("user {} role err: {}", tbl_auth_user.username, e);
+            return Err(StatusCode::FORBIDDEN);
+        }
+    };
+    Ok(AuthUser {
+        id: tbl_auth_user.id,
+        username: tbl_auth_user.username,
+        role,
+    })
+}
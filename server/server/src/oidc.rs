@@ -0,0 +1,566 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::{
+    Extension, Json,
+    extract::{ConnectInfo, Query, State},
+    http::{HeaderMap, HeaderValue, Method, StatusCode, header},
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, post},
+};
+use entity::tbl_auth_user;
+use openidconnect::{
+    AuthorizationCode, ClientId, ClientSecret, CsrfToken, EndpointMaybeSet, EndpointNotSet,
+    EndpointSet, IssuerUrl, Nonce, PkceCodeChallenge, PkceCodeVerifier, RedirectUrl, Scope,
+    TokenResponse,
+    core::{CoreAuthenticationFlow, CoreClient, CoreProviderMetadata},
+    reqwest,
+};
+use sea_orm::{ActiveValue::Set, ColumnTrait, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::OnceCell;
+
+use crate::{
+    AppState,
+    auth::{self, PublicRoute},
+    config::{Oidc, SERVER_TOML},
+    login_limit,
+    session::{self, ClientInfo},
+};
+
+// 授权请求的state/nonce/pkce_verifier, 有效期10分钟
+const OIDC_STATE_TREE: &str = "oidc_state";
+const OIDC_STATE_TTL_SECS: i64 = 10 * 60;
+// 浏览器端保存state的哈希, 回调时必须一致, 防止登录CSRF
+const OIDC_STATE_COOKIE: &str = "oidc_state";
+const OIDC_STATE_COOKIE_PATH: &str = "/api/oidc/callback";
+// 回调成功后发给前端的一次性票据, 前端用它换取token, 避免token出现在地址栏
+const OIDC_TICKET_TREE: &str = "oidc_ticket";
+const OIDC_TICKET_TTL_SECS: i64 = 60;
+// 每个IP在state有效期内最多发起的登录次数, 防止匿名请求大量写入state
+const OIDC_LOGIN_LIMIT: u32 = 20;
+
+type OidcClient = CoreClient<
+    EndpointSet,
+    EndpointNotSet,
+    EndpointNotSet,
+    EndpointNotSet,
+    EndpointMaybeSet,
+    EndpointMaybeSet,
+>;
+
+/// 登录流程的全部接口都无需登录即可访问
+pub fn public_routes() -> Vec<PublicRoute> {
+    public_routes_with_config(SERVER_TOML.oidc.clone())
+}
+
+/// 使用指定的OIDC配置挂载接口, 测试时可对接本地模拟的身份提供方
+pub fn public_routes_with_config(oidc: Option<Oidc>) -> Vec<PublicRoute> {
+    let config_extension = Extension(OidcConfig {
+        oidc: oidc.map(Arc::new),
+        provider_metadata: Arc::new(OnceCell::new()),
+    });
+    vec![
+        (Method::GET, "/oidc/config", get(oidc_config)),
+        (Method::GET, "/oidc/login", get(oidc_login)),
+        (Method::GET, "/oidc/callback", get(oidc_callback)),
+        (Method::POST, "/oidc/token", post(oidc_token)),
+    ]
+    .into_iter()
+    .map(|(method, path, method_router)| {
+        (method, path, method_router.layer(config_extension.clone()))
+    })
+    .collect()
+}
+
+// 未配置时oidc为None, 登录和回调接口返回404
+// discovery结果在进程内只获取一次, 失败时不缓存, 下次请求重试
+#[derive(Clone)]
+struct OidcConfig {
+    oidc: Option<Arc<Oidc>>,
+    provider_metadata: Arc<OnceCell<CoreProviderMetadata>>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct OidcState {
+    nonce: String,
+    pkce_verifier: String,
+    expires_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct OidcTicket {
+    user_id: i32,
+    expires_at: i64,
+}
+
+fn http_client() -> anyhow::Result<reqwest::Client> {
+    // 跟随重定向可能导致SSRF, 与身份提供方的交互不允许重定向
+    Ok(reqwest::ClientBuilder::new()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()?)
+}
+
+/// 使用缓存的discovery结果构建client, 首次调用时获取身份提供方的端点和签名公钥
+async fn oidc_client(
+    oidc: &Oidc,
+    provider_metadata: &OnceCell<CoreProviderMetadata>,
+    http_client: &reqwest::Client,
+) -> anyhow::Result<OidcClient> {
+    let provider_metadata = provider_metadata
+        .get_or_try_init(|| async {
+            CoreProviderMetadata::discover_async(
+                IssuerUrl::new(oidc.issuer_url.clone())?,
+                http_client,
+            )
+            .await
+            .map_err(anyhow::Error::from)
+        })
+        .await?;
+    Ok(CoreClient::from_provider_metadata(
+        provider_metadata.clone(),
+        ClientId::new(oidc.client_id.clone()),
+        oidc.client_secret.clone().map(ClientSecret::new),
+    )
+    .set_redirect_uri(RedirectUrl::new(oidc.redirect_url.clone())?))
+}
+
+fn take_value<T: for<'de> Deserialize<'de>>(
+    sled_db: &sled::Db,
+    tree_name: &str,
+    key: &str,
+) -> Option<T> {
+    let tree = match sled_db.open_tree(tree_name) {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("sled open_tree err: {}", e);
+            return None;
+        }
+    };
+    match tree.remove(key) {
+        Ok(Some(v)) => serde_json::from_slice(&v).ok(),
+        Ok(None) => None,
+        Err(e) => {
+            log::error!("sled remove {} err: {}", tree_name, e);
+            None
+        }
+    }
+}
+
+fn save_value<T: Serialize>(sled_db: &sled::Db, tree_name: &str, key: &str, value: &T) -> bool {
+    let tree = match sled_db.open_tree(tree_name) {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("sled open_tree err: {}", e);
+            return false;
+        }
+    };
+    match serde_json::to_vec(value) {
+        Ok(v) => match tree.insert(key, v) {
+            Ok(_) => true,
+            Err(e) => {
+                log::error!("sled insert {} err: {}", tree_name, e);
+                false
+            }
+        },
+        Err(e) => {
+            log::error!("{} to_vec err: {}", tree_name, e);
+            false
+        }
+    }
+}
+
+fn login_redirect(query: &str) -> Response {
+    Redirect::to(&format!("/login?{query}")).into_response()
+}
+
+// 跨站跳转回来时需要携带, 只能用Lax
+fn state_cookie(value: &str, max_age: i64) -> String {
+    format!(
+        "{OIDC_STATE_COOKIE}={value}; Path={OIDC_STATE_COOKIE_PATH}; Max-Age={max_age}; HttpOnly; Secure; SameSite=Lax"
+    )
+}
+
+fn cookie_value<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|v| v.trim().split_once('='))
+        .find(|(k, _)| *k == name)
+        .map(|(_, v)| v)
+}
+
+async fn oidc_config(Extension(config): Extension<OidcConfig>) -> impl IntoResponse {
+    (
+        StatusCode::OK,
+        [("code", "200"), ("msg", "ok")],
+        Json(json!({
+            "enabled": config.oidc.is_some()
+        })),
+    )
+}
+
+/// 生成state、nonce和PKCE参数后重定向到身份提供方的授权页面
+async fn oidc_login(
+    State(app_state): State<AppState>,
+    Extension(config): Extension<OidcConfig>,
+    ConnectInfo(socket_addr): ConnectInfo<SocketAddr>,
+) -> Response {
+    let Some(oidc) = config.oidc.as_deref() else {
+        return (StatusCode::NOT_FOUND, Json(json!({}))).into_response();
+    };
+    // 与密码登录共用IP锁定, 另外限制每个IP创建state的频率
+    let ip_key = login_limit::ip_key(&socket_addr.ip().to_string());
+    if login_limit::locked_secs(&app_state.sled_db, std::slice::from_ref(&ip_key)) > 0
+        || !login_limit::allow_request(
+            &app_state.sled_db,
+            &format!("oidc_login:{ip_key}"),
+            OIDC_LOGIN_LIMIT,
+            OIDC_STATE_TTL_SECS,
+        )
+    {
+        return login_redirect("oidc_error=locked");
+    }
+    let client = match http_client() {
+        Ok(http_client) => oidc_client(oidc, &config.provider_metadata, &http_client).await,
+        Err(e) => Err(e),
+    };
+    let client = match client {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("oidc discover {} err: {}", oidc.issuer_url, e);
+            return login_redirect("oidc_error=discover");
+        }
+    };
+    let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+    let (auth_url, csrf_token, nonce) = client
+        .authorize_url(
+            CoreAuthenticationFlow::AuthorizationCode,
+            CsrfToken::new_random,
+            Nonce::new_random,
+        )
+        .add_scope(Scope::new("profile".to_string()))
+        .add_scope(Scope::new("email".to_string()))
+        .set_pkce_challenge(pkce_challenge)
+        .url();
+    let oidc_state = OidcState {
+        nonce: nonce.secret().clone(),
+        pkce_verifier: pkce_verifier.secret().clone(),
+        expires_at: chrono::Utc::now().timestamp() + OIDC_STATE_TTL_SECS,
+    };
+    if !save_value(
+        &app_state.sled_db,
+        OIDC_STATE_TREE,
+        csrf_token.secret(),
+        &oidc_state,
+    ) {
+        return login_redirect("oidc_error=server");
+    }
+    (
+        [(
+            header::SET_COOKIE,
+            state_cookie(
+                &session::sha256_hex(csrf_token.secret()),
+                OIDC_STATE_TTL_SECS,
+            ),
+        )],
+        Redirect::to(auth_url.as_str()),
+    )
+        .into_response()
+}
+
+#[derive(Deserialize, Debug)]
+struct CallbackInputDto {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+}
+/// 身份提供方回调, 校验state后用授权码和PKCE换取id_token, 按claim匹配或创建用户
+async fn oidc_callback(
+    State(app_state): State<AppState>,
+    Extension(config): Extension<OidcConfig>,
+    headers: HeaderMap,
+    Query(callback_input_dto): Query<CallbackInputDto>,
+) -> Response {
+    let mut response = callback(&app_state, &config, &headers, callback_input_dto).await;
+    // state只能使用一次, 无论成功与否都清除cookie
+    if let Ok(v) = HeaderValue::from_str(&state_cookie("", 0)) {
+        response.headers_mut().append(header::SET_COOKIE, v);
+    }
+    response
+}
+
+async fn callback(
+    app_state: &AppState,
+    config: &OidcConfig,
+    headers: &HeaderMap,
+    callback_input_dto: CallbackInputDto,
+) -> Response {
+    let Some(oidc) = config.oidc.as_deref() else {
+        return (StatusCode::NOT_FOUND, Json(json!({}))).into_response();
+    };
+    if let Some(error) = callback_input_dto.error {
+        log::warn!("oidc callback error: {}", error);
+        return login_redirect("oidc_error=denied");
+    }
+    let (Some(code), Some(state)) = (callback_input_dto.code, callback_input_dto.state) else {
+        return login_redirect("oidc_error=invalid");
+    };
+    // 回调必须来自发起登录的同一个浏览器
+    let state_matched = cookie_value(headers, OIDC_STATE_COOKIE)
+        .is_some_and(|v| session::hash_eq(v, &session::sha256_hex(&state)));
+    if !state_matched {
+        log::warn!("oidc state {} not bound to this browser", state);
+        return login_redirect("oidc_error=state");
+    }
+    let Some(oidc_state) = take_value::<OidcState>(&app_state.sled_db, OIDC_STATE_TREE, &state)
+    else {
+        log::warn!("oidc state {} not exists", state);
+        return login_redirect("oidc_error=state");
+    };
+    if oidc_state.expires_at <= chrono::Utc::now().timestamp() {
+        log::warn!("oidc state {} expired", state);
+        return login_redirect("oidc_error=state");
+    }
+    let identity = match exchange_identity(oidc, &config.provider_metadata, &code, oidc_state).await
+    {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("oidc exchange code err: {}", e);
+            return login_redirect("oidc_error=exchange");
+        }
+    };
+    let tbl_auth_user = match find_or_provision_user(app_state, oidc, &identity).await {
+        Ok(v) => v,
+        Err(query) => return login_redirect(query),
+    };
+    let ticket = uuid::Uuid::new_v4().simple().to_string();
+    let oidc_ticket = OidcTicket {
+        user_id: tbl_auth_user.id,
+        expires_at: chrono::Utc::now().timestamp() + OIDC_TICKET_TTL_SECS,
+    };
+    if !save_value(&app_state.sled_db, OIDC_TICKET_TREE, &ticket, &oidc_ticket) {
+        return login_redirect("oidc_error=server");
+    }
+    auth::insert_log(app_state, format!("oidc login {}", tbl_auth_user.username)).await;
+    login_redirect(&format!("oidc_ticket={ticket}"))
+}
+
+struct OidcIdentity {
+    subject: String,
+    // 自动创建用户时使用的用户名
+    username: String,
+}
+
+/// 换取并校验id_token, 返回sub和配置的username_claim对应的值
+async fn exchange_identity(
+    oidc: &Oidc,
+    provider_metadata: &OnceCell<CoreProviderMetadata>,
+    code: &str,
+    oidc_state: OidcState,
+) -> anyhow::Result<OidcIdentity> {
+    let http_client = http_client()?;
+    let client = oidc_client(oidc, provider_metadata, &http_client).await?;
+    let token_response = client
+        .exchange_code(AuthorizationCode::new(code.to_string()))?
+        .set_pkce_verifier(PkceCodeVerifier::new(oidc_state.pkce_verifier))
+        .request_async(&http_client)
+        .await?;
+    let id_token = token_response
+        .id_token()
+        .ok_or_else(|| anyhow::anyhow!("id_token not returned"))?;
+    let claims = id_token.claims(&client.id_token_verifier(), &Nonce::new(oidc_state.nonce))?;
+    let subject = claims.subject().as_str().to_string();
+    let username = match oidc.username_claim.as_str() {
+        "sub" => Some(subject.clone()),
+        "email" => {
+            // 未验证的邮箱可能被他人注册, 不能用作用户名
+            if claims.email_verified() == Some(false) {
+                anyhow::bail!("email not verified");
+            }
+            claims.email().map(|v| v.as_str().to_string())
+        }
+        "preferred_username" => claims.preferred_username().map(|v| v.as_str().to_string()),
+        claim => anyhow::bail!("unsupported username_claim: {claim}"),
+    };
+    let username = username
+        .filter(|v| !v.is_empty())
+        .ok_or_else(|| anyhow::anyhow!("claim {} not returned", oidc.username_claim))?;
+    Ok(OidcIdentity { subject, username })
+}
+
+/// 只按oidc_subject匹配用户, 已有的本地用户需管理员显式关联
+async fn find_or_provision_user(
+    app_state: &AppState,
+    oidc: &Oidc,
+    identity: &OidcIdentity,
+) -> Result<tbl_auth_user::Model, &'static str> {
+    match tbl_auth_user::Entity::find()
+        .filter(tbl_auth_user::Column::OidcSubject.eq(&identity.subject))
+        .one(&app_state.db_conn)
+        .await
+    {
+        Ok(Some(tbl_auth_user)) if tbl_auth_user.enabled => Ok(tbl_auth_user),
+        Ok(Some(tbl_auth_user)) => {
+            log::warn!("oidc user {} disabled", tbl_auth_user.username);
+            Err("oidc_error=disabled")
+        }
+        Ok(None) if oidc.auto_provision => provision_user(app_state, oidc, identity).await,
+        Ok(None) => {
+            log::warn!("oidc subject {} not linked", identity.subject);
+            Err("oidc_error=not_exists")
+        }
+        Err(e) => {
+            log::error!("tbl_auth_user find err: {}", e);
+            Err("oidc_error=server")
+        }
+    }
+}
+
+async fn provision_user(
+    app_state: &AppState,
+    oidc: &Oidc,
+    identity: &OidcIdentity,
+) -> Result<tbl_auth_user::Model, &'static str> {
+    // 用户名已被本地用户占用时不能接管该用户
+    match tbl_auth_user::Entity::find()
+        .filter(tbl_auth_user::Column::Username.eq(&identity.username))
+        .one(&app_state.db_conn)
+        .await
+    {
+        Ok(None) => {}
+        Ok(Some(_)) => {
+            log::warn!(
+                "oidc subject {} username {} already exists",
+                identity.subject,
+                identity.username
+            );
+            return Err("oidc_error=conflict");
+        }
+        Err(e) => {
+            log::error!("tbl_auth_user find err: {}", e);
+            return Err("oidc_error=server");
+        }
+    }
+    // 自动创建的用户只能通过OIDC登录, 本地密码为不可猜测的随机值
+    let password_hash = match auth::hash_password(&session::to_hex(&rand::random::<[u8; 32]>())) {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("hash password err: {}", e);
+            return Err("oidc_error=server");
+        }
+    };
+    let tbl_auth_user_am = tbl_auth_user::ActiveModel {
+        username: Set(identity.username.clone()),
+        password: Set(password_hash),
+        role: Set(oidc.default_role.as_str().to_string()),
+        oidc_subject: Set(Some(identity.subject.clone())),
+        ..Default::default()
+    };
+    match tbl_auth_user::Entity::insert(tbl_auth_user_am)
+        .exec_with_returning(&app_state.db_conn)
+        .await
+    {
+        Ok(tbl_auth_user) => {
+            auth::insert_log(
+                app_state,
+                format!(
+                    "oidc provision user {} subject: {} role: {}",
+                    identity.username,
+                    identity.subject,
+                    oidc.default_role.as_str()
+                ),
+            )
+            .await;
+            Ok(tbl_auth_user)
+        }
+        Err(e) => {
+            log::error!("tbl_auth_user insert err: {}", e);
+            Err("oidc_error=server")
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenInputDto {
+    ticket: String,
+}
+/// 使用一次性票据换取token, 身份提供方已完成认证, 不再要求两步验证
+async fn oidc_token(
+    State(app_state): State<AppState>,
+    ConnectInfo(socket_addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(token_input_dto): Json<TokenInputDto>,
+) -> impl IntoResponse {
+    let user_id = match take_value::<OidcTicket>(
+        &app_state.sled_db,
+        OIDC_TICKET_TREE,
+        &token_input_dto.ticket,
+    ) {
+        Some(oidc_ticket) if oidc_ticket.expires_at > chrono::Utc::now().timestamp() => {
+            oidc_ticket.user_id
+        }
+        _ => {
+            log::warn!("oidc ticket invalid");
+            return (
+                StatusCode::UNAUTHORIZED,
+                [("code", "401"), ("msg", "UNAUTHORIZED")],
+                Json(json!({})),
+            );
+        }
+    };
+    match tbl_auth_user::Entity::find_by_id(user_id)
+        .one(&app_state.db_conn)
+        .await
+    {
+        Ok(Some(tbl_auth_user)) if tbl_auth_user.enabled => auth::token_response(
+            &app_state.sled_db,
+            &tbl_auth_user,
+            &ClientInfo::new(&socket_addr, &headers),
+            false,
+        ),
+        Ok(_) => {
+            log::warn!("user {} of oidc ticket not available", user_id);
+            (
+                StatusCode::UNAUTHORIZED,
+                [("code", "401"), ("msg", "UNAUTHORIZED")],
+                Json(json!({})),
+            )
+        }
+        Err(e) => {
+            log::error!("tbl_auth_user find err: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_auth_user find err")],
+                Json(json!({})),
+            )
+        }
+    }
+}
+
+/// 清理过期的state和票据, 由token_expired_task周期调用
+pub fn purge_expired(sled_db: &sled::Db) {
+    let ts_now = chrono::Utc::now().timestamp();
+    for tree_name in [OIDC_STATE_TREE, OIDC_TICKET_TREE] {
+        let tree = match sled_db.open_tree(tree_name) {
+            Ok(v) => v,
+            Err(e) => {
+                log::error!("sled open_tree err: {}", e);
+                continue;
+            }
+        };
+        for (k, v) in tree.iter().flatten() {
+            let expires_at = serde_json::from_slice::<serde_json::Value>(&v)
+                .ok()
+                .and_then(|value| value["expires_at"].as_i64())
+                .unwrap_or(0);
+            if expires_at <= ts_now
+                && let Err(e) = tree.remove(&k)
+            {
+                log::error!("sled remove {} err: {}", tree_name, e);
+            }
+        }
+    }
+}
@@ -3,34 +3,47 @@ use std::net::SocketAddr;
 use axum::{
     Json, Router,
     extract::{ConnectInfo, Multipart, Path, Query, State},
-    http::{HeaderMap, HeaderValue, StatusCode, header},
+    http::{HeaderMap, HeaderValue, Method, StatusCode, header},
     response::IntoResponse,
-    routing::{get, patch},
+    routing::{get, patch, post},
 };
 use entity::{tbl_pdf_article, tbl_pdf_article_access_log};
 use sea_orm::{
     ActiveValue::Set, ColumnTrait, EntityTrait, IntoActiveModel, PaginatorTrait, QueryFilter,
-    QueryOrder,
+    QueryOrder, sea_query::Expr,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use validator::Validate;
 
-use crate::AppState;
+use crate::{
+    AppState,
+    auth::{ADMIN, EDITOR, PublicRoute, RequireRole},
+    tag::{self, TagMode},
+};
 
 pub fn routers(state: AppState) -> Router {
     Router::new()
-        .route("/pdf_articles", get(query).post(create))
-        .route(
-            "/pdf_articles/{id}",
-            patch(update).delete(delete).get(get_pdf_content),
-        )
+        .route("/pdf_articles", post(create))
+        .route("/pdf_articles/{id}", patch(update).delete(delete))
         .with_state(state)
 }
 
+/// 无需登录即可访问的接口
+pub fn public_routes() -> Vec<PublicRoute> {
+    vec![
+        (Method::GET, "/pdf_articles", get(query)),
+        (Method::GET, "/pdf_articles/{id}", get(get_pdf_content)),
+    ]
+}
+
 #[derive(Deserialize, Debug, Validate)]
 struct QueryInputDto {
     title: Option<String>,
+    // 逗号分隔的标签
+    tags: Option<String>,
+    #[serde(default)]
+    tag_mode: TagMode,
     size: u64,
     page: u64,
 }
@@ -40,6 +53,7 @@ struct QueryOutputDto {
     id: i32,
     title: String,
     access_count: u64,
+    tags: Vec<String>,
     created_at: i64,
     updated_at: i64,
 }
@@ -47,11 +61,18 @@ async fn query(
     app_state: State<AppState>,
     Query(query_input_dto): Query<QueryInputDto>,
 ) -> impl IntoResponse {
-    let mut select = tbl_pdf_article::Entity::find();
-    if let Some(title) = query_input_dto.title {
-        if !title.is_empty() {
-            let like_pattern = format!("%{title}%");
-            select = select.filter(tbl_pdf_article::Column::Title.like(like_pattern));
+    let mut select =
+        tbl_pdf_article::Entity::find().filter(tbl_pdf_article::Column::DeletedAt.is_null());
+    if let Some(title) = query_input_dto.title
+        && !title.is_empty()
+    {
+        let like_pattern = format!("%{title}%");
+        select = select.filter(tbl_pdf_article::Column::Title.like(like_pattern));
+    }
+    if let Some(tags) = &query_input_dto.tags {
+        let tags = tag::parse_tags(tags);
+        if !tags.is_empty() {
+            select = select.filter(tag::pdf_article_filter(&tags, query_input_dto.tag_mode));
         }
     }
 
@@ -97,6 +118,21 @@ async fn query(
             );
         }
     };
+    let pdf_article_ids: Vec<i32> = tbl_pdf_articles.iter().map(|v| v.id).collect();
+    let mut pdf_article_tags =
+        match tag::pdf_article_tags(&app_state.db_conn, &pdf_article_ids).await {
+            Ok(v) => v,
+            Err(e) => {
+                log::error!("tbl_pdf_article_tag find err: {}", e);
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(json!( {
+                            "code": StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+                            "msg": "pg connection err".to_string(),
+                    })),
+                );
+            }
+        };
     let mut pdf_articles = Vec::new();
     for tbl_pdf_article in tbl_pdf_articles {
         let access_count = match tbl_pdf_article_access_log::Entity::find()
@@ -120,6 +156,9 @@ async fn query(
             id: tbl_pdf_article.id,
             title: tbl_pdf_article.title,
             access_count,
+            tags: pdf_article_tags
+                .remove(&tbl_pdf_article.id)
+                .unwrap_or_default(),
             created_at: tbl_pdf_article.created_at.and_utc().timestamp_millis(),
             updated_at: tbl_pdf_article.updated_at.and_utc().timestamp_millis(),
         });
@@ -141,7 +180,11 @@ async fn query(
     )
 }
 
-async fn create(app_state: State<AppState>, mut multipart: Multipart) -> impl IntoResponse {
+async fn create(
+    app_state: State<AppState>,
+    _: RequireRole<EDITOR>,
+    mut multipart: Multipart,
+) -> impl IntoResponse {
     let mut file_ids = Vec::new();
     while let Some(field) = match multipart.next_field().await {
         Ok(v) => v,
@@ -210,9 +253,11 @@ async fn create(app_state: State<AppState>, mut multipart: Multipart) -> impl In
 async fn update(
     Path(id): Path<i32>,
     State(app_state): State<AppState>,
+    _: RequireRole<EDITOR>,
     mut multipart: Multipart,
 ) -> impl IntoResponse {
     let tbl_pdf_article = match tbl_pdf_article::Entity::find_by_id(id)
+        .filter(tbl_pdf_article::Column::DeletedAt.is_null())
         .one(&app_state.db_conn)
         .await
     {
@@ -279,11 +324,22 @@ async fn update(
             }
         }
     }
-    return (StatusCode::BAD_REQUEST, Json(json!({})));
+    (StatusCode::BAD_REQUEST, Json(json!({})))
 }
 
-async fn delete(Path(id): Path<i32>, State(app_state): State<AppState>) -> impl IntoResponse {
-    match tbl_pdf_article::Entity::delete_by_id(id)
+async fn delete(
+    Path(id): Path<i32>,
+    State(app_state): State<AppState>,
+    _: RequireRole<ADMIN>,
+) -> impl IntoResponse {
+    // 只标记删除时间放入回收站, 访问记录保留到物理删除时
+    match tbl_pdf_article::Entity::update_many()
+        .col_expr(
+            tbl_pdf_article::Column::DeletedAt,
+            Expr::value(chrono::Utc::now().naive_utc()),
+        )
+        .filter(tbl_pdf_article::Column::Id.eq(id))
+        .filter(tbl_pdf_article::Column::DeletedAt.is_null())
         .exec(&app_state.db_conn)
         .await
     {
@@ -320,6 +376,7 @@ async fn get_pdf_content(
     State(app_state): State<AppState>,
 ) -> impl IntoResponse {
     match tbl_pdf_article::Entity::find_by_id(id)
+        .filter(tbl_pdf_article::Column::DeletedAt.is_null())
         .one(&app_state.db_conn)
         .await
     {
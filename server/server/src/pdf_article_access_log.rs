@@ -11,7 +11,10 @@ use serde::{Deserialize, Serialize};
 use serde_json::json;
 use validator::Validate;
 
-use crate::AppState;
+use crate::{
+    AppState,
+    auth::{ADMIN, RequireRole},
+};
 
 pub fn routers(state: AppState) -> Router {
     Router::new()
@@ -38,21 +41,21 @@ struct QueryOutputDto {
 }
 async fn query(
     app_state: State<AppState>,
+    _: RequireRole<ADMIN>,
     Query(query_input_dto): Query<QueryInputDto>,
 ) -> impl IntoResponse {
     let mut select = tbl_pdf_article_access_log::Entity::find();
-    if let Some(src_ip) = query_input_dto.src_ip {
-        if !src_ip.is_empty() {
-            let like_pattern = format!("%{src_ip}%");
-            select = select.filter(tbl_pdf_article_access_log::Column::SrcIp.like(like_pattern));
-        }
+    if let Some(src_ip) = query_input_dto.src_ip
+        && !src_ip.is_empty()
+    {
+        let like_pattern = format!("%{src_ip}%");
+        select = select.filter(tbl_pdf_article_access_log::Column::SrcIp.like(like_pattern));
     }
-    if let Some(user_agent) = query_input_dto.user_agent {
-        if !user_agent.is_empty() {
-            let like_pattern = format!("%{user_agent}%");
-            select =
-                select.filter(tbl_pdf_article_access_log::Column::UserAgent.like(like_pattern));
-        }
+    if let Some(user_agent) = query_input_dto.user_agent
+        && !user_agent.is_empty()
+    {
+        let like_pattern = format!("%{user_agent}%");
+        select = select.filter(tbl_pdf_article_access_log::Column::UserAgent.like(like_pattern));
     }
     let paginator = select
         .order_by_desc(tbl_pdf_article_access_log::Column::CreatedAt)
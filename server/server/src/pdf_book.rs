@@ -0,0 +1,648 @@
+use std::fmt::Write;
+
+use crate::markdown::{self, TextBlock};
+
+// A4纸, 单位为点
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN_X: f32 = 64.0;
+const MARGIN_TOP: f32 = 72.0;
+const MARGIN_BOTTOM: f32 = 72.0;
+const CONTENT_WIDTH: f32 = PAGE_WIDTH - MARGIN_X * 2.0;
+const FOOTER_Y: f32 = 40.0;
+
+// 固定的对象编号, 页面树和目录在最后写出
+const CATALOG_ID: usize = 1;
+const PAGES_ID: usize = 2;
+const FONT_ID: usize = 3;
+const CID_FONT_ID: usize = 4;
+const FONT_DESCRIPTOR_ID: usize = 5;
+
+/// 一篇文章, 日期为yyyy-mm-dd
+pub struct BookArticle<'a> {
+    pub date: &'a str,
+    pub title: &'a str,
+    pub tags: &'a [String],
+    pub content: &'a str,
+}
+
+/// 目录中的一项, page_no从1开始
+#[derive(Clone)]
+pub struct TocEntry {
+    pub date: String,
+    pub title: String,
+    pub page_no: usize,
+}
+
+struct TextLine {
+    x: f32,
+    y: f32,
+    size: f32,
+    gray: bool,
+    text: String,
+}
+
+/// 排好版的一页, 空页用于补齐页数
+#[derive(Default)]
+pub struct Page {
+    lines: Vec<TextLine>,
+    // 分隔线的纵坐标
+    rules: Vec<f32>,
+}
+
+impl Page {
+    fn is_empty(&self) -> bool {
+        self.lines.is_empty() && self.rules.is_empty()
+    }
+
+    fn content(&self, page_no: usize) -> String {
+        let mut content = String::new();
+        for line in &self.lines {
+            let _ = writeln!(
+                content,
+                "BT /F1 {:.1} Tf {} g {:.2} {:.2} Td <{}> Tj ET",
+                line.size,
+                if line.gray { "0.4" } else { "0" },
+                line.x,
+                line.y,
+                utf16_hex(&line.text)
+            );
+        }
+        for y in &self.rules {
+            let _ = writeln!(
+                content,
+                "0.7 G 0.5 w {:.2} {:.2} m {:.2} {:.2} l S",
+                MARGIN_X,
+                y,
+                PAGE_WIDTH - MARGIN_X,
+                y
+            );
+        }
+        let page_no = page_no.to_string();
+        let _ = writeln!(
+            content,
+            "BT /F1 9 Tf 0.4 g {:.2} {:.2} Td <{}> Tj ET",
+            (PAGE_WIDTH - text_width(&page_no, 9.0)) / 2.0,
+            FOOTER_Y,
+            utf16_hex(&page_no)
+        );
+        content
+    }
+}
+
+/// 按版面从上往下排列文字, 放不下时换页
+struct Cursor {
+    pages: Vec<Page>,
+    page: Page,
+    y: f32,
+}
+
+impl Cursor {
+    fn new() -> Self {
+        Self {
+            pages: Vec::new(),
+            page: Page::default(),
+            y: PAGE_HEIGHT - MARGIN_TOP,
+        }
+    }
+
+    fn ensure(&mut self, height: f32) {
+        if self.y - height < MARGIN_BOTTOM && !self.page.is_empty() {
+            self.pages.push(std::mem::take(&mut self.page));
+            self.y = PAGE_HEIGHT - MARGIN_TOP;
+        }
+    }
+
+    fn gap(&mut self, height: f32) {
+        if !self.page.is_empty() {
+            self.y -= height;
+        }
+    }
+
+    fn line(&mut self, x: f32, size: f32, leading: f32, gray: bool, text: String) {
+        self.ensure(leading);
+        self.page.lines.push(TextLine {
+            x,
+            y: self.y - size,
+            size,
+            gray,
+            text,
+        });
+        self.y -= leading;
+    }
+
+    /// 自动折行, prefix只出现在第一行
+    fn paragraph(
+        &mut self,
+        indent: f32,
+        prefix: &str,
+        text: &str,
+        size: f32,
+        leading: f32,
+        gray: bool,
+    ) {
+        let prefix_width = text_width(prefix, size);
+        let max_width = CONTENT_WIDTH - indent - prefix_width;
+        let mut first = true;
+        for segment in text.split('\n') {
+            for line in wrap(segment, size, max_width) {
+                if first && !prefix.is_empty() {
+                    self.ensure(leading);
+                    self.line(MARGIN_X + indent, size, 0.0, gray, prefix.to_string());
+                }
+                first = false;
+                self.line(MARGIN_X + indent + prefix_width, size, leading, gray, line);
+            }
+        }
+    }
+
+    fn rule(&mut self) {
+        self.ensure(16.0);
+        self.y -= 8.0;
+        self.page.rules.push(self.y);
+        self.y -= 8.0;
+    }
+
+    fn finish(mut self) -> Vec<Page> {
+        if !self.page.is_empty() || self.pages.is_empty() {
+            self.pages.push(self.page);
+        }
+        self.pages
+    }
+}
+
+/// 一篇文章排版后的页面, 每篇文章从新的一页开始
+pub fn layout_article(article: &BookArticle) -> Vec<Page> {
+    let mut cursor = Cursor::new();
+    cursor.paragraph(0.0, "", article.title, 18.0, 28.0, false);
+    let mut meta = article.date.to_string();
+    if !article.tags.is_empty() {
+        meta.push_str("  #");
+        meta.push_str(&article.tags.join(" #"));
+    }
+    cursor.paragraph(0.0, "", &meta, 9.0, 14.0, true);
+    cursor.gap(12.0);
+    for block in markdown::text_blocks(article.content) {
+        match block {
+            TextBlock::Heading(v) => {
+                cursor.gap(8.0);
+                cursor.paragraph(0.0, "", &v, 13.0, 20.0, false);
+            }
+            TextBlock::Paragraph(v) => {
+                cursor.paragraph(0.0, "", &v, 11.0, 17.0, false);
+                cursor.gap(6.0);
+            }
+            TextBlock::ListItem(depth, v) => {
+                cursor.paragraph(14.0 * (depth - 1) as f32, "· ", &v, 11.0, 17.0, false);
+            }
+            TextBlock::Code(v) => cursor.paragraph(12.0, "", &v, 9.0, 13.0, true),
+            TextBlock::Rule => cursor.rule(),
+        }
+    }
+    cursor.finish()
+}
+
+/// 目录页, 标题过长时截断, 每项只占一行
+pub fn layout_toc(entries: &[TocEntry]) -> Vec<Page> {
+    const SIZE: f32 = 11.0;
+    let mut cursor = Cursor::new();
+    cursor.line(MARGIN_X, 18.0, 28.0, false, "目录".to_string());
+    cursor.gap(12.0);
+    if entries.is_empty() {
+        cursor.line(MARGIN_X, SIZE, 18.0, true, "(无文章)".to_string());
+    }
+    for entry in entries {
+        let page_no = entry.page_no.to_string();
+        let page_no_x = PAGE_WIDTH - MARGIN_X - text_width(&page_no, SIZE);
+        let title = truncate(
+            &format!("{}  {}", entry.date, entry.title),
+            SIZE,
+            CONTENT_WIDTH - 48.0,
+        );
+        let leader_x = MARGIN_X + text_width(&title, SIZE) + 6.0;
+        let dots = ((page_no_x - 6.0 - leader_x) / text_width(".", SIZE)).max(0.0) as usize;
+        cursor.ensure(18.0);
+        cursor.line(MARGIN_X, SIZE, 0.0, false, title);
+        cursor.line(leader_x, SIZE, 0.0, true, ".".repeat(dots));
+        cursor.line(page_no_x, SIZE, 18.0, false, page_no);
+    }
+    cursor.finish()
+}
+
+/// 目录的页数, 每项只占一行, 只与目录项数有关
+pub fn toc_page_count(entry_count: usize) -> usize {
+    let entry = TocEntry {
+        date: String::new(),
+        title: String::new(),
+        page_no: 0,
+    };
+    layout_toc(&vec![entry; entry_count]).len()
+}
+
+/// 排版用的字宽, ASCII为半角, 其他字符按全角计算, 与字体的/W设置一致
+fn char_width(ch: char) -> f32 {
+    if (' '..='~').contains(&ch) { 0.5 } else { 1.0 }
+}
+
+fn text_width(text: &str, size: f32) -> f32 {
+    text.chars().map(char_width).sum::<f32>() * size
+}
+
+/// 按宽度折行, 英文单词尽量不从中间断开
+fn wrap(text: &str, size: f32, max_width: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut width = 0.0;
+    for ch in text.chars() {
+        let ch_width = char_width(ch) * size;
+        if width + ch_width > max_width && !line.is_empty() {
+            let mut carry = String::new();
+            if ch.is_ascii_graphic()
+                && let Some(pos) = line.rfind(' ')
+                && pos > 0
+                && line[pos + 1..].chars().all(|c| c.is_ascii_graphic())
+            {
+                carry = line[pos + 1..].to_string();
+                line.truncate(pos);
+            }
+            lines.push(std::mem::take(&mut line).trim_end().to_string());
+            line = carry;
+            width = text_width(&line, size);
+            if line.is_empty() && ch == ' ' {
+                continue;
+            }
+        }
+        line.push(ch);
+        width += ch_width;
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+fn truncate(text: &str, size: f32, max_width: f32) -> String {
+    if text_width(text, size) <= max_width {
+        return text.to_string();
+    }
+    let mut truncated = String::new();
+    let mut width = text_width("…", size);
+    for ch in text.chars() {
+        width += char_width(ch) * size;
+        if width > max_width {
+            break;
+        }
+        truncated.push(ch);
+    }
+    truncated.push('…');
+    truncated
+}
+
+/// UniGB-UCS2-H编码的十六进制字符串, 基本平面以外的字符替换为问号, 控制字符去掉
+fn utf16_hex(text: &str) -> String {
+    let mut hex = String::with_capacity(text.len() * 4);
+    for ch in text.chars().filter(|ch| !ch.is_control()) {
+        let code = u16::try_from(ch as u32).unwrap_or(b'?' as u16);
+        let _ = write!(hex, "{code:04X}");
+    }
+    hex
+}
+
+/// PDF中的文本字符串, UTF-16BE加BOM, 用于书签和文档信息
+fn text_string(text: &str) -> String {
+    let mut hex = String::from("<FEFF");
+    for code in text.encode_utf16() {
+        let _ = write!(hex, "{code:04X}");
+    }
+    hex.push('>');
+    hex
+}
+
+/// 流式写PDF, 页面写出后只保留对象偏移和页面编号, 页面树、书签和交叉引用表在finish时写出
+/// 页面顺序由页面树决定, 因此最后写出的目录页也可以排在最前面
+/// 使用阅读器内置的STSong-Light中文字体, 不嵌入字体文件, 导出的文件较小;
+/// Adobe Reader需要安装亚洲语言字体包, 没有该字体且不会替换字体的阅读器中文字显示为空白或方块
+pub struct PdfWriter {
+    buf: Vec<u8>,
+    offset: usize,
+    // 下标为对象编号, 0号对象不使用
+    xref: Vec<usize>,
+    front_page_ids: Vec<usize>,
+    page_ids: Vec<usize>,
+    outlines: Vec<(String, usize)>,
+}
+
+impl Default for PdfWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PdfWriter {
+    pub fn new() -> Self {
+        let mut pdf_writer = Self {
+            buf: Vec::new(),
+            offset: 0,
+            xref: vec![0; FONT_DESCRIPTOR_ID + 1],
+            front_page_ids: Vec::new(),
+            page_ids: Vec::new(),
+            outlines: Vec::new(),
+        };
+        pdf_writer.write(b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n");
+        pdf_writer.object(
+            FONT_ID,
+            format!(
+                "<< /Type /Font /Subtype /Type0 /BaseFont /STSong-Light-UniGB-UCS2-H /Encoding /UniGB-UCS2-H /DescendantFonts [{CID_FONT_ID} 0 R] >>"
+            ),
+        );
+        pdf_writer.object(
+            CID_FONT_ID,
+            format!(
+                "<< /Type /Font /Subtype /CIDFontType0 /BaseFont /STSong-Light /CIDSystemInfo << /Registry (Adobe) /Ordering (GB1) /Supplement 4 >> /FontDescriptor {FONT_DESCRIPTOR_ID} 0 R /DW 1000 /W [1 95 500] >>"
+            ),
+        );
+        pdf_writer.object(
+            FONT_DESCRIPTOR_ID,
+            "<< /Type /FontDescriptor /FontName /STSong-Light /Flags 6 /FontBBox [-25 -254 1000 880] /ItalicAngle 0 /Ascent 880 /Descent -120 /CapHeight 880 /StemV 93 >>".to_string(),
+        );
+        pdf_writer
+    }
+
+    /// 写出一页, 返回页面对象编号, page_no为页脚显示的页码
+    pub fn add_page(&mut self, page: &Page, page_no: usize) -> usize {
+        let page_id = self.write_page(page, page_no);
+        self.page_ids.push(page_id);
+        page_id
+    }
+
+    /// 写出放在最前面的页面, 例如最后才能确定内容的目录页
+    pub fn add_front_page(&mut self, page: &Page, page_no: usize) -> usize {
+        let page_id = self.write_page(page, page_no);
+        self.front_page_ids.push(page_id);
+        page_id
+    }
+
+    fn write_page(&mut self, page: &Page, page_no: usize) -> usize {
+        let content = page.content(page_no);
+        let content_id = self.alloc_id();
+        self.begin_object(content_id);
+        self.write(format!("<< /Length {} >>\nstream\n", content.len()).as_bytes());
+        self.write(content.as_bytes());
+        self.write(b"\nendstream\nendobj\n");
+        let page_id = self.alloc_id();
+        self.object(
+            page_id,
+            format!(
+                "<< /Type /Page /Parent {PAGES_ID} 0 R /MediaBox [0 0 {PAGE_WIDTH} {PAGE_HEIGHT}] /Resources << /Font << /F1 {FONT_ID} 0 R >> >> /Contents {content_id} 0 R >>"
+            ),
+        );
+        page_id
+    }
+
+    /// 添加指向某页的书签
+    pub fn add_outline(&mut self, title: &str, page_id: usize) {
+        self.outlines.push((title.to_string(), page_id));
+    }
+
+    /// 取走已经写好的数据
+    pub fn take(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.buf)
+    }
+
+    /// 写页面树、书签、文档信息和交叉引用表, 返回剩余的数据
+    pub fn finish(mut self, title: &str) -> Vec<u8> {
+        let outlines = std::mem::take(&mut self.outlines);
+        let outlines_id = self.alloc_id();
+        let first_item_id = self.xref.len();
+        let item_ids: Vec<usize> = outlines.iter().map(|_| self.alloc_id()).collect();
+        let mut root = format!("<< /Type /Outlines /Count {}", item_ids.len());
+        if let (Some(first), Some(last)) = (item_ids.first(), item_ids.last()) {
+            let _ = write!(root, " /First {first} 0 R /Last {last} 0 R");
+        }
+        root.push_str(" >>");
+        self.object(outlines_id, root);
+        for (i, (item_title, page_id)) in outlines.iter().enumerate() {
+            let item_id = first_item_id + i;
+            let mut item = format!(
+                "<< /Title {} /Parent {outlines_id} 0 R /Dest [{page_id} 0 R /XYZ null null null]",
+                text_string(item_title)
+            );
+            if i > 0 {
+                let _ = write!(item, " /Prev {} 0 R", item_id - 1);
+            }
+            if i + 1 < item_ids.len() {
+                let _ = write!(item, " /Next {} 0 R", item_id + 1);
+            }
+            item.push_str(" >>");
+            self.object(item_id, item);
+        }
+        let kids: Vec<String> = self
+            .front_page_ids
+            .iter()
+            .chain(&self.page_ids)
+            .map(|page_id| format!("{page_id} 0 R"))
+            .collect();
+        self.object(
+            PAGES_ID,
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                kids.join(" "),
+                kids.len()
+            ),
+        );
+        self.object(
+            CATALOG_ID,
+            format!(
+                "<< /Type /Catalog /Pages {PAGES_ID} 0 R /Outlines {outlines_id} 0 R /PageMode /UseOutlines >>"
+            ),
+        );
+        let info_id = self.alloc_id();
+        self.object(
+            info_id,
+            format!("<< /Title {} /Producer (zhaogj) >>", text_string(title)),
+        );
+        let xref_offset = self.offset;
+        let mut xref = format!("xref\n0 {}\n0000000000 65535 f \n", self.xref.len());
+        for offset in &self.xref[1..] {
+            let _ = writeln!(xref, "{offset:010} 00000 n ");
+        }
+        let _ = write!(
+            xref,
+            "trailer\n<< /Size {} /Root {CATALOG_ID} 0 R /Info {info_id} 0 R >>\nstartxref\n{xref_offset}\n%%EOF\n",
+            self.xref.len()
+        );
+        self.write(xref.as_bytes());
+        self.buf
+    }
+
+    fn alloc_id(&mut self) -> usize {
+        self.xref.push(0);
+        self.xref.len() - 1
+    }
+
+    fn begin_object(&mut self, id: usize) {
+        self.xref[id] = self.offset;
+        self.write(format!("{id} 0 obj\n").as_bytes());
+    }
+
+    fn object(&mut self, id: usize, body: String) {
+        self.begin_object(id);
+        self.write(body.as_bytes());
+        self.write(b"\nendobj\n");
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+        self.offset += bytes.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn toc_entry(i: usize) -> TocEntry {
+        TocEntry {
+            date: "2026-10-18".to_string(),
+            title: format!("第{i}篇 {}", "很长的标题".repeat(i % 20)),
+            page_no: i * 3 + 1,
+        }
+    }
+
+    /// 按交叉引用表检查每个对象的偏移
+    fn check_xref(pdf: &[u8]) {
+        let text = String::from_utf8_lossy(pdf);
+        assert!(text.starts_with("%PDF-1.4\n"));
+        assert!(text.ends_with("%%EOF\n"));
+        let startxref: usize = text
+            .rsplit("startxref\n")
+            .next()
+            .and_then(|v| v.lines().next())
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(pdf[startxref..].starts_with(b"xref\n"));
+        let mut lines = std::str::from_utf8(&pdf[startxref..])
+            .unwrap()
+            .lines()
+            .skip(1);
+        let size: usize = lines
+            .next()
+            .unwrap()
+            .strip_prefix("0 ")
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(lines.next(), Some("0000000000 65535 f "));
+        for id in 1..size {
+            let line = lines.next().unwrap();
+            assert_eq!(line.len(), 19, "{line}");
+            assert!(line.ends_with(" 00000 n "), "{line}");
+            let offset: usize = line[..10].parse().unwrap();
+            assert!(
+                pdf[offset..].starts_with(format!("{id} 0 obj\n").as_bytes()),
+                "object {id} at {offset}"
+            );
+        }
+        assert_eq!(lines.next(), Some("trailer"));
+        assert!(
+            lines
+                .next()
+                .unwrap()
+                .starts_with(&format!("<< /Size {size} /Root {CATALOG_ID} 0 R"))
+        );
+    }
+
+    #[test]
+    fn pdf_xref_offsets() {
+        let tags = vec!["标签".to_string()];
+        let content = "# 小标题\n\n正文段落。\n\n- 列表\n\n```\ncode\n```\n\n---\n\n".repeat(40);
+        let article = BookArticle {
+            date: "2026-10-18",
+            title: "文章标题 Title",
+            tags: &tags,
+            content: &content,
+        };
+        let mut pdf_writer = PdfWriter::new();
+        let mut pdf = Vec::new();
+        let mut page_no = 2;
+        for _ in 0..3 {
+            for (i, page) in layout_article(&article).iter().enumerate() {
+                let page_id = pdf_writer.add_page(page, page_no);
+                if i == 0 {
+                    pdf_writer.add_outline("书签 (1)", page_id);
+                }
+                page_no += 1;
+            }
+            // 模拟边写边发送
+            pdf.extend(pdf_writer.take());
+        }
+        pdf_writer.add_front_page(&layout_toc(&[toc_entry(1)])[0], 1);
+        pdf.extend(pdf_writer.finish("书名"));
+        check_xref(&pdf);
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.contains(&format!("/Count {}", page_no - 1)));
+        assert!(text.contains("/Type /Outlines /Count 3"));
+    }
+
+    #[test]
+    fn pdf_xref_offsets_empty() {
+        let mut pdf_writer = PdfWriter::new();
+        pdf_writer.add_front_page(&layout_toc(&[])[0], 1);
+        check_xref(&pdf_writer.finish(""));
+    }
+
+    #[test]
+    fn wrap_lines() {
+        // 11号字, 全角字宽11
+        assert_eq!(wrap("", 11.0, 100.0), [""]);
+        assert_eq!(
+            wrap("一二三四五六七八九十", 11.0, 44.0),
+            ["一二三四", "五六七八", "九十"]
+        );
+        // 英文单词不断开, 行首的空格去掉
+        assert_eq!(
+            wrap("hello world foo", 10.0, 40.0),
+            ["hello", "world", "foo"]
+        );
+        assert_eq!(wrap("ab cd", 10.0, 25.0), ["ab cd"]);
+        assert_eq!(wrap("ab cd", 10.0, 20.0), ["ab", "cd"]);
+        // 放不下的长单词从中间断开
+        assert_eq!(wrap("abcdefghijkl", 10.0, 30.0), ["abcdef", "ghijkl"]);
+        // 中英混排
+        assert_eq!(wrap("中文abc def", 10.0, 40.0), ["中文abc", "def"]);
+        for line in wrap(&"混合 mixed 文字 text ".repeat(30), 11.0, 120.0) {
+            assert!(text_width(&line, 11.0) <= 120.0, "{line}");
+        }
+    }
+
+    #[test]
+    fn truncate_text() {
+        assert_eq!(truncate("short", 10.0, 100.0), "short");
+        assert_eq!(truncate("一二三四", 10.0, 40.0), "一二三四");
+        assert_eq!(truncate("一二三四五", 10.0, 40.0), "一二三…");
+        let truncated = truncate(&"title ".repeat(50), 11.0, 200.0);
+        assert!(truncated.ends_with('…'));
+        assert!(text_width(&truncated, 11.0) <= 200.0);
+    }
+
+    #[test]
+    fn toc_page_count_matches_layout() {
+        for count in (0..80).chain([150, 151, 152, 500, 1000]) {
+            let entries: Vec<TocEntry> = (1..=count).map(toc_entry).collect();
+            assert_eq!(
+                toc_page_count(count),
+                layout_toc(&entries).len(),
+                "{count} entries"
+            );
+        }
+        assert_eq!(toc_page_count(0), 1);
+    }
+
+    #[test]
+    fn utf16_hex_text() {
+        assert_eq!(utf16_hex("A中\n"), "00414E2D");
+        assert_eq!(utf16_hex("😀"), "003F");
+        assert_eq!(text_string("A😀"), "<FEFF0041D83DDE00>");
+    }
+}
@@ -0,0 +1,594 @@
+use std::collections::HashSet;
+
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{delete, get},
+};
+use chrono::NaiveDate;
+use entity::{tbl_article, tbl_article_answer, tbl_reflection_prompt, tbl_reflection_template};
+use sea_orm::{
+    ActiveValue::Set, ColumnTrait, ConnectionTrait, DbErr, EntityTrait, Order, PaginatorTrait,
+    QueryFilter, QueryOrder, TransactionTrait, sea_query::Expr,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use validator::Validate;
+
+use crate::{
+    AppState,
+    article::ARTICLE_DATE,
+    auth::{self, ADMIN, READER, RequireRole},
+};
+
+pub fn routers(state: AppState) -> Router {
+    Router::new()
+        .route(
+            "/reflection_templates",
+            get(query_templates).post(create_template),
+        )
+        .route("/reflection_templates/{id}", delete(delete_template))
+        .route("/reflection_prompts/{id}/answers", get(prompt_answers))
+        .route("/articles/{id}/answers", get(article_answers))
+        .with_state(state)
+}
+
+/// 文章中对某个问题的回答
+#[derive(Deserialize, Debug, Clone)]
+pub struct AnswerInputDto {
+    pub prompt_id: i32,
+    pub answer: String,
+}
+
+/// 模板的问题列表, 按position排序, 模板不存在时返回None
+pub async fn template_prompts<C: ConnectionTrait>(
+    db: &C,
+    template_id: i32,
+) -> Result<
+    Option<(
+        tbl_reflection_template::Model,
+        Vec<tbl_reflection_prompt::Model>,
+    )>,
+    DbErr,
+> {
+    let Some(template) = tbl_reflection_template::Entity::find_by_id(template_id)
+        .one(db)
+        .await?
+    else {
+        return Ok(None);
+    };
+    let prompts = tbl_reflection_prompt::Entity::find()
+        .filter(tbl_reflection_prompt::Column::TemplateId.eq(template_id))
+        .order_by_asc(tbl_reflection_prompt::Column::Position)
+        .all(db)
+        .await?;
+    Ok(Some((template, prompts)))
+}
+
+/// 回答的问题必须属于该模板且不能重复
+pub fn validate_answers(
+    prompts: &[tbl_reflection_prompt::Model],
+    answers: &[AnswerInputDto],
+) -> Result<(), String> {
+    let mut prompt_ids = HashSet::new();
+    for answer in answers {
+        if !prompts.iter().any(|prompt| prompt.id == answer.prompt_id) {
+            return Err(format!("prompt {} not in template", answer.prompt_id));
+        }
+        if !prompt_ids.insert(answer.prompt_id) {
+            return Err(format!("prompt {} answered twice", answer.prompt_id));
+        }
+    }
+    Ok(())
+}
+
+/// 按模板问题顺序生成文章正文, 使搜索和列表预览对模板文章同样有效
+pub fn render_content(
+    prompts: &[tbl_reflection_prompt::Model],
+    answers: &[AnswerInputDto],
+) -> String {
+    let mut content = String::new();
+    for prompt in prompts {
+        let answer = answers
+            .iter()
+            .find(|answer| answer.prompt_id == prompt.id)
+            .map(|answer| answer.answer.as_str())
+            .unwrap_or_default();
+        content.push_str(&format!("## {}\n\n{}\n\n", prompt.content, answer));
+    }
+    content.trim_end().to_string()
+}
+
+/// 模板文章的正文由回答生成, 额外填写的正文附在最后
+pub fn compose_content(answers_content: &str, extra: &str) -> String {
+    if extra.is_empty() {
+        answers_content.to_string()
+    } else if answers_content.is_empty() {
+        extra.to_string()
+    } else {
+        format!("{answers_content}\n\n{extra}")
+    }
+}
+
+/// 取出模板文章正文中回答之后额外填写的部分, 正文与回答对不上时整篇视为额外正文, 避免丢失内容
+pub fn extra_content<'a>(content: &'a str, answers_content: &str) -> &'a str {
+    match content.strip_prefix(answers_content) {
+        Some(rest) => rest.strip_prefix("\n\n").unwrap_or(rest),
+        None => content,
+    }
+}
+
+#[derive(Debug)]
+pub enum TemplateContentErr {
+    TemplateNotFound,
+    InvalidAnswers(String),
+    Db(DbErr),
+}
+
+impl From<DbErr> for TemplateContentErr {
+    fn from(e: DbErr) -> Self {
+        Self::Db(e)
+    }
+}
+
+/// 重新生成模板文章的正文, answers为空时沿用已保存的回答, extra为空时沿用原有的额外正文
+pub async fn template_content<C: ConnectionTrait>(
+    db: &C,
+    tbl_article: &tbl_article::Model,
+    template_id: i32,
+    answers: Option<&[AnswerInputDto]>,
+    extra: Option<&str>,
+) -> Result<String, TemplateContentErr> {
+    let Some((_, prompts)) = template_prompts(db, template_id).await? else {
+        return Err(TemplateContentErr::TemplateNotFound);
+    };
+    let saved_answers: Vec<AnswerInputDto> = tbl_article_answer::Entity::find()
+        .filter(tbl_article_answer::Column::ArticleId.eq(tbl_article.id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|v| AnswerInputDto {
+            prompt_id: v.prompt_id,
+            answer: v.answer,
+        })
+        .collect();
+    let extra = extra.unwrap_or_else(|| {
+        extra_content(
+            &tbl_article.content,
+            &render_content(&prompts, &saved_answers),
+        )
+    });
+    let answers = match answers {
+        Some(answers) => {
+            validate_answers(&prompts, answers).map_err(TemplateContentErr::InvalidAnswers)?;
+            answers
+        }
+        None => &saved_answers,
+    };
+    Ok(compose_content(&render_content(&prompts, answers), extra))
+}
+
+/// 用answers替换文章的全部回答
+pub async fn replace_answers<C: ConnectionTrait>(
+    db: &C,
+    article_id: i32,
+    answers: &[AnswerInputDto],
+) -> Result<(), DbErr> {
+    tbl_article_answer::Entity::delete_many()
+        .filter(tbl_article_answer::Column::ArticleId.eq(article_id))
+        .exec(db)
+        .await?;
+    if answers.is_empty() {
+        return Ok(());
+    }
+    let tbl_article_answer_ams = answers
+        .iter()
+        .map(|answer| tbl_article_answer::ActiveModel {
+            article_id: Set(article_id),
+            prompt_id: Set(answer.prompt_id),
+            answer: Set(answer.answer.clone()),
+            ..Default::default()
+        });
+    tbl_article_answer::Entity::insert_many(tbl_article_answer_ams)
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+#[derive(Serialize, Debug)]
+struct PromptOutputDto {
+    id: i32,
+    position: i32,
+    content: String,
+}
+#[derive(Serialize, Debug)]
+struct TemplateOutputDto {
+    id: i32,
+    name: String,
+    prompts: Vec<PromptOutputDto>,
+    created_at: i64,
+}
+async fn query_templates(
+    State(app_state): State<AppState>,
+    _: RequireRole<READER>,
+) -> impl IntoResponse {
+    let templates = match tbl_reflection_template::Entity::find()
+        .order_by_asc(tbl_reflection_template::Column::Id)
+        .find_with_related(tbl_reflection_prompt::Entity)
+        .all(&app_state.db_conn)
+        .await
+    {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("tbl_reflection_template find err: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_reflection_template find err")],
+                Json(json!({})),
+            );
+        }
+    };
+    let template_output_dtos: Vec<TemplateOutputDto> = templates
+        .into_iter()
+        .map(|(template, mut prompts)| {
+            prompts.sort_by_key(|prompt| prompt.position);
+            TemplateOutputDto {
+                id: template.id,
+                name: template.name,
+                prompts: prompts
+                    .into_iter()
+                    .map(|prompt| PromptOutputDto {
+                        id: prompt.id,
+                        position: prompt.position,
+                        content: prompt.content,
+                    })
+                    .collect(),
+                created_at: template.created_at.and_utc().timestamp_millis(),
+            }
+        })
+        .collect();
+    (
+        StatusCode::OK,
+        [("code", "200"), ("msg", "ok")],
+        Json(json!(template_output_dtos)),
+    )
+}
+
+#[derive(Deserialize, Debug, Validate)]
+struct CreateTemplateInputDto {
+    #[validate(length(min = 1, max = 64))]
+    name: String,
+    #[validate(length(min = 1, max = 20))]
+    prompts: Vec<String>,
+}
+async fn create_template(
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<ADMIN>,
+    Json(create_template_input_dto): Json<CreateTemplateInputDto>,
+) -> impl IntoResponse {
+    if let Err(e) = create_template_input_dto.validate() {
+        log::warn!("create template input invalid: {}", e);
+        return (
+            StatusCode::BAD_REQUEST,
+            [("code", "400"), ("msg", "invalid input")],
+            Json(json!({})),
+        );
+    }
+    if create_template_input_dto
+        .prompts
+        .iter()
+        .any(|prompt| prompt.trim().is_empty())
+    {
+        log::warn!("create template input has empty prompt");
+        return (
+            StatusCode::BAD_REQUEST,
+            [("code", "400"), ("msg", "empty prompt")],
+            Json(json!({})),
+        );
+    }
+    auth::insert_log(
+        &app_state,
+        format!(
+            "{} create reflection template by {:?}",
+            auth_user.username, create_template_input_dto
+        ),
+    )
+    .await;
+    let tbl_reflection_template_am = tbl_reflection_template::ActiveModel {
+        name: Set(create_template_input_dto.name),
+        ..Default::default()
+    };
+    let result = async {
+        let txn = app_state.db_conn.begin().await?;
+        let template_id = tbl_reflection_template::Entity::insert(tbl_reflection_template_am)
+            .exec(&txn)
+            .await?
+            .last_insert_id;
+        let tbl_reflection_prompt_ams = create_template_input_dto
+            .prompts
+            .into_iter()
+            .enumerate()
+            .map(|(position, content)| tbl_reflection_prompt::ActiveModel {
+                template_id: Set(template_id),
+                position: Set(position as i32),
+                content: Set(content),
+                ..Default::default()
+            });
+        tbl_reflection_prompt::Entity::insert_many(tbl_reflection_prompt_ams)
+            .exec(&txn)
+            .await?;
+        txn.commit().await?;
+        Ok::<_, DbErr>(template_id)
+    }
+    .await;
+    match result {
+        Ok(template_id) => (
+            StatusCode::OK,
+            [("code", "200"), ("msg", "ok")],
+            Json(json!({
+                "id": template_id
+            })),
+        ),
+        Err(e) => {
+            log::error!("create reflection template err: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "create template err")],
+                Json(json!({})),
+            )
+        }
+    }
+}
+
+async fn delete_template(
+    Path(id): Path<i32>,
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<ADMIN>,
+) -> impl IntoResponse {
+    // 已有文章使用的模板不能删除, 否则回答会失去对应的问题, 回收站中的文章也算在内
+    match tbl_article::Entity::find()
+        .filter(tbl_article::Column::TemplateId.eq(id))
+        .count(&app_state.db_conn)
+        .await
+    {
+        Ok(0) => {}
+        Ok(count) => {
+            log::warn!("reflection template {} used by {} articles", id, count);
+            return (
+                StatusCode::CONFLICT,
+                [("code", "409"), ("msg", "template in use")],
+                Json(json!({})),
+            );
+        }
+        Err(e) => {
+            log::error!("tbl_article count err: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_article count err")],
+                Json(json!({})),
+            );
+        }
+    }
+    match tbl_reflection_template::Entity::delete_by_id(id)
+        .exec(&app_state.db_conn)
+        .await
+    {
+        Ok(delete_result) if delete_result.rows_affected == 1 => {
+            auth::insert_log(
+                &app_state,
+                format!("{} delete reflection template {}", auth_user.username, id),
+            )
+            .await;
+            (
+                StatusCode::OK,
+                [("code", "200"), ("msg", "ok")],
+                Json(json!({})),
+            )
+        }
+        Ok(_) => (
+            StatusCode::NOT_FOUND,
+            [("code", "404"), ("msg", "not found")],
+            Json(json!({})),
+        ),
+        Err(e) => {
+            log::error!("delete reflection template {id} err: {e}");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "delete db err")],
+                Json(json!({})),
+            )
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct PromptAnswersInputDto {
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+}
+#[derive(Serialize, Debug)]
+struct PromptAnswerOutputDto {
+    article_id: i32,
+    date: String,
+    answer: String,
+}
+async fn prompt_answers(
+    Path(id): Path<i32>,
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<READER>,
+    Query(prompt_answers_input_dto): Query<PromptAnswersInputDto>,
+) -> impl IntoResponse {
+    let prompt = match tbl_reflection_prompt::Entity::find_by_id(id)
+        .one(&app_state.db_conn)
+        .await
+    {
+        Ok(Some(v)) => v,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                [("code", "404"), ("msg", "not found")],
+                Json(json!({})),
+            );
+        }
+        Err(e) => {
+            log::error!("tbl_reflection_prompt find err: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_reflection_prompt find err")],
+                Json(json!({})),
+            );
+        }
+    };
+    let mut select = tbl_article_answer::Entity::find()
+        .find_also_related(tbl_article::Entity)
+        .filter(tbl_article_answer::Column::PromptId.eq(id))
+        .filter(tbl_article::Column::UserId.eq(auth_user.id))
+        .filter(tbl_article::Column::DeletedAt.is_null());
+    if let Some(from) = prompt_answers_input_dto.from {
+        select = select.filter(Expr::cust_with_values(
+            format!("{ARTICLE_DATE} >= ?"),
+            [from.to_string()],
+        ));
+    }
+    if let Some(to) = prompt_answers_input_dto.to {
+        select = select.filter(Expr::cust_with_values(
+            format!("{ARTICLE_DATE} <= ?"),
+            [to.to_string()],
+        ));
+    }
+    let rows = match select
+        .order_by(Expr::cust(ARTICLE_DATE), Order::Asc)
+        .all(&app_state.db_conn)
+        .await
+    {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("tbl_article_answer find err: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_article_answer find err")],
+                Json(json!({})),
+            );
+        }
+    };
+    let answers: Vec<PromptAnswerOutputDto> = rows
+        .into_iter()
+        .filter_map(|(answer, article)| {
+            let article = article?;
+            let date = article
+                .entry_date
+                .unwrap_or_else(|| article.created_at.date());
+            Some(PromptAnswerOutputDto {
+                article_id: article.id,
+                date: date.to_string(),
+                answer: answer.answer,
+            })
+        })
+        .collect();
+    (
+        StatusCode::OK,
+        [("code", "200"), ("msg", "ok")],
+        Json(json!({
+            "prompt": {
+                "id": prompt.id,
+                "template_id": prompt.template_id,
+                "content": prompt.content
+            },
+            "answers": answers
+        })),
+    )
+}
+
+#[derive(Serialize, Debug)]
+struct ArticleAnswerOutputDto {
+    prompt_id: i32,
+    prompt: String,
+    answer: Option<String>,
+}
+async fn article_answers(
+    Path(id): Path<i32>,
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<READER>,
+) -> impl IntoResponse {
+    let tbl_article = match tbl_article::Entity::find_by_id(id)
+        .filter(tbl_article::Column::UserId.eq(auth_user.id))
+        .filter(tbl_article::Column::DeletedAt.is_null())
+        .one(&app_state.db_conn)
+        .await
+    {
+        Ok(Some(v)) => v,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                [("code", "404"), ("msg", "not found")],
+                Json(json!({})),
+            );
+        }
+        Err(e) => {
+            log::error!("tbl_article find err: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_article find err")],
+                Json(json!({})),
+            );
+        }
+    };
+    let Some(template_id) = tbl_article.template_id else {
+        return (
+            StatusCode::OK,
+            [("code", "200"), ("msg", "ok")],
+            Json(json!({
+                "template_id": null,
+                "answers": []
+            })),
+        );
+    };
+    let prompts = match template_prompts(&app_state.db_conn, template_id).await {
+        Ok(Some((_, prompts))) => prompts,
+        Ok(None) => Vec::new(),
+        Err(e) => {
+            log::error!("tbl_reflection_prompt find err: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_reflection_prompt find err")],
+                Json(json!({})),
+            );
+        }
+    };
+    let answers = match tbl_article_answer::Entity::find()
+        .filter(tbl_article_answer::Column::ArticleId.eq(id))
+        .all(&app_state.db_conn)
+        .await
+    {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("tbl_article_answer find err: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_article_answer find err")],
+                Json(json!({})),
+            );
+        }
+    };
+    let article_answer_output_dtos: Vec<ArticleAnswerOutputDto> = prompts
+        .into_iter()
+        .map(|prompt| ArticleAnswerOutputDto {
+            prompt_id: prompt.id,
+            answer: answers
+                .iter()
+                .find(|answer| answer.prompt_id == prompt.id)
+                .map(|answer| answer.answer.clone()),
+            prompt: prompt.content,
+        })
+        .collect();
+    (
+        StatusCode::OK,
+        [("code", "200"), ("msg", "ok")],
+        Json(json!({
+            "template_id": template_id,
+            "answers": article_answer_output_dtos
+        })),
+    )
+}
@@ -0,0 +1,802 @@
+use std::{net::SocketAddr, str::FromStr};
+
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode, header},
+    response::IntoResponse,
+    routing::{delete, get},
+};
+use entity::tbl_auth_user;
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+use serde_json::json;
+
+use crate::{
+    AppState,
+    auth::{self, ADMIN, READER, RequireRole, RequireSession, Role},
+    config::SERVER_TOML,
+};
+
+// sled中的tree划分
+const META_TREE: &str = "meta";
+const SESSION_TREE: &str = "session";
+const DENYLIST_TREE: &str = "denylist";
+const TOKEN_SECRET_KEY: &str = "token_secret";
+
+// access token有效期15分钟, 过期后使用refresh token换取
+pub const ACCESS_TOKEN_TTL_SECS: i64 = 15 * 60;
+// 轮换后旧refresh token的宽限期, 期间重放只拒绝不吊销, 兼容多个标签页同时刷新
+const REFRESH_GRACE_SECS: i64 = 30;
+// 每个请求都会更新last_seen, 间隔内不重复写sled
+const LAST_SEEN_INTERVAL_SECS: i64 = 60;
+
+pub fn routers(state: AppState) -> Router {
+    Router::new()
+        .route("/sessions", get(my_sessions).delete(revoke_my_sessions))
+        .route("/sessions/{sid}", delete(revoke_my_session))
+        .route(
+            "/users/{id}/sessions",
+            get(sessions_of_user).delete(revoke_sessions_of_user),
+        )
+        .route("/users/{id}/sessions/{sid}", delete(revoke_session_of_user))
+        .with_state(state)
+}
+
+static TOKEN_KEYS: OnceCell<(EncodingKey, DecodingKey)> = OnceCell::new();
+
+/// access token的载荷, RequireAuth只校验签名和denylist, 不读写数据库
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Claims {
+    pub sub: i32,
+    pub username: String,
+    pub role: Role,
+    pub sid: String,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+/// 服务端保存的会话, key为sid, 只保存refresh token的hash
+/// 会话的修改都通过compare_and_swap完成, 并发请求不会互相覆盖
+#[derive(Serialize, Deserialize, Debug)]
+struct Session {
+    user_id: i32,
+    refresh_hash: String,
+    // 上一个refresh token的hash和轮换时间
+    #[serde(default)]
+    prev_refresh_hash: String,
+    #[serde(default)]
+    rotated_at: i64,
+    #[serde(default)]
+    ip: String,
+    #[serde(default)]
+    user_agent: String,
+    #[serde(default)]
+    remember_me: bool,
+    created_at: i64,
+    last_seen: i64,
+}
+
+impl Session {
+    /// 闲置超时和最长有效期取自配置, 修改配置后对已有会话同样生效
+    fn idle_timeout(&self) -> i64 {
+        if self.remember_me {
+            SERVER_TOML.auth.remember_me_timeout
+        } else {
+            SERVER_TOML.auth.idle_timeout
+        }
+    }
+
+    fn expires_at(&self) -> i64 {
+        if self.remember_me {
+            self.created_at + SERVER_TOML.auth.remember_me_timeout
+        } else {
+            self.created_at + SERVER_TOML.auth.absolute_timeout
+        }
+    }
+
+    fn is_expired(&self, ts_now: i64) -> bool {
+        ts_now - self.last_seen >= self.idle_timeout() || ts_now >= self.expires_at()
+    }
+}
+
+/// 登录请求的来源信息, 记录在会话中
+pub struct ClientInfo {
+    pub ip: String,
+    pub user_agent: String,
+}
+
+impl ClientInfo {
+    pub fn new(socket_addr: &SocketAddr, headers: &HeaderMap) -> Self {
+        Self {
+            ip: socket_addr.ip().to_string(),
+            user_agent: headers
+                .get(header::USER_AGENT)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default()
+                .chars()
+                .take(256)
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct TokenPair {
+    pub token: String,
+    pub refresh_token: String,
+    pub expires_in: i64,
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+pub fn sha256_hex(s: &str) -> String {
+    format!("{:x}", Sha256::digest(s.as_bytes()))
+}
+
+/// 加载签名密钥, 首次启动时随机生成并保存到sled
+pub fn init(sled_db: &sled::Db) -> anyhow::Result<()> {
+    let meta_tree = sled_db.open_tree(META_TREE)?;
+    let secret = match meta_tree.get(TOKEN_SECRET_KEY)? {
+        Some(v) => v.to_vec(),
+        None => {
+            let secret = rand::random::<[u8; 32]>().to_vec();
+            meta_tree.insert(TOKEN_SECRET_KEY, secret.as_slice())?;
+            log::info!("generate token secret");
+            secret
+        }
+    };
+    let _ = TOKEN_KEYS.set((
+        EncodingKey::from_secret(&secret),
+        DecodingKey::from_secret(&secret),
+    ));
+    // 旧版本的uuid token保存在默认tree中, 已不再使用
+    if !sled_db.is_empty() {
+        log::info!("remove {} legacy tokens", sled_db.len());
+        sled_db.clear()?;
+    }
+    Ok(())
+}
+
+/// 签发access token, 有效期不超过会话的最长有效期
+fn encode_access_token(
+    tbl_auth_user: &tbl_auth_user::Model,
+    sid: &str,
+    session: &Session,
+    ts_now: i64,
+) -> Option<(String, i64)> {
+    let Some((encoding_key, _)) = TOKEN_KEYS.get() else {
+        log::error!("token keys not init");
+        return None;
+    };
+    let role = match Role::from_str(&tbl_auth_user.role) {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("user {} role err: {}", tbl_auth_user.username, e);
+            return None;
+        }
+    };
+    let exp = (ts_now + ACCESS_TOKEN_TTL_SECS).min(session.expires_at());
+    let claims = Claims {
+        sub: tbl_auth_user.id,
+        username: tbl_auth_user.username.clone(),
+        role,
+        sid: sid.to_string(),
+        iat: ts_now,
+        exp,
+    };
+    match jsonwebtoken::encode(&Header::new(Algorithm::HS256), &claims, encoding_key) {
+        Ok(token) => Some((token, exp)),
+        Err(e) => {
+            log::error!("encode access token err: {}", e);
+            None
+        }
+    }
+}
+
+fn save_session(tree: &sled::Tree, sid: &str, session: &Session) -> bool {
+    match serde_json::to_vec(session) {
+        Ok(v) => match tree.insert(sid, v) {
+            Ok(_) => true,
+            Err(e) => {
+                log::error!("sled insert session err: {}", e);
+                false
+            }
+        },
+        Err(e) => {
+            log::error!("session to_vec err: {}", e);
+            false
+        }
+    }
+}
+
+/// 登录成功后创建会话, 返回access token和refresh token
+pub fn issue(
+    sled_db: &sled::Db,
+    tbl_auth_user: &tbl_auth_user::Model,
+    client_info: &ClientInfo,
+    remember_me: bool,
+) -> Option<TokenPair> {
+    let tree = match sled_db.open_tree(SESSION_TREE) {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("sled open_tree err: {}", e);
+            return None;
+        }
+    };
+    let ts_now = chrono::Utc::now().timestamp();
+    let sid = uuid::Uuid::new_v4().simple().to_string();
+    let refresh_secret = to_hex(&rand::random::<[u8; 32]>());
+    let session = Session {
+        user_id: tbl_auth_user.id,
+        refresh_hash: sha256_hex(&refresh_secret),
+        prev_refresh_hash: String::new(),
+        rotated_at: 0,
+        ip: client_info.ip.clone(),
+        user_agent: client_info.user_agent.clone(),
+        remember_me,
+        created_at: ts_now,
+        last_seen: ts_now,
+    };
+    let (token, exp) = encode_access_token(tbl_auth_user, &sid, &session, ts_now)?;
+    if !save_session(&tree, &sid, &session) {
+        return None;
+    }
+    Some(TokenPair {
+        token,
+        refresh_token: format!("{sid}.{refresh_secret}"),
+        expires_in: exp - ts_now,
+    })
+}
+
+pub fn hash_eq(a: &str, b: &str) -> bool {
+    a.as_bytes().ct_eq(b.as_bytes()).into()
+}
+
+/// 校验refresh token是否为会话当前的token, 旧token被重放时吊销整个会话
+/// 刚轮换过的旧token只拒绝, 可能是同一客户端的并发刷新
+fn check_refresh_secret(
+    sled_db: &sled::Db,
+    sid: &str,
+    session: &Session,
+    refresh_secret: &str,
+) -> bool {
+    let refresh_hash = sha256_hex(refresh_secret);
+    if hash_eq(&refresh_hash, &session.refresh_hash) {
+        if session.is_expired(chrono::Utc::now().timestamp()) {
+            log::info!("session {} expired", sid);
+            revoke_session(sled_db, sid);
+            return false;
+        }
+        return true;
+    }
+    if hash_eq(&refresh_hash, &session.prev_refresh_hash)
+        && chrono::Utc::now().timestamp() - session.rotated_at < REFRESH_GRACE_SECS
+    {
+        log::warn!("session {} refresh token just rotated, reject", sid);
+        return false;
+    }
+    log::warn!("session {} refresh token reused, revoke", sid);
+    revoke_session(sled_db, sid);
+    false
+}
+
+fn get_session(tree: &sled::Tree, sid: &str) -> Option<(sled::IVec, Session)> {
+    match tree.get(sid) {
+        Ok(Some(v)) => {
+            let session = serde_json::from_slice::<Session>(&v).ok()?;
+            Some((v, session))
+        }
+        Ok(None) => {
+            log::warn!("session {} not exists", sid);
+            None
+        }
+        Err(e) => {
+            log::error!("sled get session err: {}", e);
+            None
+        }
+    }
+}
+
+/// 校验refresh token, 返回会话所属的user_id
+pub fn refresh_token_user(sled_db: &sled::Db, refresh_token: &str) -> Option<i32> {
+    let (sid, refresh_secret) = refresh_token.split_once('.')?;
+    let tree = match sled_db.open_tree(SESSION_TREE) {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("sled open_tree err: {}", e);
+            return None;
+        }
+    };
+    let (_, session) = get_session(&tree, sid)?;
+    check_refresh_secret(sled_db, sid, &session, refresh_secret).then_some(session.user_id)
+}
+
+/// 使用refresh token换取新的access token, refresh token同时轮换
+/// 读取、校验和写回通过compare_and_swap保证原子, 失败时重新读取后再校验
+pub fn refresh(
+    sled_db: &sled::Db,
+    refresh_token: &str,
+    tbl_auth_user: &tbl_auth_user::Model,
+    client_info: &ClientInfo,
+) -> Option<TokenPair> {
+    let (sid, refresh_secret) = refresh_token.split_once('.')?;
+    let tree = match sled_db.open_tree(SESSION_TREE) {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("sled open_tree err: {}", e);
+            return None;
+        }
+    };
+    loop {
+        let (old, mut session) = get_session(&tree, sid)?;
+        if session.user_id != tbl_auth_user.id {
+            log::warn!("session {} user mismatch", sid);
+            return None;
+        }
+        if !check_refresh_secret(sled_db, sid, &session, refresh_secret) {
+            return None;
+        }
+        let ts_now = chrono::Utc::now().timestamp();
+        let new_refresh_secret = to_hex(&rand::random::<[u8; 32]>());
+        session.prev_refresh_hash =
+            std::mem::replace(&mut session.refresh_hash, sha256_hex(&new_refresh_secret));
+        session.rotated_at = ts_now;
+        session.ip = client_info.ip.clone();
+        session.user_agent = client_info.user_agent.clone();
+        session.last_seen = ts_now;
+        let (token, exp) = encode_access_token(tbl_auth_user, sid, &session, ts_now)?;
+        let new = match serde_json::to_vec(&session) {
+            Ok(v) => v,
+            Err(e) => {
+                log::error!("session to_vec err: {}", e);
+                return None;
+            }
+        };
+        match tree.compare_and_swap(sid, Some(old), Some(new)) {
+            Ok(Ok(())) => {
+                return Some(TokenPair {
+                    token,
+                    refresh_token: format!("{sid}.{new_refresh_secret}"),
+                    expires_in: exp - ts_now,
+                });
+            }
+            // 会话在读取后被其他请求修改
+            Ok(Err(_)) => continue,
+            Err(e) => {
+                log::error!("sled compare_and_swap session err: {}", e);
+                return None;
+            }
+        }
+    }
+}
+
+/// 更新会话的最近活跃时间, 距上次更新不足LAST_SEEN_INTERVAL_SECS时跳过
+fn touch_session(sled_db: &sled::Db, sid: &str) {
+    let tree = match sled_db.open_tree(SESSION_TREE) {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("sled open_tree err: {}", e);
+            return;
+        }
+    };
+    let ts_now = chrono::Utc::now().timestamp();
+    let Some((old, mut session)) = get_session(&tree, sid) else {
+        return;
+    };
+    if ts_now - session.last_seen < LAST_SEEN_INTERVAL_SECS {
+        return;
+    }
+    session.last_seen = ts_now;
+    let Ok(new) = serde_json::to_vec(&session) else {
+        return;
+    };
+    // 与refresh并发时放弃本次更新, refresh同样会更新last_seen
+    if let Err(e) = tree.compare_and_swap(sid, Some(old), Some(new)) {
+        log::error!("sled compare_and_swap session err: {}", e);
+    }
+}
+
+/// 校验access token的签名和有效期, 并检查会话是否已被吊销
+pub fn verify_access_token(sled_db: &sled::Db, token: &str) -> Option<Claims> {
+    let Some((_, decoding_key)) = TOKEN_KEYS.get() else {
+        log::error!("token keys not init");
+        return None;
+    };
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.leeway = 0;
+    let claims = match jsonwebtoken::decode::<Claims>(token, decoding_key, &validation) {
+        Ok(v) => v.claims,
+        Err(e) => {
+            log::warn!("decode access token err: {}", e);
+            return None;
+        }
+    };
+    match sled_db.open_tree(DENYLIST_TREE) {
+        Ok(tree) => match tree.contains_key(&claims.sid) {
+            Ok(false) => {
+                touch_session(sled_db, &claims.sid);
+                Some(claims)
+            }
+            Ok(true) => {
+                log::warn!("session {} revoked", claims.sid);
+                None
+            }
+            Err(e) => {
+                log::error!("sled contains_key denylist err: {}", e);
+                None
+            }
+        },
+        Err(e) => {
+            log::error!("sled open_tree err: {}", e);
+            None
+        }
+    }
+}
+
+// 文章中嵌入文件的签名有效期, <img>无法携带Authorization头, 只能通过地址中的签名授权
+pub const FILE_TOKEN_TTL_SECS: i64 = 10 * 60;
+const FILE_TOKEN_AUDIENCE: &str = "file";
+
+/// 文件签名的载荷, 绑定用户、会话和文件, 会话吊销后签名随之失效
+#[derive(Serialize, Deserialize, Debug)]
+struct FileClaims {
+    sub: i32,
+    sid: String,
+    file_id: i32,
+    aud: String,
+    exp: i64,
+}
+
+/// 为会话中的用户签发读取某个文件的短期签名
+pub fn sign_file_token(user_id: i32, sid: &str, file_id: i32) -> Option<String> {
+    let Some((encoding_key, _)) = TOKEN_KEYS.get() else {
+        log::error!("token keys not init");
+        return None;
+    };
+    let claims = FileClaims {
+        sub: user_id,
+        sid: sid.to_string(),
+        file_id,
+        aud: FILE_TOKEN_AUDIENCE.to_string(),
+        exp: chrono::Utc::now().timestamp() + FILE_TOKEN_TTL_SECS,
+    };
+    match jsonwebtoken::encode(&Header::new(Algorithm::HS256), &claims, encoding_key) {
+        Ok(token) => Some(token),
+        Err(e) => {
+            log::error!("encode file token err: {}", e);
+            None
+        }
+    }
+}
+
+/// 校验文件签名, 返回签发时的用户id; access token不能当作文件签名使用, 反之亦然
+pub fn verify_file_token(sled_db: &sled::Db, token: &str, file_id: i32) -> Option<i32> {
+    let Some((_, decoding_key)) = TOKEN_KEYS.get() else {
+        log::error!("token keys not init");
+        return None;
+    };
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.leeway = 0;
+    validation.set_audience(&[FILE_TOKEN_AUDIENCE]);
+    validation.set_required_spec_claims(&["exp", "aud"]);
+    let claims = match jsonwebtoken::decode::<FileClaims>(token, decoding_key, &validation) {
+        Ok(v) => v.claims,
+        Err(e) => {
+            log::warn!("decode file token err: {}", e);
+            return None;
+        }
+    };
+    if claims.file_id != file_id {
+        log::warn!("file token for {} used for {}", claims.file_id, file_id);
+        return None;
+    }
+    match sled_db.open_tree(DENYLIST_TREE) {
+        Ok(tree) => match tree.contains_key(&claims.sid) {
+            Ok(false) => Some(claims.sub),
+            Ok(true) => {
+                log::warn!("session {} revoked", claims.sid);
+                None
+            }
+            Err(e) => {
+                log::error!("sled contains_key denylist err: {}", e);
+                None
+            }
+        },
+        Err(e) => {
+            log::error!("sled open_tree err: {}", e);
+            None
+        }
+    }
+}
+
+/// 删除会话, 并将sid加入denylist使已签发的access token立即失效
+pub fn revoke_session(sled_db: &sled::Db, sid: &str) {
+    match sled_db.open_tree(SESSION_TREE) {
+        Ok(tree) => {
+            if let Err(e) = tree.remove(sid) {
+                log::error!("sled remove session err: {}", e);
+            }
+        }
+        Err(e) => {
+            log::error!("sled open_tree err: {}", e);
+        }
+    }
+    match sled_db.open_tree(DENYLIST_TREE) {
+        Ok(tree) => {
+            // 超过access token有效期后denylist记录即可清理
+            let expires_at = chrono::Utc::now().timestamp() + ACCESS_TOKEN_TTL_SECS;
+            if let Err(e) = tree.insert(sid, &expires_at.to_be_bytes()) {
+                log::error!("sled insert denylist err: {}", e);
+            }
+        }
+        Err(e) => {
+            log::error!("sled open_tree err: {}", e);
+        }
+    }
+    log::info!("revoke session {}", sid);
+}
+
+/// 查询用户的全部会话, 按最近活跃时间倒序
+fn user_sessions(sled_db: &sled::Db, user_id: i32) -> Vec<(String, Session)> {
+    let tree = match sled_db.open_tree(SESSION_TREE) {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("sled open_tree err: {}", e);
+            return Vec::new();
+        }
+    };
+    let mut sessions: Vec<(String, Session)> = tree
+        .iter()
+        .flatten()
+        .filter_map(|(k, v)| {
+            let session = serde_json::from_slice::<Session>(&v).ok()?;
+            (session.user_id == user_id).then(|| (String::from_utf8_lossy(&k).to_string(), session))
+        })
+        .collect();
+    sessions.sort_by_key(|(_, session)| std::cmp::Reverse(session.last_seen));
+    sessions
+}
+
+/// 吊销用户的全部会话, 用于禁用用户、重置密码、修改角色等场景
+pub fn revoke_user(sled_db: &sled::Db, user_id: i32) -> usize {
+    let sessions = user_sessions(sled_db, user_id);
+    for (sid, _) in &sessions {
+        revoke_session(sled_db, sid);
+    }
+    sessions.len()
+}
+
+/// 吊销用户除当前会话外的全部会话, 用于用户自己修改密码
+pub fn revoke_user_except(sled_db: &sled::Db, user_id: i32, current_sid: &str) -> usize {
+    let sessions: Vec<_> = user_sessions(sled_db, user_id)
+        .into_iter()
+        .filter(|(sid, _)| sid != current_sid)
+        .collect();
+    for (sid, _) in &sessions {
+        revoke_session(sled_db, sid);
+    }
+    sessions.len()
+}
+
+/// 清理闲置过期的会话和过期的denylist记录, 由token_expired_task周期调用
+pub fn purge_expired(sled_db: &sled::Db) {
+    let ts_now = chrono::Utc::now().timestamp();
+    match sled_db.open_tree(SESSION_TREE) {
+        Ok(tree) => {
+            for (k, v) in tree.iter().flatten() {
+                let expired = match serde_json::from_slice::<Session>(&v) {
+                    Ok(session) => session.is_expired(ts_now),
+                    Err(e) => {
+                        log::error!("session from_slice err: {}", e);
+                        true
+                    }
+                };
+                if expired {
+                    if let Err(e) = tree.remove(&k) {
+                        log::error!("sled remove session err: {}", e);
+                    }
+                    log::info!("session expired {}", String::from_utf8_lossy(&k));
+                }
+            }
+        }
+        Err(e) => {
+            log::error!("sled open_tree err: {}", e);
+        }
+    }
+    match sled_db.open_tree(DENYLIST_TREE) {
+        Ok(tree) => {
+            for (k, v) in tree.iter().flatten() {
+                let expires_at = v.as_ref().try_into().map(i64::from_be_bytes).unwrap_or(0);
+                if expires_at <= ts_now
+                    && let Err(e) = tree.remove(&k)
+                {
+                    log::error!("sled remove denylist err: {}", e);
+                }
+            }
+        }
+        Err(e) => {
+            log::error!("sled open_tree err: {}", e);
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct SessionOutputDto {
+    sid: String,
+    ip: String,
+    user_agent: String,
+    remember_me: bool,
+    created_at: i64,
+    last_seen: i64,
+    expires_at: i64,
+    current: bool,
+}
+
+fn session_output_dtos(
+    sled_db: &sled::Db,
+    user_id: i32,
+    current_sid: Option<&str>,
+) -> Vec<SessionOutputDto> {
+    user_sessions(sled_db, user_id)
+        .into_iter()
+        .map(|(sid, session)| SessionOutputDto {
+            current: current_sid == Some(sid.as_str()),
+            expires_at: session.expires_at() * 1000,
+            sid,
+            ip: session.ip,
+            user_agent: session.user_agent,
+            remember_me: session.remember_me,
+            created_at: session.created_at * 1000,
+            last_seen: session.last_seen * 1000,
+        })
+        .collect()
+}
+
+/// 撤销指定会话, 会话不属于该用户时返回404
+fn revoke_owned_session(
+    sled_db: &sled::Db,
+    user_id: i32,
+    sid: &str,
+) -> (
+    StatusCode,
+    [(&'static str, &'static str); 2],
+    Json<serde_json::Value>,
+) {
+    if !user_sessions(sled_db, user_id)
+        .iter()
+        .any(|(v, _)| v == sid)
+    {
+        log::warn!("session {} of user {} not exists", sid, user_id);
+        return (
+            StatusCode::NOT_FOUND,
+            [("code", "404"), ("msg", "session not exists")],
+            Json(json!({})),
+        );
+    }
+    revoke_session(sled_db, sid);
+    (
+        StatusCode::OK,
+        [("code", "200"), ("msg", "ok")],
+        Json(json!({})),
+    )
+}
+
+/// 查询当前用户的全部会话, current标记当前请求所属的会话
+async fn my_sessions(
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<READER>,
+    RequireSession(session_id): RequireSession,
+) -> impl IntoResponse {
+    let sessions = session_output_dtos(&app_state.sled_db, auth_user.id, Some(&session_id.0));
+    (
+        StatusCode::OK,
+        [("code", "200"), ("msg", "ok")],
+        Json(json!({
+            "_embedded": {
+                "session": sessions
+            }
+        })),
+    )
+}
+
+async fn revoke_my_session(
+    Path(sid): Path<String>,
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<READER>,
+    _: RequireSession,
+) -> impl IntoResponse {
+    auth::insert_log(
+        &app_state,
+        format!("{} revoke session {}", auth_user.username, sid),
+    )
+    .await;
+    revoke_owned_session(&app_state.sled_db, auth_user.id, &sid)
+}
+
+/// 退出全部设备, 包括当前会话
+async fn revoke_my_sessions(
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<READER>,
+    _: RequireSession,
+) -> impl IntoResponse {
+    let count = revoke_user(&app_state.sled_db, auth_user.id);
+    auth::insert_log(
+        &app_state,
+        format!("{} revoke all {} sessions", auth_user.username, count),
+    )
+    .await;
+    (
+        StatusCode::OK,
+        [("code", "200"), ("msg", "ok")],
+        Json(json!({
+            "revoked": count
+        })),
+    )
+}
+
+async fn sessions_of_user(
+    Path(id): Path<i32>,
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<ADMIN>,
+    RequireSession(session_id): RequireSession,
+) -> impl IntoResponse {
+    log::info!("admin {} query sessions of user {}", auth_user.username, id);
+    let sessions = session_output_dtos(&app_state.sled_db, id, Some(&session_id.0));
+    (
+        StatusCode::OK,
+        [("code", "200"), ("msg", "ok")],
+        Json(json!({
+            "_embedded": {
+                "session": sessions
+            }
+        })),
+    )
+}
+
+async fn revoke_session_of_user(
+    Path((id, sid)): Path<(i32, String)>,
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<ADMIN>,
+    _: RequireSession,
+) -> impl IntoResponse {
+    auth::insert_log(
+        &app_state,
+        format!(
+            "{} revoke session {} of user {}",
+            auth_user.username, sid, id
+        ),
+    )
+    .await;
+    revoke_owned_session(&app_state.sled_db, id, &sid)
+}
+
+async fn revoke_sessions_of_user(
+    Path(id): Path<i32>,
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<ADMIN>,
+    _: RequireSession,
+) -> impl IntoResponse {
+    let count = revoke_user(&app_state.sled_db, id);
+    auth::insert_log(
+        &app_state,
+        format!(
+            "{} revoke all {} sessions of user {}",
+            auth_user.username, count, id
+        ),
+    )
+    .await;
+    (
+        StatusCode::OK,
+        [("code", "200"), ("msg", "ok")],
+        Json(json!({
+            "revoked": count
+        })),
+    )
+}
@@ -0,0 +1,365 @@
+use std::collections::{HashMap, HashSet};
+
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, put},
+};
+use entity::{tbl_article_tag, tbl_pdf_article, tbl_pdf_article_tag, tbl_tag};
+use sea_orm::{
+    ActiveValue::Set,
+    ColumnTrait, ConnectionTrait, DbBackend, DbErr, EntityTrait, FromQueryResult, QueryFilter,
+    QueryOrder, Statement, TransactionTrait,
+    sea_query::{Expr, OnConflict, SimpleExpr},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    AppState,
+    auth::{self, EDITOR, READER, RequireRole},
+};
+
+pub fn routers(state: AppState) -> Router {
+    Router::new()
+        .route("/tags/cloud", get(cloud))
+        .route("/pdf_articles/{id}/tags", put(replace_pdf_tags))
+        .with_state(state)
+}
+
+const MAX_TAGS: usize = 20;
+const MAX_TAG_CHARS: usize = 32;
+
+/// 多个标签的过滤方式, and要求包含全部标签, or包含任一标签即可
+#[derive(Deserialize, Debug, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum TagMode {
+    #[default]
+    And,
+    Or,
+}
+
+/// 去掉首尾空白并去重, 保持原有顺序
+pub fn normalize_tags(tags: &[String]) -> Result<Vec<String>, String> {
+    let mut seen = HashSet::new();
+    let mut names = Vec::new();
+    for tag in tags {
+        let name = tag.trim();
+        if name.is_empty() {
+            return Err("empty tag".to_string());
+        }
+        if name.chars().count() > MAX_TAG_CHARS {
+            return Err(format!("tag {name} longer than {MAX_TAG_CHARS} chars"));
+        }
+        if seen.insert(name) {
+            names.push(name.to_string());
+        }
+    }
+    if names.len() > MAX_TAGS {
+        return Err(format!("more than {MAX_TAGS} tags"));
+    }
+    Ok(names)
+}
+
+/// 解析查询参数中逗号分隔的标签, 去掉重复的标签
+pub fn parse_tags(tags: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    tags.split(',')
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty() && seen.insert(*tag))
+        .map(str::to_string)
+        .collect()
+}
+
+/// 标签名对应的id, 不存在的标签自动创建
+async fn tag_ids<C: ConnectionTrait>(db: &C, names: &[String]) -> Result<Vec<i32>, DbErr> {
+    if names.is_empty() {
+        return Ok(Vec::new());
+    }
+    let tbl_tag_ams = names.iter().map(|name| tbl_tag::ActiveModel {
+        name: Set(name.clone()),
+        ..Default::default()
+    });
+    tbl_tag::Entity::insert_many(tbl_tag_ams)
+        .on_conflict(
+            OnConflict::column(tbl_tag::Column::Name)
+                .do_nothing()
+                .to_owned(),
+        )
+        .do_nothing()
+        .exec(db)
+        .await?;
+    Ok(tbl_tag::Entity::find()
+        .filter(tbl_tag::Column::Name.is_in(names))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|tbl_tag| tbl_tag.id)
+        .collect())
+}
+
+/// 替换文章的全部标签, names需先经过normalize_tags
+pub async fn replace_article_tags<C: ConnectionTrait>(
+    db: &C,
+    article_id: i32,
+    names: &[String],
+) -> Result<(), DbErr> {
+    tbl_article_tag::Entity::delete_many()
+        .filter(tbl_article_tag::Column::ArticleId.eq(article_id))
+        .exec(db)
+        .await?;
+    let tag_ids = tag_ids(db, names).await?;
+    if tag_ids.is_empty() {
+        return Ok(());
+    }
+    let tbl_article_tag_ams = tag_ids
+        .into_iter()
+        .map(|tag_id| tbl_article_tag::ActiveModel {
+            article_id: Set(article_id),
+            tag_id: Set(tag_id),
+            ..Default::default()
+        });
+    tbl_article_tag::Entity::insert_many(tbl_article_tag_ams)
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// 替换PDF的全部标签, names需先经过normalize_tags
+pub async fn replace_pdf_article_tags<C: ConnectionTrait>(
+    db: &C,
+    pdf_article_id: i32,
+    names: &[String],
+) -> Result<(), DbErr> {
+    tbl_pdf_article_tag::Entity::delete_many()
+        .filter(tbl_pdf_article_tag::Column::PdfArticleId.eq(pdf_article_id))
+        .exec(db)
+        .await?;
+    let tag_ids = tag_ids(db, names).await?;
+    if tag_ids.is_empty() {
+        return Ok(());
+    }
+    let tbl_pdf_article_tag_ams =
+        tag_ids
+            .into_iter()
+            .map(|tag_id| tbl_pdf_article_tag::ActiveModel {
+                pdf_article_id: Set(pdf_article_id),
+                tag_id: Set(tag_id),
+                ..Default::default()
+            });
+    tbl_pdf_article_tag::Entity::insert_many(tbl_pdf_article_tag_ams)
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// 批量查询文章的标签, 按标签名排序
+pub async fn article_tags<C: ConnectionTrait>(
+    db: &C,
+    article_ids: &[i32],
+) -> Result<HashMap<i32, Vec<String>>, DbErr> {
+    let mut tags: HashMap<i32, Vec<String>> = HashMap::new();
+    for (tbl_article_tag, tbl_tag) in tbl_article_tag::Entity::find()
+        .find_also_related(tbl_tag::Entity)
+        .filter(tbl_article_tag::Column::ArticleId.is_in(article_ids.iter().copied()))
+        .order_by_asc(tbl_tag::Column::Name)
+        .all(db)
+        .await?
+    {
+        if let Some(tbl_tag) = tbl_tag {
+            tags.entry(tbl_article_tag.article_id)
+                .or_default()
+                .push(tbl_tag.name);
+        }
+    }
+    Ok(tags)
+}
+
+/// 批量查询PDF的标签, 按标签名排序
+pub async fn pdf_article_tags<C: ConnectionTrait>(
+    db: &C,
+    pdf_article_ids: &[i32],
+) -> Result<HashMap<i32, Vec<String>>, DbErr> {
+    let mut tags: HashMap<i32, Vec<String>> = HashMap::new();
+    for (tbl_pdf_article_tag, tbl_tag) in tbl_pdf_article_tag::Entity::find()
+        .find_also_related(tbl_tag::Entity)
+        .filter(tbl_pdf_article_tag::Column::PdfArticleId.is_in(pdf_article_ids.iter().copied()))
+        .order_by_asc(tbl_tag::Column::Name)
+        .all(db)
+        .await?
+    {
+        if let Some(tbl_tag) = tbl_tag {
+            tags.entry(tbl_pdf_article_tag.pdf_article_id)
+                .or_default()
+                .push(tbl_tag.name);
+        }
+    }
+    Ok(tags)
+}
+
+/// 按标签过滤文章的条件
+pub fn article_filter(tags: &[String], mode: TagMode) -> SimpleExpr {
+    tag_filter("tbl_article", "tbl_article_tag", "article_id", tags, mode)
+}
+
+/// 按标签过滤PDF的条件
+pub fn pdf_article_filter(tags: &[String], mode: TagMode) -> SimpleExpr {
+    tag_filter(
+        "tbl_pdf_article",
+        "tbl_pdf_article_tag",
+        "pdf_article_id",
+        tags,
+        mode,
+    )
+}
+
+fn tag_filter(
+    table: &str,
+    join_table: &str,
+    join_column: &str,
+    tags: &[String],
+    mode: TagMode,
+) -> SimpleExpr {
+    let placeholders = vec!["?"; tags.len()].join(", ");
+    // 关联表有(对象, 标签)唯一索引, 命中数等于标签数即包含全部标签
+    let having = match mode {
+        TagMode::And => format!(" HAVING COUNT(*) = {}", tags.len()),
+        TagMode::Or => String::new(),
+    };
+    Expr::cust_with_values(
+        format!(
+            "{table}.id IN (SELECT {join_table}.{join_column} FROM {join_table} JOIN tbl_tag ON tbl_tag.id = {join_table}.tag_id WHERE tbl_tag.name IN ({placeholders}) GROUP BY {join_table}.{join_column}{having})"
+        ),
+        tags.iter().cloned(),
+    )
+}
+
+#[derive(Deserialize, Debug)]
+struct CloudInputDto {
+    limit: Option<u64>,
+}
+
+#[derive(Serialize, Debug, FromQueryResult)]
+struct CloudOutputDto {
+    name: String,
+    article_count: i64,
+    pdf_article_count: i64,
+    count: i64,
+}
+
+/// 标签云, 文章只统计自己的, PDF统计全部, 不含回收站中的内容
+async fn cloud(
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<READER>,
+    Query(cloud_input_dto): Query<CloudInputDto>,
+) -> impl IntoResponse {
+    let limit = cloud_input_dto.limit.unwrap_or(100).clamp(1, 1000);
+    match CloudOutputDto::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Sqlite,
+        "SELECT name, article_count, pdf_article_count, article_count + pdf_article_count AS count FROM (SELECT t.name, (SELECT COUNT(*) FROM tbl_article_tag at JOIN tbl_article a ON a.id = at.article_id WHERE at.tag_id = t.id AND a.user_id = ? AND a.deleted_at IS NULL) AS article_count, (SELECT COUNT(*) FROM tbl_pdf_article_tag pt JOIN tbl_pdf_article p ON p.id = pt.pdf_article_id WHERE pt.tag_id = t.id AND p.deleted_at IS NULL) AS pdf_article_count FROM tbl_tag t) WHERE count > 0 ORDER BY count DESC, name LIMIT ?",
+        [auth_user.id.into(), (limit as i64).into()],
+    ))
+    .all(&app_state.db_conn)
+    .await
+    {
+        Ok(cloud_output_dtos) => (
+            StatusCode::OK,
+            [("code", "200"), ("msg", "ok")],
+            Json(json!({
+                "_embedded": {
+                    "tag": cloud_output_dtos
+                }
+            })),
+        ),
+        Err(e) => {
+            log::error!("tag cloud err: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tag cloud err")],
+                Json(json!({})),
+            )
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct ReplaceTagsInputDto {
+    tags: Vec<String>,
+}
+
+/// PDF上传接口为multipart, 标签单独设置
+async fn replace_pdf_tags(
+    Path(id): Path<i32>,
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<EDITOR>,
+    Json(replace_tags_input_dto): Json<ReplaceTagsInputDto>,
+) -> impl IntoResponse {
+    let names = match normalize_tags(&replace_tags_input_dto.tags) {
+        Ok(v) => v,
+        Err(e) => {
+            log::warn!("pdf_article {} tags invalid: {}", id, e);
+            return (
+                StatusCode::BAD_REQUEST,
+                [("code", "400"), ("msg", "invalid tags")],
+                Json(json!({})),
+            );
+        }
+    };
+    match tbl_pdf_article::Entity::find_by_id(id)
+        .filter(tbl_pdf_article::Column::DeletedAt.is_null())
+        .one(&app_state.db_conn)
+        .await
+    {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            log::warn!("tbl_pdf_article not find {}", id);
+            return (
+                StatusCode::NOT_FOUND,
+                [("code", "404"), ("msg", "not found")],
+                Json(json!({})),
+            );
+        }
+        Err(e) => {
+            log::error!("tbl_pdf_article find err: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_pdf_article find err")],
+                Json(json!({})),
+            );
+        }
+    }
+    auth::insert_log(
+        &app_state,
+        format!(
+            "{} tag pdf_article {} by {:?}",
+            auth_user.username, id, names
+        ),
+    )
+    .await;
+    let result = async {
+        let txn = app_state.db_conn.begin().await?;
+        replace_pdf_article_tags(&txn, id, &names).await?;
+        txn.commit().await?;
+        Ok::<_, DbErr>(())
+    }
+    .await;
+    match result {
+        Ok(()) => (
+            StatusCode::OK,
+            [("code", "200"), ("msg", "ok")],
+            Json(json!({
+                "tags": names
+            })),
+        ),
+        Err(e) => {
+            log::error!("tbl_pdf_article_tag replace err: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_pdf_article_tag replace err")],
+                Json(json!({})),
+            )
+        }
+    }
+}
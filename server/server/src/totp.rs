@@ -0,0 +1,602 @@
+use axum::{Json, Router, extract::State, http::StatusCode, response::IntoResponse, routing::post};
+use entity::tbl_auth_user;
+use sea_orm::{
+    ActiveValue::Set, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter, sea_query::Expr,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use subtle::ConstantTimeEq;
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use crate::{
+    AppState,
+    auth::{self, READER, RequireRole, RequireSession},
+};
+
+const ISSUER: &str = "self_examination";
+const RECOVERY_CODE_COUNT: usize = 10;
+// 登录挑战保存在sled独立的tree中, 有效期5分钟, 最多尝试5次
+const LOGIN_CHALLENGE_TREE: &str = "login_challenge";
+const LOGIN_CHALLENGE_TTL_SECS: i64 = 5 * 60;
+const LOGIN_CHALLENGE_MAX_ATTEMPTS: u32 = 5;
+// 记录每个用户最近一次使用的时间片, 防止验证码重放
+const TOTP_LAST_STEP_TREE: &str = "totp_last_step";
+
+pub fn routers(state: AppState) -> Router {
+    Router::new()
+        .route("/users/me/totp", post(enroll).delete(disable))
+        .route("/users/me/totp/enable", post(enable))
+        .with_state(state)
+}
+
+fn build_totp(secret_base32: &str, username: &str) -> Option<TOTP> {
+    let secret = match Secret::Encoded(secret_base32.to_string()).to_bytes() {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("totp secret decode err: {:?}", e);
+            return None;
+        }
+    };
+    // otpauth的账号名不允许包含':'
+    let account_name = username.replace(':', "_");
+    match TOTP::new(
+        Algorithm::SHA1,
+        6,
+        1,
+        30,
+        secret,
+        Some(ISSUER.to_string()),
+        account_name,
+    ) {
+        Ok(v) => Some(v),
+        Err(e) => {
+            log::error!("totp new err: {:?}", e);
+            None
+        }
+    }
+}
+
+/// 校验TOTP验证码, 允许前后各一个时间片的偏差, 同一时间片的验证码只能使用一次
+pub fn verify_code(sled_db: &sled::Db, tbl_auth_user: &tbl_auth_user::Model, code: &str) -> bool {
+    let Some(secret_base32) = tbl_auth_user.totp_secret.as_deref() else {
+        return false;
+    };
+    let Some(totp) = build_totp(secret_base32, &tbl_auth_user.username) else {
+        return false;
+    };
+    let tree = match sled_db.open_tree(TOTP_LAST_STEP_TREE) {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("sled open_tree err: {}", e);
+            return false;
+        }
+    };
+    let ts_now = chrono::Utc::now().timestamp() as u64;
+    let step_now = ts_now / totp.step;
+    let matched_steps: Vec<u64> = [step_now - 1, step_now, step_now + 1]
+        .into_iter()
+        .filter(|step| {
+            let expected = totp.generate(step * totp.step);
+            bool::from(expected.as_bytes().ct_eq(code.as_bytes()))
+        })
+        .collect();
+    let user_key = tbl_auth_user.id.to_be_bytes();
+    // 通过compare_and_swap更新时间片, 并发提交同一验证码时只有一个成功
+    loop {
+        let old = match tree.get(user_key) {
+            Ok(v) => v,
+            Err(e) => {
+                log::error!("sled get totp last step err: {}", e);
+                return false;
+            }
+        };
+        let last_step = old
+            .as_ref()
+            .and_then(|v| v.as_ref().try_into().ok())
+            .map(u64::from_be_bytes)
+            .unwrap_or(0);
+        let Some(step) = matched_steps.iter().find(|step| **step > last_step) else {
+            return false;
+        };
+        match tree.compare_and_swap(user_key, old, Some(&step.to_be_bytes()[..])) {
+            Ok(Ok(())) => return true,
+            // 其他请求已更新, 重新读取后再校验
+            Ok(Err(_)) => continue,
+            Err(e) => {
+                log::error!("sled compare_and_swap totp last step err: {}", e);
+                return false;
+            }
+        }
+    }
+}
+
+/// 校验恢复码, 校验成功后该恢复码作废
+pub async fn verify_recovery_code(
+    app_state: &AppState,
+    tbl_auth_user: &tbl_auth_user::Model,
+    recovery_code: &str,
+) -> bool {
+    let recovery_code_hashes: Vec<String> = match tbl_auth_user.totp_recovery_codes.as_deref() {
+        Some(v) => serde_json::from_str(v).unwrap_or_default(),
+        None => return false,
+    };
+    let recovery_code = recovery_code.trim().to_lowercase();
+    let Some(index) = recovery_code_hashes
+        .iter()
+        .position(|hash| auth::verify_password(&recovery_code, hash))
+    else {
+        return false;
+    };
+    let mut remain = recovery_code_hashes;
+    remain.remove(index);
+    // 只有恢复码未被其他请求改动时才更新, 同一恢复码并发使用时只有一个成功
+    match tbl_auth_user::Entity::update_many()
+        .col_expr(
+            tbl_auth_user::Column::TotpRecoveryCodes,
+            Expr::value(json!(remain).to_string()),
+        )
+        .filter(tbl_auth_user::Column::Id.eq(tbl_auth_user.id))
+        .filter(
+            tbl_auth_user::Column::TotpRecoveryCodes.eq(tbl_auth_user.totp_recovery_codes.clone()),
+        )
+        .exec(&app_state.db_conn)
+        .await
+    {
+        Ok(update_result) if update_result.rows_affected == 1 => {
+            log::warn!(
+                "user {} used recovery code, {} remain",
+                tbl_auth_user.username,
+                remain.len()
+            );
+            true
+        }
+        Ok(_) => {
+            log::warn!(
+                "user {} recovery codes changed concurrently",
+                tbl_auth_user.username
+            );
+            false
+        }
+        Err(e) => {
+            log::error!("tbl_auth_user update recovery codes err: {}", e);
+            false
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct LoginChallenge {
+    user_id: i32,
+    #[serde(default)]
+    remember_me: bool,
+    expires_at: i64,
+    attempts: u32,
+}
+
+/// 密码校验通过且开启了两步验证时, 生成短期有效的登录挑战
+pub fn new_challenge(sled_db: &sled::Db, user_id: i32, remember_me: bool) -> Option<String> {
+    let tree = match sled_db.open_tree(LOGIN_CHALLENGE_TREE) {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("sled open_tree err: {}", e);
+            return None;
+        }
+    };
+    let challenge = uuid::Uuid::new_v4().to_string();
+    let login_challenge = LoginChallenge {
+        user_id,
+        remember_me,
+        expires_at: chrono::Utc::now().timestamp() + LOGIN_CHALLENGE_TTL_SECS,
+        attempts: 0,
+    };
+    match serde_json::to_vec(&login_challenge) {
+        Ok(v) => {
+            if let Err(e) = tree.insert(&challenge, v) {
+                log::error!("sled insert login challenge err: {}", e);
+                return None;
+            }
+        }
+        Err(e) => {
+            log::error!("login_challenge to_vec err: {}", e);
+            return None;
+        }
+    }
+    Some(challenge)
+}
+
+/// 返回挑战对应的user_id和是否记住登录, 挑战过期或尝试次数过多时返回None
+pub fn get_challenge_user(sled_db: &sled::Db, challenge: &str) -> Option<(i32, bool)> {
+    let tree = match sled_db.open_tree(LOGIN_CHALLENGE_TREE) {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("sled open_tree err: {}", e);
+            return None;
+        }
+    };
+    let login_challenge = match tree.get(challenge) {
+        Ok(Some(v)) => serde_json::from_slice::<LoginChallenge>(&v).ok()?,
+        Ok(None) => return None,
+        Err(e) => {
+            log::error!("sled get login challenge err: {}", e);
+            return None;
+        }
+    };
+    if login_challenge.expires_at <= chrono::Utc::now().timestamp()
+        || login_challenge.attempts >= LOGIN_CHALLENGE_MAX_ATTEMPTS
+    {
+        if let Err(e) = tree.remove(challenge) {
+            log::error!("sled remove login challenge err: {}", e);
+        }
+        return None;
+    }
+    Some((login_challenge.user_id, login_challenge.remember_me))
+}
+
+/// 验证失败时增加挑战的尝试次数
+pub fn fail_challenge(sled_db: &sled::Db, challenge: &str) {
+    let tree = match sled_db.open_tree(LOGIN_CHALLENGE_TREE) {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("sled open_tree err: {}", e);
+            return;
+        }
+    };
+    if let Ok(Some(v)) = tree.get(challenge)
+        && let Ok(mut login_challenge) = serde_json::from_slice::<LoginChallenge>(&v)
+    {
+        login_challenge.attempts += 1;
+        match serde_json::to_vec(&login_challenge) {
+            Ok(v) => {
+                if let Err(e) = tree.insert(challenge, v) {
+                    log::error!("sled insert login challenge err: {}", e);
+                }
+            }
+            Err(e) => {
+                log::error!("login_challenge to_vec err: {}", e);
+            }
+        }
+    }
+}
+
+pub fn remove_challenge(sled_db: &sled::Db, challenge: &str) {
+    match sled_db.open_tree(LOGIN_CHALLENGE_TREE) {
+        Ok(tree) => {
+            if let Err(e) = tree.remove(challenge) {
+                log::error!("sled remove login challenge err: {}", e);
+            }
+        }
+        Err(e) => {
+            log::error!("sled open_tree err: {}", e);
+        }
+    }
+}
+
+/// 清理过期的登录挑战, 由token_expired_task周期调用
+pub fn purge_expired(sled_db: &sled::Db) {
+    let tree = match sled_db.open_tree(LOGIN_CHALLENGE_TREE) {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("sled open_tree err: {}", e);
+            return;
+        }
+    };
+    let ts_now = chrono::Utc::now().timestamp();
+    for (k, v) in tree.iter().flatten() {
+        let expired = match serde_json::from_slice::<LoginChallenge>(&v) {
+            Ok(login_challenge) => login_challenge.expires_at <= ts_now,
+            Err(_) => true,
+        };
+        if expired && let Err(e) = tree.remove(&k) {
+            log::error!("sled remove login challenge err: {}", e);
+        }
+    }
+}
+
+async fn find_user(
+    app_state: &AppState,
+    user_id: i32,
+) -> Result<tbl_auth_user::Model, (StatusCode, [(&'static str, &'static str); 2])> {
+    match tbl_auth_user::Entity::find_by_id(user_id)
+        .one(&app_state.db_conn)
+        .await
+    {
+        Ok(Some(v)) => Ok(v),
+        Ok(None) => {
+            log::warn!("user not found: {}", user_id);
+            Err((
+                StatusCode::BAD_REQUEST,
+                [("code", "400"), ("msg", "not found")],
+            ))
+        }
+        Err(e) => {
+            log::error!("find user by id {} err: {e}", user_id);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_auth_user find err")],
+            ))
+        }
+    }
+}
+
+/// 生成新的密钥, 需调用enable接口校验验证码后才生效
+async fn enroll(
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<READER>,
+    _: RequireSession,
+) -> impl IntoResponse {
+    let tbl_auth_user = match find_user(&app_state, auth_user.id).await {
+        Ok(v) => v,
+        Err((status_code, headers)) => return (status_code, headers, Json(json!({}))),
+    };
+    if tbl_auth_user.totp_enabled {
+        log::warn!("user {} totp already enabled", auth_user.username);
+        return (
+            StatusCode::CONFLICT,
+            [("code", "409"), ("msg", "totp already enabled")],
+            Json(json!({})),
+        );
+    }
+    let secret_base32 = match Secret::generate_secret().to_encoded() {
+        Secret::Encoded(v) => v,
+        Secret::Raw(_) => {
+            log::error!("totp secret encode err");
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "totp secret err")],
+                Json(json!({})),
+            );
+        }
+    };
+    let Some(totp) = build_totp(&secret_base32, &tbl_auth_user.username) else {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            [("code", "500"), ("msg", "totp secret err")],
+            Json(json!({})),
+        );
+    };
+    let mut tbl_auth_user_am = tbl_auth_user.into_active_model();
+    tbl_auth_user_am.totp_secret = Set(Some(secret_base32.clone()));
+    tbl_auth_user_am.totp_recovery_codes = Set(None);
+    match tbl_auth_user::Entity::update(tbl_auth_user_am)
+        .exec(&app_state.db_conn)
+        .await
+    {
+        Ok(_) => (
+            StatusCode::OK,
+            [("code", "200"), ("msg", "ok")],
+            Json(json!({
+                "secret": secret_base32,
+                "otpauth_uri": totp.get_url(),
+            })),
+        ),
+        Err(e) => {
+            log::error!("tbl_auth_user update totp secret err: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "update db err")],
+                Json(json!({})),
+            )
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct EnableInputDto {
+    code: String,
+}
+/// 校验验证码后开启两步验证, 返回的恢复码只展示这一次
+async fn enable(
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<READER>,
+    _: RequireSession,
+    Json(enable_input_dto): Json<EnableInputDto>,
+) -> impl IntoResponse {
+    let tbl_auth_user = match find_user(&app_state, auth_user.id).await {
+        Ok(v) => v,
+        Err((status_code, headers)) => return (status_code, headers, Json(json!({}))),
+    };
+    if tbl_auth_user.totp_enabled {
+        log::warn!("user {} totp already enabled", auth_user.username);
+        return (
+            StatusCode::CONFLICT,
+            [("code", "409"), ("msg", "totp already enabled")],
+            Json(json!({})),
+        );
+    }
+    if !verify_code(&app_state.sled_db, &tbl_auth_user, &enable_input_dto.code) {
+        log::warn!("user {} enable totp code mismatch", auth_user.username);
+        return (
+            StatusCode::BAD_REQUEST,
+            [("code", "400"), ("msg", "code mismatch")],
+            Json(json!({})),
+        );
+    }
+    let mut recovery_codes = Vec::new();
+    let mut recovery_code_hashes = Vec::new();
+    for _ in 0..RECOVERY_CODE_COUNT {
+        let random = uuid::Uuid::new_v4().simple().to_string();
+        let recovery_code = format!("{}-{}", &random[..5], &random[5..10]);
+        match auth::hash_password(&recovery_code) {
+            Ok(v) => recovery_code_hashes.push(v),
+            Err(e) => {
+                log::error!("hash recovery code err: {}", e);
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    [("code", "500"), ("msg", "hash recovery code err")],
+                    Json(json!({})),
+                );
+            }
+        }
+        recovery_codes.push(recovery_code);
+    }
+    let mut tbl_auth_user_am = tbl_auth_user.into_active_model();
+    tbl_auth_user_am.totp_enabled = Set(true);
+    tbl_auth_user_am.totp_recovery_codes = Set(Some(json!(recovery_code_hashes).to_string()));
+    match tbl_auth_user::Entity::update(tbl_auth_user_am)
+        .exec(&app_state.db_conn)
+        .await
+    {
+        Ok(_) => {
+            auth::insert_log(&app_state, format!("{} enable totp", auth_user.username)).await;
+            (
+                StatusCode::OK,
+                [("code", "200"), ("msg", "ok")],
+                Json(json!({
+                    "recovery_codes": recovery_codes
+                })),
+            )
+        }
+        Err(e) => {
+            log::error!("tbl_auth_user update totp enabled err: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "update db err")],
+                Json(json!({})),
+            )
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct DisableInputDto {
+    password: String,
+}
+/// 关闭两步验证, 需要再次输入密码
+async fn disable(
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<READER>,
+    _: RequireSession,
+    Json(disable_input_dto): Json<DisableInputDto>,
+) -> impl IntoResponse {
+    let tbl_auth_user = match find_user(&app_state, auth_user.id).await {
+        Ok(v) => v,
+        Err((status_code, headers)) => return (status_code, headers, Json(json!({}))),
+    };
+    if !auth::verify_password(&disable_input_dto.password, &tbl_auth_user.password) {
+        log::warn!("user {} disable totp password mismatch", auth_user.username);
+        return (
+            StatusCode::BAD_REQUEST,
+            [("code", "400"), ("msg", "password mismatch")],
+            Json(json!({})),
+        );
+    }
+    let mut tbl_auth_user_am = tbl_auth_user.into_active_model();
+    tbl_auth_user_am.totp_enabled = Set(false);
+    tbl_auth_user_am.totp_secret = Set(None);
+    tbl_auth_user_am.totp_recovery_codes = Set(None);
+    match tbl_auth_user::Entity::update(tbl_auth_user_am)
+        .exec(&app_state.db_conn)
+        .await
+    {
+        Ok(_) => {
+            auth::insert_log(&app_state, format!("{} disable totp", auth_user.username)).await;
+            (
+                StatusCode::OK,
+                [("code", "200"), ("msg", "ok")],
+                Json(json!({})),
+            )
+        }
+        Err(e) => {
+            log::error!("tbl_auth_user update totp disabled err: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "update db err")],
+                Json(json!({})),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use migration::{Migrator, MigratorTrait};
+    use sea_orm::Database;
+
+    use super::*;
+
+    fn totp_user(totp_secret: String) -> tbl_auth_user::Model {
+        tbl_auth_user::Model {
+            id: 1,
+            username: "bob".to_string(),
+            password: "not used".to_string(),
+            created_at: chrono::Utc::now().naive_utc(),
+            enabled: true,
+            role: "editor".to_string(),
+            totp_secret: Some(totp_secret),
+            totp_enabled: true,
+            totp_recovery_codes: None,
+            time_zone: "UTC".to_string(),
+            oidc_subject: None,
+        }
+    }
+
+    fn new_secret() -> String {
+        match Secret::generate_secret().to_encoded() {
+            Secret::Encoded(v) => v,
+            Secret::Raw(_) => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn same_code_accepted_once_under_concurrency() {
+        let sled_db = sled::Config::new().temporary(true).open().unwrap();
+        let secret = new_secret();
+        let tbl_auth_user = totp_user(secret.clone());
+        let code = build_totp(&secret, &tbl_auth_user.username)
+            .unwrap()
+            .generate_current()
+            .unwrap();
+        let accepted: usize = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..16)
+                .map(|_| scope.spawn(|| verify_code(&sled_db, &tbl_auth_user, &code)))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap() as usize)
+                .sum()
+        });
+        assert_eq!(accepted, 1);
+    }
+
+    #[tokio::test]
+    async fn recovery_code_used_once() {
+        let db_path = std::env::temp_dir().join(format!(
+            "totp_test_{}.sqlite",
+            uuid::Uuid::new_v4().simple()
+        ));
+        let db_conn = Database::connect(format!("sqlite://{}?mode=rwc", db_path.to_string_lossy()))
+            .await
+            .unwrap();
+        Migrator::up(&db_conn, None).await.unwrap();
+        let sled_db = sled::Config::new().temporary(true).open().unwrap();
+        let app_state = AppState { db_conn, sled_db };
+        let recovery_codes = json!([
+            auth::hash_password("aaaa-bbbb").unwrap(),
+            auth::hash_password("cccc-dddd").unwrap()
+        ])
+        .to_string();
+        let tbl_auth_user = tbl_auth_user::Entity::insert(tbl_auth_user::ActiveModel {
+            username: Set("bob".to_string()),
+            password: Set("not used".to_string()),
+            role: Set("editor".to_string()),
+            totp_secret: Set(Some(new_secret())),
+            totp_enabled: Set(true),
+            totp_recovery_codes: Set(Some(recovery_codes)),
+            ..Default::default()
+        })
+        .exec_with_returning(&app_state.db_conn)
+        .await
+        .unwrap();
+
+        // 两个请求读到同一份恢复码后先后提交, 只有第一个成功
+        assert!(verify_recovery_code(&app_state, &tbl_auth_user, "AAAA-BBBB").await);
+        assert!(!verify_recovery_code(&app_state, &tbl_auth_user, "aaaa-bbbb").await);
+        let tbl_auth_user = tbl_auth_user::Entity::find_by_id(tbl_auth_user.id)
+            .one(&app_state.db_conn)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!verify_recovery_code(&app_state, &tbl_auth_user, "aaaa-bbbb").await);
+        assert!(verify_recovery_code(&app_state, &tbl_auth_user, "cccc-dddd").await);
+    }
+}
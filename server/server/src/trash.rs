@@ -0,0 +1,307 @@
+use std::cmp::Reverse;
+
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, post},
+};
+use chrono::{Duration, NaiveDateTime};
+use entity::{tbl_article, tbl_file, tbl_pdf_article};
+use sea_orm::{
+    ColumnTrait, DbErr, EntityTrait, FromQueryResult, QueryFilter, QuerySelect, TransactionTrait,
+    sea_query::Expr,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    AppState, article, attachment,
+    auth::{self, EDITOR, READER, RequireRole, Role},
+    config::SERVER_TOML,
+};
+
+pub fn routers(state: AppState) -> Router {
+    Router::new()
+        .route("/trash", get(query))
+        .route("/trash/{kind}/{id}/restore", post(restore))
+        .with_state(state)
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum TrashKind {
+    Article,
+    File,
+    PdfArticle,
+}
+
+#[derive(FromQueryResult)]
+struct TrashItem {
+    id: i32,
+    title: String,
+    deleted_at: NaiveDateTime,
+}
+
+#[derive(Serialize, Debug)]
+struct QueryOutputDto {
+    kind: TrashKind,
+    id: i32,
+    title: String,
+    deleted_at: i64,
+    // 预计物理删除的时间, 不自动清理时为空
+    purge_at: Option<i64>,
+}
+
+/// 列出回收站, 文章只列出自己的; 文件需要编辑权限且只列出自己上传的, PDF需要管理员权限, 与删除接口一致
+async fn query(
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<READER>,
+) -> impl IntoResponse {
+    let result = async {
+        let mut trash_items = Vec::new();
+        for tbl_article in tbl_article::Entity::find()
+            .select_only()
+            .column(tbl_article::Column::Id)
+            .column(tbl_article::Column::Title)
+            .column(tbl_article::Column::DeletedAt)
+            .filter(tbl_article::Column::UserId.eq(auth_user.id))
+            .filter(tbl_article::Column::DeletedAt.is_not_null())
+            .into_model::<TrashItem>()
+            .all(&app_state.db_conn)
+            .await?
+        {
+            trash_items.push((TrashKind::Article, tbl_article));
+        }
+        if auth_user.role >= Role::Editor {
+            for tbl_file in tbl_file::Entity::find()
+                .select_only()
+                .column(tbl_file::Column::Id)
+                .column_as(tbl_file::Column::Name, "title")
+                .column(tbl_file::Column::DeletedAt)
+                .filter(tbl_file::Column::DeletedAt.is_not_null())
+                .filter(attachment::file_managed_by(&auth_user))
+                .into_model::<TrashItem>()
+                .all(&app_state.db_conn)
+                .await?
+            {
+                trash_items.push((TrashKind::File, tbl_file));
+            }
+        }
+        if auth_user.role >= Role::Admin {
+            for tbl_pdf_article in tbl_pdf_article::Entity::find()
+                .select_only()
+                .column(tbl_pdf_article::Column::Id)
+                .column(tbl_pdf_article::Column::Title)
+                .column(tbl_pdf_article::Column::DeletedAt)
+                .filter(tbl_pdf_article::Column::DeletedAt.is_not_null())
+                .into_model::<TrashItem>()
+                .all(&app_state.db_conn)
+                .await?
+            {
+                trash_items.push((TrashKind::PdfArticle, tbl_pdf_article));
+            }
+        }
+        Ok::<_, DbErr>(trash_items)
+    }
+    .await;
+    let mut trash_items = match result {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("trash query err: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "trash query err")],
+                Json(json!({})),
+            );
+        }
+    };
+    trash_items.sort_by_key(|(_, trash_item)| Reverse(trash_item.deleted_at));
+    let retention_days = SERVER_TOML.trash.retention_days;
+    let query_output_dtos: Vec<_> = trash_items
+        .into_iter()
+        .map(|(kind, trash_item)| QueryOutputDto {
+            kind,
+            id: trash_item.id,
+            title: trash_item.title,
+            deleted_at: trash_item.deleted_at.and_utc().timestamp_millis(),
+            purge_at: (retention_days > 0).then(|| {
+                (trash_item.deleted_at + Duration::days(retention_days as i64))
+                    .and_utc()
+                    .timestamp_millis()
+            }),
+        })
+        .collect();
+    (
+        StatusCode::OK,
+        [("code", "200"), ("msg", "ok")],
+        Json(json!({
+            "retention_days": retention_days,
+            "_embedded": {
+                "trash": query_output_dtos
+            }
+        })),
+    )
+}
+
+async fn restore(
+    Path((kind, id)): Path<(TrashKind, i32)>,
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<EDITOR>,
+) -> impl IntoResponse {
+    if kind == TrashKind::PdfArticle && auth_user.role < Role::Admin {
+        log::warn!(
+            "user {} not allowed to restore pdf_article",
+            auth_user.username
+        );
+        return (
+            StatusCode::FORBIDDEN,
+            [("code", "403"), ("msg", "FORBIDDEN")],
+            Json(json!({})),
+        );
+    }
+    auth::insert_log(
+        &app_state,
+        format!("{} restore {:?} {}", auth_user.username, kind, id),
+    )
+    .await;
+    let result = match kind {
+        TrashKind::Article => {
+            async {
+                let txn = app_state.db_conn.begin().await?;
+                let update_result = tbl_article::Entity::update_many()
+                    .col_expr(
+                        tbl_article::Column::DeletedAt,
+                        Expr::value(None::<NaiveDateTime>),
+                    )
+                    .filter(tbl_article::Column::Id.eq(id))
+                    .filter(tbl_article::Column::UserId.eq(auth_user.id))
+                    .filter(tbl_article::Column::DeletedAt.is_not_null())
+                    .exec(&txn)
+                    .await?;
+                // 删除文章时附件随之放入了回收站
+                if update_result.rows_affected == 1 && SERVER_TOML.article.trash_orphan_files {
+                    attachment::restore_files(&txn, id).await?;
+                }
+                txn.commit().await?;
+                Ok(update_result)
+            }
+            .await
+        }
+        TrashKind::File => {
+            tbl_file::Entity::update_many()
+                .col_expr(
+                    tbl_file::Column::DeletedAt,
+                    Expr::value(None::<NaiveDateTime>),
+                )
+                .filter(tbl_file::Column::Id.eq(id))
+                .filter(tbl_file::Column::DeletedAt.is_not_null())
+                .filter(attachment::file_managed_by(&auth_user))
+                .exec(&app_state.db_conn)
+                .await
+        }
+        TrashKind::PdfArticle => {
+            tbl_pdf_article::Entity::update_many()
+                .col_expr(
+                    tbl_pdf_article::Column::DeletedAt,
+                    Expr::value(None::<NaiveDateTime>),
+                )
+                .filter(tbl_pdf_article::Column::Id.eq(id))
+                .filter(tbl_pdf_article::Column::DeletedAt.is_not_null())
+                .exec(&app_state.db_conn)
+                .await
+        }
+    };
+    match result {
+        Ok(update_result) if update_result.rows_affected == 1 => {
+            log::info!("restore {:?} {} success", kind, id);
+            (
+                StatusCode::OK,
+                [("code", "200"), ("msg", "ok")],
+                Json(json!({})),
+            )
+        }
+        Ok(_) => {
+            log::warn!("{:?} {} not in trash", kind, id);
+            (
+                StatusCode::NOT_FOUND,
+                [("code", "404"), ("msg", "not found")],
+                Json(json!({})),
+            )
+        }
+        // 恢复的日记所在日期已经重新写过
+        Err(e) if article::is_unique_violation(&e) => {
+            log::warn!("restore {:?} {} conflict: {}", kind, id, e);
+            (
+                StatusCode::CONFLICT,
+                [("code", "409"), ("msg", "entry exists")],
+                Json(json!({})),
+            )
+        }
+        Err(e) => {
+            log::error!("restore {:?} {} err: {}", kind, id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "restore db err")],
+                Json(json!({})),
+            )
+        }
+    }
+}
+
+/// 物理删除超过保留期的回收站内容, 文章的回答和历史版本、PDF的访问记录随外键级联删除
+async fn purge_expired(app_state: &AppState, retention_days: u32) -> Result<(), DbErr> {
+    let cutoff = chrono::Utc::now().naive_utc() - Duration::days(retention_days as i64);
+    let article_count = tbl_article::Entity::delete_many()
+        .filter(tbl_article::Column::DeletedAt.lt(cutoff))
+        .exec(&app_state.db_conn)
+        .await?
+        .rows_affected;
+    let file_count = tbl_file::Entity::delete_many()
+        .filter(tbl_file::Column::DeletedAt.lt(cutoff))
+        .exec(&app_state.db_conn)
+        .await?
+        .rows_affected;
+    let pdf_article_count = tbl_pdf_article::Entity::delete_many()
+        .filter(tbl_pdf_article::Column::DeletedAt.lt(cutoff))
+        .exec(&app_state.db_conn)
+        .await?
+        .rows_affected;
+    if article_count + file_count + pdf_article_count > 0 {
+        log::info!(
+            "trash purge {} articles, {} files, {} pdf_articles",
+            article_count,
+            file_count,
+            pdf_article_count
+        );
+        auth::insert_log(
+            app_state,
+            format!(
+                "trash purge {article_count} articles, {file_count} files, {pdf_article_count} pdf_articles deleted before {cutoff}"
+            ),
+        )
+        .await;
+    }
+    Ok(())
+}
+
+pub fn purge_task(app_state: AppState) {
+    let retention_days = SERVER_TOML.trash.retention_days;
+    if retention_days == 0 {
+        log::info!("trash purge disabled");
+        return;
+    }
+    tokio::spawn(async move {
+        log::info!("trash purge_task running, retention_days: {retention_days}");
+        loop {
+            if let Err(e) = purge_expired(&app_state, retention_days).await {
+                log::error!("trash purge err: {}", e);
+            }
+            tokio::time::sleep(tokio::time::Duration::from_secs(
+                SERVER_TOML.trash.purge_interval,
+            ))
+            .await;
+        }
+    });
+}
@@ -0,0 +1,575 @@
+use std::str::FromStr;
+
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, patch},
+};
+use chrono_tz::Tz;
+use entity::tbl_auth_user;
+use sea_orm::{
+    ActiveValue::Set, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, IntoActiveModel,
+    PaginatorTrait, QueryFilter, QueryOrder,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use validator::Validate;
+
+use crate::{
+    AppState,
+    auth::{self, ADMIN, READER, RequireRole, RequireSession, Role},
+    session,
+};
+
+pub fn routers(state: AppState) -> Router {
+    Router::new()
+        .route("/users", get(query).post(create))
+        .route("/users/{id}", patch(update))
+        .route("/users/me/password", patch(change_my_password))
+        .route(
+            "/users/me/time_zone",
+            get(my_time_zone).patch(change_my_time_zone),
+        )
+        .with_state(state)
+}
+
+#[derive(Deserialize, Debug, Validate)]
+struct QueryInputDto {
+    username: Option<String>,
+    size: u64,
+    page: u64,
+}
+
+#[derive(Serialize, Debug)]
+struct QueryOutputDto {
+    id: i32,
+    username: String,
+    enabled: bool,
+    role: Role,
+    created_at: i64,
+    oidc_subject: Option<String>,
+}
+async fn query(
+    app_state: State<AppState>,
+    _: RequireRole<ADMIN>,
+    Query(query_input_dto): Query<QueryInputDto>,
+) -> impl IntoResponse {
+    let mut select = tbl_auth_user::Entity::find();
+    if let Some(username) = query_input_dto.username
+        && !username.is_empty()
+    {
+        let like_pattern = format!("%{username}%");
+        select = select.filter(tbl_auth_user::Column::Username.like(like_pattern));
+    }
+    let paginator = select
+        .order_by_asc(tbl_auth_user::Column::Id)
+        .paginate(&app_state.db_conn, query_input_dto.size);
+    let num_pages = match paginator.num_pages().await {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("num_pages err: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "pg connection err")],
+                Json(json!({})),
+            );
+        }
+    };
+    let num_items = match paginator.num_items().await {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("num_items err: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "pg connection err")],
+                Json(json!({})),
+            );
+        }
+    };
+    let tbl_auth_users = match paginator.fetch_page(query_input_dto.page).await {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("fetch_page err: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "pg content err")],
+                Json(json!({})),
+            );
+        }
+    };
+    let mut users = Vec::new();
+    for tbl_auth_user in tbl_auth_users {
+        users.push(QueryOutputDto {
+            id: tbl_auth_user.id,
+            username: tbl_auth_user.username,
+            enabled: tbl_auth_user.enabled,
+            role: Role::from_str(&tbl_auth_user.role).unwrap_or(Role::Reader),
+            created_at: tbl_auth_user.created_at.and_utc().timestamp_millis(),
+            oidc_subject: tbl_auth_user.oidc_subject,
+        });
+    }
+    (
+        StatusCode::OK,
+        [("code", "200"), ("msg", "ok")],
+        Json(json!(
+            {
+            "page":{
+              "size":query_input_dto.size,
+              "total_elements":num_items,
+              "total_pages":num_pages
+            },
+            "_embedded":{
+                "user":users
+            }
+           }
+        )),
+    )
+}
+
+#[derive(Deserialize, Debug, Validate)]
+struct CreateInputDto {
+    #[validate(length(min = 1, max = 64))]
+    username: String,
+    #[validate(length(min = 8, max = 128))]
+    password: String,
+    role: Option<Role>,
+}
+async fn create(
+    app_state: State<AppState>,
+    RequireRole(auth_user): RequireRole<ADMIN>,
+    _: RequireSession,
+    Json(create_input_dto): Json<CreateInputDto>,
+) -> impl IntoResponse {
+    if let Err(e) = create_input_dto.validate() {
+        log::warn!("create user input invalid: {}", e);
+        return (
+            StatusCode::BAD_REQUEST,
+            [("code", "400"), ("msg", "invalid input")],
+            Json(json!({})),
+        );
+    }
+    auth::insert_log(
+        &app_state,
+        format!(
+            "{} create user {} role: {:?}",
+            auth_user.username, create_input_dto.username, create_input_dto.role
+        ),
+    )
+    .await;
+    match tbl_auth_user::Entity::find()
+        .filter(tbl_auth_user::Column::Username.eq(&create_input_dto.username))
+        .count(&app_state.db_conn)
+        .await
+    {
+        Ok(0) => {}
+        Ok(_) => {
+            log::warn!("user {} already exists", create_input_dto.username);
+            return (
+                StatusCode::CONFLICT,
+                [("code", "409"), ("msg", "user already exists")],
+                Json(json!({})),
+            );
+        }
+        Err(e) => {
+            log::error!("tbl_auth_user count err: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_auth_user count err")],
+                Json(json!({})),
+            );
+        }
+    }
+    let password_hash = match auth::hash_password(&create_input_dto.password) {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("hash password err: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "hash password err")],
+                Json(json!({})),
+            );
+        }
+    };
+    let tbl_auth_user_am = tbl_auth_user::ActiveModel {
+        username: Set(create_input_dto.username),
+        password: Set(password_hash),
+        role: Set(create_input_dto
+            .role
+            .unwrap_or(Role::Reader)
+            .as_str()
+            .to_string()),
+        ..Default::default()
+    };
+    match tbl_auth_user::Entity::insert(tbl_auth_user_am)
+        .exec(&app_state.db_conn)
+        .await
+    {
+        Ok(insert_result) => (
+            StatusCode::OK,
+            [("code", "200"), ("msg", "ok")],
+            Json(json!({
+                "user_id": insert_result.last_insert_id
+            })),
+        ),
+        Err(e) => {
+            log::error!("tbl_auth_user insert err: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_auth_user insert err")],
+                Json(json!({})),
+            )
+        }
+    }
+}
+
+#[derive(Deserialize, Validate)]
+struct UpdateInputDto {
+    enabled: Option<bool>,
+    role: Option<Role>,
+    #[validate(length(min = 8, max = 128))]
+    password: Option<String>,
+    // 关联身份提供方的sub, 空字符串表示取消关联
+    #[validate(length(max = 255))]
+    oidc_subject: Option<String>,
+}
+/// 管理员修改用户状态、角色、OIDC关联或重置密码, 禁用或重置后该用户已有token立即失效
+async fn update(
+    Path(id): Path<i32>,
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<ADMIN>,
+    _: RequireSession,
+    Json(update_input_dto): Json<UpdateInputDto>,
+) -> impl IntoResponse {
+    if let Err(e) = update_input_dto.validate() {
+        log::warn!("update user input invalid: {}", e);
+        return (
+            StatusCode::BAD_REQUEST,
+            [("code", "400"), ("msg", "invalid input")],
+            Json(json!({})),
+        );
+    }
+    auth::insert_log(
+        &app_state,
+        format!(
+            "{} update user {} enabled: {:?}, role: {:?}, reset password: {}, oidc_subject: {:?}",
+            auth_user.username,
+            id,
+            update_input_dto.enabled,
+            update_input_dto.role,
+            update_input_dto.password.is_some(),
+            update_input_dto.oidc_subject
+        ),
+    )
+    .await;
+    let tbl_auth_user = match tbl_auth_user::Entity::find_by_id(id)
+        .one(&app_state.db_conn)
+        .await
+    {
+        Ok(Some(v)) => v,
+        Ok(None) => {
+            log::warn!("user not found: {id}");
+            return (
+                StatusCode::BAD_REQUEST,
+                [("code", "400"), ("msg", "not found")],
+                Json(json!({})),
+            );
+        }
+        Err(e) => {
+            log::error!("find user by id {id} err: {e}");
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_auth_user find err")],
+                Json(json!({})),
+            );
+        }
+    };
+    let mut revoke_tokens = false;
+    let mut tbl_auth_user_am = tbl_auth_user.into_active_model();
+    if let Some(enabled) = update_input_dto.enabled {
+        if id == auth_user.id && !enabled {
+            log::warn!("user {} can not disable self", auth_user.username);
+            return (
+                StatusCode::BAD_REQUEST,
+                [("code", "400"), ("msg", "can not disable self")],
+                Json(json!({})),
+            );
+        }
+        tbl_auth_user_am.enabled = Set(enabled);
+        revoke_tokens |= !enabled;
+    }
+    if let Some(role) = update_input_dto.role {
+        if id == auth_user.id && role != auth_user.role {
+            log::warn!("user {} can not change own role", auth_user.username);
+            return (
+                StatusCode::BAD_REQUEST,
+                [("code", "400"), ("msg", "can not change own role")],
+                Json(json!({})),
+            );
+        }
+        tbl_auth_user_am.role = Set(role.as_str().to_string());
+        // token中携带角色, 修改后需要重新登录
+        revoke_tokens = true;
+    }
+    if let Some(password) = update_input_dto.password {
+        match auth::hash_password(&password) {
+            Ok(password_hash) => tbl_auth_user_am.password = Set(password_hash),
+            Err(e) => {
+                log::error!("hash password err: {}", e);
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    [("code", "500"), ("msg", "hash password err")],
+                    Json(json!({})),
+                );
+            }
+        }
+        revoke_tokens = true;
+    }
+    if let Some(oidc_subject) = update_input_dto.oidc_subject {
+        let oidc_subject = Some(oidc_subject).filter(|v| !v.is_empty());
+        if let Some(subject) = &oidc_subject {
+            match tbl_auth_user::Entity::find()
+                .filter(tbl_auth_user::Column::OidcSubject.eq(subject))
+                .filter(tbl_auth_user::Column::Id.ne(id))
+                .one(&app_state.db_conn)
+                .await
+            {
+                Ok(None) => {}
+                Ok(Some(_)) => {
+                    log::warn!("oidc subject {} already linked", subject);
+                    return (
+                        StatusCode::BAD_REQUEST,
+                        [("code", "400"), ("msg", "oidc subject already linked")],
+                        Json(json!({})),
+                    );
+                }
+                Err(e) => {
+                    log::error!("tbl_auth_user find err: {}", e);
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        [("code", "500"), ("msg", "tbl_auth_user find err")],
+                        Json(json!({})),
+                    );
+                }
+            }
+        }
+        tbl_auth_user_am.oidc_subject = Set(oidc_subject);
+    }
+    match tbl_auth_user::Entity::update(tbl_auth_user_am)
+        .exec(&app_state.db_conn)
+        .await
+    {
+        Ok(_) => {
+            if revoke_tokens {
+                session::revoke_user(&app_state.sled_db, id);
+            }
+            (
+                StatusCode::OK,
+                [("code", "200"), ("msg", "ok")],
+                Json(json!({})),
+            )
+        }
+        Err(e) => {
+            log::error!("update user {id} err: {e}");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "update db err")],
+                Json(json!({})),
+            )
+        }
+    }
+}
+
+#[derive(Deserialize, Validate)]
+struct ChangePasswordInputDto {
+    old_password: String,
+    #[validate(length(min = 8, max = 128))]
+    new_password: String,
+}
+async fn change_my_password(
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<READER>,
+    RequireSession(session_id): RequireSession,
+    Json(change_password_input_dto): Json<ChangePasswordInputDto>,
+) -> impl IntoResponse {
+    if let Err(e) = change_password_input_dto.validate() {
+        log::warn!("change password input invalid: {}", e);
+        return (
+            StatusCode::BAD_REQUEST,
+            [("code", "400"), ("msg", "invalid input")],
+            Json(json!({})),
+        );
+    }
+    let tbl_auth_user = match tbl_auth_user::Entity::find_by_id(auth_user.id)
+        .one(&app_state.db_conn)
+        .await
+    {
+        Ok(Some(v)) => v,
+        Ok(None) => {
+            log::warn!("user not found: {}", auth_user.id);
+            return (
+                StatusCode::BAD_REQUEST,
+                [("code", "400"), ("msg", "not found")],
+                Json(json!({})),
+            );
+        }
+        Err(e) => {
+            log::error!("find user by id {} err: {e}", auth_user.id);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_auth_user find err")],
+                Json(json!({})),
+            );
+        }
+    };
+    if !auth::verify_password(
+        &change_password_input_dto.old_password,
+        &tbl_auth_user.password,
+    ) {
+        log::warn!("user {} old password mismatch", auth_user.username);
+        return (
+            StatusCode::BAD_REQUEST,
+            [("code", "400"), ("msg", "old password mismatch")],
+            Json(json!({})),
+        );
+    }
+    let password_hash = match auth::hash_password(&change_password_input_dto.new_password) {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("hash password err: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "hash password err")],
+                Json(json!({})),
+            );
+        }
+    };
+    let mut tbl_auth_user_am = tbl_auth_user.into_active_model();
+    tbl_auth_user_am.password = Set(password_hash);
+    match tbl_auth_user::Entity::update(tbl_auth_user_am)
+        .exec(&app_state.db_conn)
+        .await
+    {
+        Ok(_) => {
+            // 其他设备上的会话全部下线, 保留当前会话
+            let count =
+                session::revoke_user_except(&app_state.sled_db, auth_user.id, &session_id.0);
+            auth::insert_log(
+                &app_state,
+                format!(
+                    "{} change password, revoke {} other sessions",
+                    auth_user.username, count
+                ),
+            )
+            .await;
+            (
+                StatusCode::OK,
+                [("code", "200"), ("msg", "ok")],
+                Json(json!({})),
+            )
+        }
+        Err(e) => {
+            log::error!("update user {} password err: {e}", auth_user.id);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "update db err")],
+                Json(json!({})),
+            )
+        }
+    }
+}
+
+/// 用户设置的时区, 用于按用户的日期计算"今天"和统计, 未知时区按UTC处理
+pub async fn user_time_zone(db_conn: &DatabaseConnection, user_id: i32) -> Result<Tz, DbErr> {
+    let time_zone = tbl_auth_user::Entity::find_by_id(user_id)
+        .one(db_conn)
+        .await?
+        .map(|v| v.time_zone)
+        .unwrap_or_default();
+    match Tz::from_str(&time_zone) {
+        Ok(v) => Ok(v),
+        Err(e) => {
+            log::warn!("user {} time zone {} err: {}", user_id, time_zone, e);
+            Ok(Tz::UTC)
+        }
+    }
+}
+
+async fn my_time_zone(
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<READER>,
+) -> impl IntoResponse {
+    match user_time_zone(&app_state.db_conn, auth_user.id).await {
+        Ok(time_zone) => (
+            StatusCode::OK,
+            [("code", "200"), ("msg", "ok")],
+            Json(json!({
+                "time_zone": time_zone.name()
+            })),
+        ),
+        Err(e) => {
+            log::error!("find user by id {} err: {e}", auth_user.id);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "tbl_auth_user find err")],
+                Json(json!({})),
+            )
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct ChangeTimeZoneInputDto {
+    // IANA时区名, 例如 Asia/Shanghai
+    time_zone: String,
+}
+async fn change_my_time_zone(
+    State(app_state): State<AppState>,
+    RequireRole(auth_user): RequireRole<READER>,
+    Json(change_time_zone_input_dto): Json<ChangeTimeZoneInputDto>,
+) -> impl IntoResponse {
+    let time_zone = match Tz::from_str(&change_time_zone_input_dto.time_zone) {
+        Ok(v) => v,
+        Err(e) => {
+            log::warn!("time zone input invalid: {}", e);
+            return (
+                StatusCode::BAD_REQUEST,
+                [("code", "400"), ("msg", "invalid time zone")],
+                Json(json!({})),
+            );
+        }
+    };
+    let tbl_auth_user_am = tbl_auth_user::ActiveModel {
+        id: Set(auth_user.id),
+        time_zone: Set(time_zone.name().to_string()),
+        ..Default::default()
+    };
+    match tbl_auth_user::Entity::update(tbl_auth_user_am)
+        .exec(&app_state.db_conn)
+        .await
+    {
+        Ok(_) => {
+            auth::insert_log(
+                &app_state,
+                format!("{} change time zone to {}", auth_user.username, time_zone),
+            )
+            .await;
+            (
+                StatusCode::OK,
+                [("code", "200"), ("msg", "ok")],
+                Json(json!({
+                    "time_zone": time_zone.name()
+                })),
+            )
+        }
+        Err(e) => {
+            log::error!("update user {} time zone err: {e}", auth_user.id);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("code", "500"), ("msg", "update db err")],
+                Json(json!({})),
+            )
+        }
+    }
+}
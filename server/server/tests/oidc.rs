@@ -0,0 +1,442 @@
+//! 使用本地模拟的身份提供方测试OIDC登录、回调和票据换取token
+
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+};
+
+use axum::{
+    Form, Json, Router,
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use entity::tbl_auth_user;
+use migration::{Migrator, MigratorTrait};
+use openidconnect::{
+    AccessToken, Audience, AuthUrl, EmptyAdditionalClaims, EmptyAdditionalProviderMetadata,
+    EmptyExtraTokenFields, EndUserUsername, IssuerUrl, JsonWebKeyId, JsonWebKeySetUrl, Nonce,
+    PkceCodeChallenge, PkceCodeVerifier, PrivateSigningKey, ResponseTypes, StandardClaims,
+    SubjectIdentifier, TokenUrl,
+    core::{
+        CoreIdToken, CoreIdTokenClaims, CoreIdTokenFields, CoreJsonWebKeySet,
+        CoreJwsSigningAlgorithm, CoreProviderMetadata, CoreResponseType, CoreRsaPrivateSigningKey,
+        CoreSubjectIdentifierType, CoreTokenResponse, CoreTokenType,
+    },
+    reqwest,
+};
+use sea_orm::{ActiveValue::Set, ColumnTrait, Database, EntityTrait, QueryFilter};
+use server::{AppState, auth::Role, config::Oidc};
+
+const CLIENT_ID: &str = "self_examination";
+const SIGNING_KEY_PEM: &str = include_str!("data/oidc_rsa_key.pem");
+const SIGNING_KEY_ID: &str = "test-key";
+
+// 用户在身份提供方完成授权后, 授权码对应的登录信息
+struct Grant {
+    nonce: String,
+    code_challenge: String,
+    subject: String,
+    preferred_username: String,
+}
+
+struct Issuer {
+    url: String,
+    grants: Mutex<HashMap<String, Grant>>,
+    // discovery被请求的次数, 以及是否模拟discovery失败
+    discovery_count: AtomicUsize,
+    discovery_down: AtomicBool,
+}
+
+fn signing_key() -> CoreRsaPrivateSigningKey {
+    CoreRsaPrivateSigningKey::from_pem(
+        SIGNING_KEY_PEM,
+        Some(JsonWebKeyId::new(SIGNING_KEY_ID.to_string())),
+    )
+    .unwrap()
+}
+
+async fn discovery(State(issuer): State<Arc<Issuer>>) -> Response {
+    issuer.discovery_count.fetch_add(1, Ordering::SeqCst);
+    if issuer.discovery_down.load(Ordering::SeqCst) {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    }
+    let provider_metadata = CoreProviderMetadata::new(
+        IssuerUrl::new(issuer.url.clone()).unwrap(),
+        AuthUrl::new(format!("{}/authorize", issuer.url)).unwrap(),
+        JsonWebKeySetUrl::new(format!("{}/jwks", issuer.url)).unwrap(),
+        vec![ResponseTypes::new(vec![CoreResponseType::Code])],
+        vec![CoreSubjectIdentifierType::Public],
+        vec![CoreJwsSigningAlgorithm::RsaSsaPkcs1V15Sha256],
+        EmptyAdditionalProviderMetadata {},
+    )
+    .set_token_endpoint(Some(
+        TokenUrl::new(format!("{}/token", issuer.url)).unwrap(),
+    ));
+    Json(provider_metadata).into_response()
+}
+
+async fn jwks() -> impl IntoResponse {
+    Json(CoreJsonWebKeySet::new(vec![
+        signing_key().as_verification_key(),
+    ]))
+}
+
+async fn token(
+    State(issuer): State<Arc<Issuer>>,
+    Form(form): Form<HashMap<String, String>>,
+) -> Response {
+    let grant = form
+        .get("code")
+        .and_then(|code| issuer.grants.lock().unwrap().remove(code));
+    let Some(grant) = grant else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": "invalid_grant"})),
+        )
+            .into_response();
+    };
+    let pkce_matched = form.get("code_verifier").is_some_and(|v| {
+        PkceCodeChallenge::from_code_verifier_sha256(&PkceCodeVerifier::new(v.clone())).as_str()
+            == grant.code_challenge
+    });
+    if !pkce_matched || form.get("client_id").map(String::as_str) != Some(CLIENT_ID) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": "invalid_grant"})),
+        )
+            .into_response();
+    }
+    let access_token = AccessToken::new(uuid::Uuid::new_v4().simple().to_string());
+    let now = chrono::Utc::now();
+    let claims = CoreIdTokenClaims::new(
+        IssuerUrl::new(issuer.url.clone()).unwrap(),
+        vec![Audience::new(CLIENT_ID.to_string())],
+        now + chrono::Duration::minutes(5),
+        now,
+        StandardClaims::new(SubjectIdentifier::new(grant.subject))
+            .set_preferred_username(Some(EndUserUsername::new(grant.preferred_username))),
+        EmptyAdditionalClaims {},
+    )
+    .set_nonce(Some(Nonce::new(grant.nonce)));
+    let id_token = CoreIdToken::new(
+        claims,
+        &signing_key(),
+        CoreJwsSigningAlgorithm::RsaSsaPkcs1V15Sha256,
+        Some(&access_token),
+        None,
+    )
+    .unwrap();
+    Json(CoreTokenResponse::new(
+        access_token,
+        CoreTokenType::Bearer,
+        CoreIdTokenFields::new(Some(id_token), EmptyExtraTokenFields {}),
+    ))
+    .into_response()
+}
+
+async fn start_issuer() -> Arc<Issuer> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let issuer = Arc::new(Issuer {
+        url: format!("http://{}", listener.local_addr().unwrap()),
+        grants: Mutex::new(HashMap::new()),
+        discovery_count: AtomicUsize::new(0),
+        discovery_down: AtomicBool::new(false),
+    });
+    let app = Router::new()
+        .route("/.well-known/openid-configuration", get(discovery))
+        .route("/jwks", get(jwks))
+        .route("/token", post(token))
+        .with_state(issuer.clone());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    issuer
+}
+
+struct TestServer {
+    url: String,
+    app_state: AppState,
+}
+
+async fn start_server(issuer: &Issuer) -> TestServer {
+    let db_path = std::env::temp_dir().join(format!(
+        "oidc_test_{}.sqlite",
+        uuid::Uuid::new_v4().simple()
+    ));
+    let db_conn = Database::connect(format!("sqlite://{}?mode=rwc", db_path.to_string_lossy()))
+        .await
+        .unwrap();
+    Migrator::up(&db_conn, None).await.unwrap();
+    let sled_db = sled::Config::new().temporary(true).open().unwrap();
+    server::session::init(&sled_db).unwrap();
+    let app_state = AppState { db_conn, sled_db };
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let oidc = Oidc {
+        issuer_url: issuer.url.clone(),
+        client_id: CLIENT_ID.to_string(),
+        client_secret: None,
+        redirect_url: format!("{url}/api/oidc/callback"),
+        username_claim: "preferred_username".to_string(),
+        auto_provision: true,
+        default_role: Role::Reader,
+    };
+    let app = server::auth::public_router(
+        app_state.clone(),
+        "/api",
+        server::oidc::public_routes_with_config(Some(oidc)),
+    );
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .unwrap()
+    });
+    TestServer { url, app_state }
+}
+
+fn http_client() -> reqwest::Client {
+    reqwest::ClientBuilder::new()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap()
+}
+
+fn location(response: &reqwest::Response) -> String {
+    response.headers()["location"].to_str().unwrap().to_string()
+}
+
+// 浏览器发起登录后得到的授权请求参数和state cookie
+struct LoginStarted {
+    state: String,
+    nonce: String,
+    code_challenge: String,
+    cookie: String,
+}
+
+async fn start_login(server: &TestServer, issuer: &Issuer) -> LoginStarted {
+    let response = http_client()
+        .get(format!("{}/api/oidc/login", server.url))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_redirection());
+    let auth_url = reqwest::Url::parse(&location(&response)).unwrap();
+    assert!(
+        auth_url
+            .as_str()
+            .starts_with(&format!("{}/authorize", issuer.url))
+    );
+    let params: HashMap<String, String> = auth_url.query_pairs().into_owned().collect();
+    assert_eq!(params["code_challenge_method"], "S256");
+    let set_cookie = response.headers()["set-cookie"].to_str().unwrap();
+    assert!(set_cookie.contains("HttpOnly"));
+    assert!(set_cookie.contains("SameSite=Lax"));
+    LoginStarted {
+        state: params["state"].clone(),
+        nonce: params["nonce"].clone(),
+        code_challenge: params["code_challenge"].clone(),
+        cookie: set_cookie.split(';').next().unwrap().to_string(),
+    }
+}
+
+/// 模拟用户在身份提供方登录并同意授权, 返回授权码
+fn authorize(
+    issuer: &Issuer,
+    login: &LoginStarted,
+    subject: &str,
+    preferred_username: &str,
+) -> String {
+    let code = uuid::Uuid::new_v4().simple().to_string();
+    issuer.grants.lock().unwrap().insert(
+        code.clone(),
+        Grant {
+            nonce: login.nonce.clone(),
+            code_challenge: login.code_challenge.clone(),
+            subject: subject.to_string(),
+            preferred_username: preferred_username.to_string(),
+        },
+    );
+    code
+}
+
+async fn callback(server: &TestServer, code: &str, state: &str, cookie: Option<&str>) -> String {
+    let mut request = http_client()
+        .get(format!("{}/api/oidc/callback", server.url))
+        .query(&[("code", code), ("state", state)]);
+    if let Some(cookie) = cookie {
+        request = request.header("cookie", cookie);
+    }
+    let response = request.send().await.unwrap();
+    assert!(response.status().is_redirection());
+    location(&response)
+}
+
+async fn exchange_ticket(server: &TestServer, ticket: &str) -> (StatusCode, serde_json::Value) {
+    let response = http_client()
+        .post(format!("{}/api/oidc/token", server.url))
+        .header("content-type", "application/json")
+        .body(serde_json::json!({ "ticket": ticket }).to_string())
+        .send()
+        .await
+        .unwrap();
+    let status = StatusCode::from_u16(response.status().as_u16()).unwrap();
+    (
+        status,
+        serde_json::from_slice(&response.bytes().await.unwrap()).unwrap(),
+    )
+}
+
+async fn find_user(server: &TestServer, username: &str) -> Option<tbl_auth_user::Model> {
+    tbl_auth_user::Entity::find()
+        .filter(tbl_auth_user::Column::Username.eq(username))
+        .one(&server.app_state.db_conn)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn login_callback_and_ticket_exchange() {
+    let issuer = start_issuer().await;
+    let server = start_server(&issuer).await;
+
+    let login = start_login(&server, &issuer).await;
+    let code = authorize(&issuer, &login, "sub-alice", "alice");
+    let location = callback(&server, &code, &login.state, Some(&login.cookie)).await;
+    let ticket = location
+        .strip_prefix("/login?oidc_ticket=")
+        .unwrap_or_else(|| panic!("unexpected redirect: {location}"));
+
+    let (status, body) = exchange_ticket(&server, ticket).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["token"].as_str().is_some_and(|v| !v.is_empty()));
+    assert!(body["refresh_token"].as_str().is_some());
+    // 票据只能使用一次
+    let (status, _) = exchange_ticket(&server, ticket).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let alice = find_user(&server, "alice").await.unwrap();
+    assert_eq!(alice.oidc_subject.as_deref(), Some("sub-alice"));
+    assert_eq!(alice.role, Role::Reader.as_str());
+
+    // 同一sub再次登录时, 即使用户名改变也对应同一用户
+    let login = start_login(&server, &issuer).await;
+    let code = authorize(&issuer, &login, "sub-alice", "alice-renamed");
+    let location = callback(&server, &code, &login.state, Some(&login.cookie)).await;
+    assert!(location.starts_with("/login?oidc_ticket="), "{location}");
+    assert!(find_user(&server, "alice-renamed").await.is_none());
+}
+
+#[tokio::test]
+async fn callback_requires_state_cookie_of_same_browser() {
+    let issuer = start_issuer().await;
+    let server = start_server(&issuer).await;
+
+    // 攻击者用自己的授权码和state构造回调地址发给受害者
+    let attacker = start_login(&server, &issuer).await;
+    let code = authorize(&issuer, &attacker, "sub-mallory", "mallory");
+    let location = callback(&server, &code, &attacker.state, None).await;
+    assert_eq!(location, "/login?oidc_error=state");
+
+    let victim = start_login(&server, &issuer).await;
+    let location = callback(&server, &code, &attacker.state, Some(&victim.cookie)).await;
+    assert_eq!(location, "/login?oidc_error=state");
+    assert!(find_user(&server, "mallory").await.is_none());
+
+    // state只能使用一次, 重放同一回调地址会失败
+    let location = callback(&server, &code, &attacker.state, Some(&attacker.cookie)).await;
+    assert!(location.starts_with("/login?oidc_ticket="), "{location}");
+    let location = callback(&server, &code, &attacker.state, Some(&attacker.cookie)).await;
+    assert_eq!(location, "/login?oidc_error=state");
+}
+
+#[tokio::test]
+async fn existing_local_user_is_not_taken_over() {
+    let issuer = start_issuer().await;
+    let server = start_server(&issuer).await;
+    let admin = tbl_auth_user::Entity::insert(tbl_auth_user::ActiveModel {
+        username: Set("admin".to_string()),
+        password: Set("not used".to_string()),
+        role: Set(Role::Admin.as_str().to_string()),
+        ..Default::default()
+    })
+    .exec_with_returning(&server.app_state.db_conn)
+    .await
+    .unwrap();
+
+    // 身份提供方中可自选的用户名与本地管理员相同
+    let login = start_login(&server, &issuer).await;
+    let code = authorize(&issuer, &login, "sub-mallory", "admin");
+    let location = callback(&server, &code, &login.state, Some(&login.cookie)).await;
+    assert_eq!(location, "/login?oidc_error=conflict");
+    let admin_now = find_user(&server, "admin").await.unwrap();
+    assert_eq!(admin_now.oidc_subject, None);
+
+    // 管理员显式关联sub后才能通过OIDC登录该用户
+    tbl_auth_user::Entity::update(tbl_auth_user::ActiveModel {
+        id: Set(admin.id),
+        oidc_subject: Set(Some("sub-admin".to_string())),
+        ..Default::default()
+    })
+    .exec(&server.app_state.db_conn)
+    .await
+    .unwrap();
+    let login = start_login(&server, &issuer).await;
+    let code = authorize(&issuer, &login, "sub-admin", "someone-else");
+    let location = callback(&server, &code, &login.state, Some(&login.cookie)).await;
+    let ticket = location
+        .strip_prefix("/login?oidc_ticket=")
+        .unwrap_or_else(|| panic!("unexpected redirect: {location}"));
+    let (status, _) = exchange_ticket(&server, ticket).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn discovery_is_cached_and_retried_after_error() {
+    let issuer = start_issuer().await;
+    let server = start_server(&issuer).await;
+    issuer.discovery_down.store(true, Ordering::SeqCst);
+    let response = http_client()
+        .get(format!("{}/api/oidc/login", server.url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(location(&response), "/login?oidc_error=discover");
+    assert_eq!(issuer.discovery_count.load(Ordering::SeqCst), 1);
+
+    // 失败的结果不缓存, 恢复后重新获取, 之后的登录和回调都使用缓存
+    issuer.discovery_down.store(false, Ordering::SeqCst);
+    let login = start_login(&server, &issuer).await;
+    start_login(&server, &issuer).await;
+    let code = authorize(&issuer, &login, "sub-alice", "alice");
+    let location = callback(&server, &code, &login.state, Some(&login.cookie)).await;
+    assert!(location.starts_with("/login?oidc_ticket="), "{location}");
+    assert_eq!(issuer.discovery_count.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn login_is_throttled_per_ip() {
+    let issuer = start_issuer().await;
+    let server = start_server(&issuer).await;
+    let mut locked = false;
+    for _ in 0..100 {
+        let response = http_client()
+            .get(format!("{}/api/oidc/login", server.url))
+            .send()
+            .await
+            .unwrap();
+        if location(&response) == "/login?oidc_error=locked" {
+            assert!(response.headers().get("set-cookie").is_none());
+            locked = true;
+            break;
+        }
+    }
+    assert!(locked);
+}
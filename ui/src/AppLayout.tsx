@@ -64,15 +64,12 @@ const App: React.FC = () => {
               const token = localStorage.getItem("token");
               if (token) {
                 try {
-                  await restful_api.post(`/api/logout/${token}`, null, {
-                    headers: {
-                      Authorization: `Bearer ${token}`,
-                    },
-                  });
+                  await restful_api.post("/api/logout");
                 } catch (error) {
                   console.error("Logout failed", error);
                 }
                 localStorage.removeItem("token");
+                localStorage.removeItem("refresh_token");
                 window.location.reload();
               }
             }}
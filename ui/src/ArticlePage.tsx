@@ -17,7 +17,7 @@ type UpdateField = {
 type Article = {
   id: number;
   title: string;
-  content: string;
+  preview: string;
 };
 
 type Page = {
@@ -98,9 +98,20 @@ const App: React.FC = () => {
       setLoading(false);
     }
   };
-  const handleUpdate = (record: Article) => {
-    form.setFieldsValue(record);
-    setUpdateOpen(true);
+  const handleUpdate = async (record: Article) => {
+    // 列表中只有摘要, 编辑前取完整正文
+    try {
+      const response = await restful_api.get(`/api/articles/${record.id}`);
+      form.setFieldsValue({
+        id: response.data.id,
+        title: response.data.title,
+        content: response.data.content,
+      });
+      setUpdateOpen(true);
+    } catch (e) {
+      console.error("get article error: ", e);
+      message.error("get article error");
+    }
   };
   const handleDelete = async (id: number) => {
     try {
@@ -125,8 +136,8 @@ const App: React.FC = () => {
     },
     {
       title: "内容",
-      dataIndex: "content",
-      key: "content",
+      dataIndex: "preview",
+      key: "preview",
     },
     {
       title: "创建时间",
@@ -1,68 +1,147 @@
-import React from "react";
+import React, { useEffect, useState } from "react";
 import type { FormProps } from "antd";
-import { Button, Form, Input, message } from "antd";
+import { Button, Checkbox, Form, Input, message } from "antd";
 import axios from "axios";
 
 type FieldType = {
   username?: string;
   password?: string;
-  remember?: string;
+  remember?: boolean;
+  code?: string;
 };
 
-const onFinish: FormProps<FieldType>["onFinish"] = async (values) => {
-  console.log("Success:", values);
-  try {
-    const response = await axios.post("/api/login", {
-      username: values.username,
-      password: values.password,
-    });
-
-    const { token } = response.data;
-    console.log("token: ", token);
-    localStorage.setItem("token", token);
-    message.success("Login successful!");
-    window.location.href = "/";
-  } catch (error: unknown) {
-    console.error("Login failed:", error);
-  }
+const saveToken = (data: { token: string; refresh_token: string }) => {
+  localStorage.setItem("token", data.token);
+  localStorage.setItem("refresh_token", data.refresh_token);
+  message.success("Login successful!");
+  window.location.href = "/";
 };
 
 const onFinishFailed: FormProps<FieldType>["onFinishFailed"] = (errorInfo) => {
   console.log("Failed:", errorInfo);
 };
 
-const App: React.FC = () => (
-  <Form
-    name="basic"
-    labelCol={{ span: 8 }}
-    wrapperCol={{ span: 16 }}
-    style={{ maxWidth: 600 }}
-    onFinish={onFinish}
-    onFinishFailed={onFinishFailed}
-    autoComplete="off"
-  >
-    <Form.Item<FieldType>
-      label="Username"
-      name="username"
-      rules={[{ required: true, message: "Please input your username!" }]}
-    >
-      <Input />
-    </Form.Item>
+const App: React.FC = () => {
+  const [setupRequired, setSetupRequired] = useState(false);
+  const [challenge, setChallenge] = useState<string | null>(null);
+  const [oidcEnabled, setOidcEnabled] = useState(false);
+
+  useEffect(() => {
+    axios
+      .get("/api/setup")
+      .then((response) => setSetupRequired(response.data.setup_required))
+      .catch((error) => console.error("Get setup status failed:", error));
+    axios
+      .get("/api/oidc/config")
+      .then((response) => setOidcEnabled(response.data.enabled))
+      .catch((error) => console.error("Get oidc config failed:", error));
+    // OIDC回调后携带一次性票据返回登录页
+    const params = new URLSearchParams(window.location.search);
+    const ticket = params.get("oidc_ticket");
+    if (ticket) {
+      axios
+        .post("/api/oidc/token", { ticket })
+        .then((response) => saveToken(response.data))
+        .catch((error) => console.error("OIDC login failed:", error));
+    } else if (params.get("oidc_error")) {
+      message.error(`SSO login failed: ${params.get("oidc_error")}`);
+    }
+  }, []);
+
+  const onFinish: FormProps<FieldType>["onFinish"] = async (values) => {
+    console.log("Success:", values);
+    try {
+      if (challenge) {
+        // 两步验证: 验证码或恢复码
+        const code = values.code?.trim() ?? "";
+        const response = await axios.post("/api/login/totp", {
+          challenge,
+          ...(code.includes("-") ? { recovery_code: code } : { code }),
+        });
+        saveToken(response.data);
+        return;
+      }
+      // 首次启动时用户表为空, 提交的账号将被创建为管理员
+      const url = setupRequired ? "/api/setup" : "/api/login";
+      const response = await axios.post(url, {
+        username: values.username,
+        password: values.password,
+        remember_me: !!values.remember,
+      });
+      if (response.data.totp_required) {
+        setChallenge(response.data.challenge);
+        return;
+      }
+      saveToken(response.data);
+    } catch (error: unknown) {
+      console.error("Login failed:", error);
+      setChallenge(null);
+    }
+  };
 
-    <Form.Item<FieldType>
-      label="Password"
-      name="password"
-      rules={[{ required: true, message: "Please input your password!" }]}
+  return (
+    <Form
+      name="basic"
+      labelCol={{ span: 8 }}
+      wrapperCol={{ span: 16 }}
+      style={{ maxWidth: 600 }}
+      onFinish={onFinish}
+      onFinishFailed={onFinishFailed}
+      autoComplete="off"
     >
-      <Input.Password />
-    </Form.Item>
+      <Form.Item<FieldType>
+        label="Username"
+        name="username"
+        rules={[{ required: true, message: "Please input your username!" }]}
+      >
+        <Input />
+      </Form.Item>
 
-    <Form.Item label={null}>
-      <Button type="primary" htmlType="submit">
-        Submit
-      </Button>
-    </Form.Item>
-  </Form>
-);
+      <Form.Item<FieldType>
+        label="Password"
+        name="password"
+        rules={[{ required: true, message: "Please input your password!" }]}
+      >
+        <Input.Password />
+      </Form.Item>
+
+      {!setupRequired && (
+        <Form.Item<FieldType>
+          name="remember"
+          valuePropName="checked"
+          label={null}
+        >
+          <Checkbox>Remember me</Checkbox>
+        </Form.Item>
+      )}
+
+      {challenge && (
+        <Form.Item<FieldType>
+          label="Code"
+          name="code"
+          rules={[
+            { required: true, message: "Please input your TOTP code!" },
+          ]}
+        >
+          <Input autoFocus />
+        </Form.Item>
+      )}
+
+      <Form.Item label={null}>
+        <Button type="primary" htmlType="submit">
+          {setupRequired ? "Create Admin" : "Submit"}
+        </Button>
+        {oidcEnabled && !setupRequired && (
+          <Button
+            style={{ marginLeft: 8 }}
+            onClick={() => (window.location.href = "/api/oidc/login")}
+          >
+            Sign in with SSO
+          </Button>
+        )}
+      </Form.Item>
+    </Form>
+  );
+};
 
 export default App;
@@ -15,12 +15,38 @@ restful_api.interceptors.request.use(
   (error) => Promise.reject(error)
 );
 
+// access token有效期较短, 过期后使用refresh token换取新token并重试一次
+let refreshing: Promise<string> | null = null;
+
+const refreshToken = async (): Promise<string> => {
+  const refresh_token = localStorage.getItem("refresh_token");
+  if (!refresh_token) {
+    throw new Error("no refresh token");
+  }
+  const response = await axios.post("/api/refresh", { refresh_token });
+  localStorage.setItem("token", response.data.token);
+  localStorage.setItem("refresh_token", response.data.refresh_token);
+  return response.data.token;
+};
+
 restful_api.interceptors.response.use(
   (response) => response,
-  (error) => {
-    if (error.response?.status === 401) {
-      localStorage.removeItem("token");
-      window.location.reload();
+  async (error) => {
+    const config = error.config;
+    if (error.response?.status === 401 && config && !config._retry) {
+      config._retry = true;
+      try {
+        refreshing = refreshing ?? refreshToken();
+        const token = await refreshing;
+        config.headers.Authorization = `Bearer ${token}`;
+        return restful_api(config);
+      } catch {
+        localStorage.removeItem("token");
+        localStorage.removeItem("refresh_token");
+        window.location.reload();
+      } finally {
+        refreshing = null;
+      }
     }
     return Promise.reject(error);
   }